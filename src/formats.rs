@@ -1,10 +1,132 @@
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
+use crate::ukf::{KeySystem, MissingKey};
 
 pub struct Format {
     pub name: &'static str,
     pub detector_func: fn(&AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>>,
     pub extractor_func: fn(&AppContext, Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>>,
+    pub keys: &'static [KeySlot],           //keys the format reads from the key files
+    pub options: &'static [FormatOption],   //-o options it understands, besides the global ones
+}
+
+impl Format {
+    //required keys that are not loaded, checked before extraction so it does not fail halfway
+    pub fn missing_keys(&self, keys: &KeySystem) -> Vec<&KeySlot> {
+        self.keys.iter().filter(|slot| slot.required && !slot.is_loaded(keys)).collect()
+    }
+
+    //a missing key error gets what the format needs it for, other errors are returned as they are
+    pub fn explain_error(&self, e: Box<dyn std::error::Error>) -> Box<dyn std::error::Error> {
+        let Some(missing) = e.downcast_ref::<MissingKey>() else {return e};
+        match self.keys.iter().find(|slot| slot.name == missing.name && slot.is_collection == missing.is_collection) {
+            Some(slot) => format!("{} needs {}, which is not in the loaded keys!", self.name, slot.describe()).into(),
+            None => e,
+        }
+    }
+}
+
+//an -o option, given as "name" for a flag or "name=value"
+pub struct FormatOption {
+    pub name: &'static str,
+    pub value: Option<&'static str>,    //placeholder shown in the help, None for a flag
+    pub description: &'static str,
+}
+impl FormatOption {
+    const fn flag(name: &'static str, description: &'static str) -> Self {
+        FormatOption {name, value: None, description}
+    }
+    const fn value(name: &'static str, placeholder: &'static str, description: &'static str) -> Self {
+        FormatOption {name, value: Some(placeholder), description}
+    }
+
+    //like "mstar:keep_unknown" or "format=<name>"
+    pub fn usage(&self) -> String {
+        match self.value {
+            Some(placeholder) => format!("{}={}", self.name, placeholder),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+//options every format understands
+pub static GLOBAL_OPTIONS: &[FormatOption] = &[
+    FormatOption::flag("dump_dec_hdrs", "For formats with an encrypted header - dump the decrypted header(s)."),
+    FormatOption::flag("key_hints", "Try the keys that matched similar files before first, and save the key that matches this one."),
+    FormatOption::value("format", "<name>", "Only try to detect this format."),
+    FormatOption::value("key", "<entry name>", "Only try this collection entry, instead of searching or picking one by the file."),
+];
+
+//options used by more than one format
+const MSTAR_KEEP_UNKNOWN: FormatOption = FormatOption::flag("mstar:keep_unknown", "Save data with unknown destination.");
+const MSTAR_OFFSET: FormatOption = FormatOption::value("mstar:offset", "<n>", "Offset of the MStar script, instead of trying 0x0 and 0x1000.");
+const MSTAR_SECURE_OLD_KEEP_DECRYPTED: FormatOption = FormatOption::flag("mstar_secure_old:keep_decrypted", "Keep decrypted file (it will be deleted by default).");
+const NVT_TIMG_PKG_VARIANT: FormatOption = FormatOption::value("nvt_timg_pkg:variant", "<v1|v2>", "Key of the encrypted images, v1 by default as the one used can not be detected.");
+const NVT_TIMG_PKG_USE_V2_KEY: FormatOption = FormatOption::flag("nvt_timg_pkg:use_v2_key", "Deprecated, same as nvt_timg_pkg:variant=v2.");
+const MSD_PRINT_OUITH: FormatOption = FormatOption::flag("msd:print_ouith", "Prints the entire parsed OUITH header.");
+
+//a key or collection a format uses, with the allowed lengths of each part. an empty list means any length
+pub struct KeySlot {
+    pub name: &'static str,
+    pub is_collection: bool,
+    pub parts: &'static [&'static [usize]],
+    pub required: bool,         //false if only some files or options need it
+    pub used_for: &'static str,
+}
+impl KeySlot {
+    const fn key(name: &'static str, parts: &'static [&'static [usize]]) -> Self {
+        KeySlot {name, is_collection: false, parts, required: true, used_for: ""}
+    }
+    const fn collection(name: &'static str, parts: &'static [&'static [usize]]) -> Self {
+        KeySlot {name, is_collection: true, parts, required: true, used_for: ""}
+    }
+    const fn note(self, used_for: &'static str) -> Self {
+        KeySlot {used_for, ..self}
+    }
+    const fn optional(self, used_for: &'static str) -> Self {
+        KeySlot {required: false, used_for, ..self}
+    }
+
+    pub fn is_loaded(&self, keys: &KeySystem) -> bool {
+        if self.is_collection {keys.get_collection(self.name).is_ok()} else {keys.get_key(self.name).is_ok()}
+    }
+
+    pub fn kind(&self) -> &'static str {
+        if self.is_collection {"collection"} else {"key"}
+    }
+
+    //like "2 part(s) of 16, 16 bytes"
+    pub fn shape(&self) -> String {
+        let lengths: Vec<String> = self.parts.iter().map(|lengths| {
+            if lengths.is_empty() {"any".to_string()} else {lengths.iter().map(|l| l.to_string()).collect::<Vec<_>>().join("|")}
+        }).collect();
+        format!("{} part(s) of {} bytes", self.parts.len(), lengths.join(", "))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = format!("{} {} - {}, {}", self.kind(), self.name, if self.required {"required"} else {"optional"}, self.shape());
+        if !self.used_for.is_empty() {
+            text.push_str(&format!(" ({})", self.used_for));
+        }
+        text
+    }
+}
+
+type KeySearchFunc = fn(&AppContext) -> Result<Option<String>, Box<dyn std::error::Error>>;
+type KeyHuntFunc = fn(&AppContext) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>>;
+
+//formats that pick their key from several candidates, the search returns the name of the key that matched
+pub struct KeySearch {
+    pub name: &'static str,
+    pub search_func: KeySearchFunc,
+}
+
+//formats that can test keyhunt candidates against the known plaintext of a package
+pub struct KeyHunt {
+    pub name: &'static str,
+    pub has_magic: bool,        //false if the target is built from any file, as the format can only be recognized with the key
+    pub hunt_func: KeyHuntFunc,
 }
 
 pub mod mstar;
@@ -63,6 +185,14 @@ pub mod mtk_pkg_old;
 pub mod mtk_pkg_new;
 pub mod mtk_bdp;
 
+pub mod uimage;
+pub mod android_boot;
+pub mod dtb;
+pub mod ext4;
+pub mod jffs2;
+pub mod cramfs;
+pub mod yaffs2;
+
 //define all formats here
 pub fn get_registry() -> Vec<Format> {
     return vec![
@@ -70,251 +200,489 @@ pub fn get_registry() -> Vec<Format> {
             name: "mstar",
             detector_func: crate::formats::mstar::is_mstar_file,
             extractor_func: crate::formats::mstar::extract_mstar,
+            keys: &[],
+            options: &[MSTAR_KEEP_UNKNOWN, MSTAR_OFFSET],
         },
         Format {
             name: "samsung_old",
             detector_func: crate::formats::samsung_old::is_samsung_old_dir,
             extractor_func: crate::formats::samsung_old::extract_samsung_old,
+            keys: const {&[KeySlot::collection("SAMSUNG_OLD", &[&[]]).note("picked by the firmware info prefix")]},
+            options: &[],
         },
         Format {
             name: "nvt_timg",
             detector_func: crate::formats::nvt_timg::is_nvt_timg_file,
             extractor_func: crate::formats::nvt_timg::extract_nvt_timg,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "nvt_timg_pkg",
             detector_func: crate::formats::nvt_timg_pkg::is_nvt_timg_pkg_file,
             extractor_func: crate::formats::nvt_timg_pkg::extract_nvt_timg_pkg,
+            keys: const {&[
+                KeySlot::key("NVT_FW40_IMG_ENC_KEY", &[&[16]]).optional("encrypted images"),
+                KeySlot::key("NVT_FW40_IMG_ENC_IV", &[&[16]]).optional("encrypted images"),
+                KeySlot::key("NVT_FW40_IMG_ENC_V2_KEY", &[&[32]]).optional("encrypted images with nvt_timg_pkg:variant=v2"),
+            ]},
+            options: &[NVT_TIMG_PKG_VARIANT, NVT_TIMG_PKG_USE_V2_KEY],
         },
         Format {
             name: "pfl_upg",
             detector_func: crate::formats::pfl_upg::is_pfl_upg_file,
             extractor_func: crate::formats::pfl_upg::extract_pfl_upg,
+            keys: const {&[KeySlot::collection("PFL_UPG", &[&[]]).optional("encrypted files, RSA public key modulus")]},
+            options: const {&[FormatOption::flag("pfl_upg:no_extract_inner_upg", "Do not automatically extract inner UPGs. (Warning: this can cause file collisions sometimes!)")]},
         },
         Format {
             name: "sddl_sec",
             detector_func: crate::formats::sddl_sec::is_sddl_sec_file,
             extractor_func: crate::formats::sddl_sec::extract_sddl_sec,
+            keys: const {&[
+                KeySlot::key("SDDL_SEC_NEW_KEY", &[&[16], &[16]]).note("2011+ files, always tried first"),
+                KeySlot::collection("SDDL_SEC_OLD_KEYS_AES", &[&[16], &[16]]).optional("pre-2011 files"),
+                KeySlot::collection("SDDL_SEC_OLD_KEYS_DES", &[&[24], &[8]]).optional("pre-2011 files"),
+            ]},
+            options: const {&[
+                FormatOption::flag("sddl_sec:save_extra", "Save SDIT.FDI and .TXT files that are not extracted by default."),
+                FormatOption::flag("sddl_sec:split_peaks", "Split PEAKS module into partitions (only on older files). This will also automatically decompress compressed partitions."),
+                FormatOption::flag("sddl_sec:no_decomp_peaks", "Do not automatically decompress partitions when splitting PEAKS with above option."),
+            ]},
         },
         Format {
             name: "sdboot",
             detector_func: crate::formats::sdboot::is_sdboot_file,
             extractor_func: crate::formats::sdboot::extract_sdboot,
+            keys: const {&[KeySlot::collection("SDBOOT", &[&[16], &[16]]).note("entry named after the key id of the file")]},
+            options: &[],
         },
         Format {
             name: "sdimage",
             detector_func: crate::formats::sdimage::is_sdimage_file,
             extractor_func: crate::formats::sdimage::extract_sdimage,
+            keys: const {&[KeySlot::collection("SDIMAGE", &[&[16, 24], &[16, 8]]).optional("encrypted entries, saved encrypted without it")]},
+            options: &[],
         },
         Format {
             name: "novatek",
             detector_func: crate::formats::novatek::is_novatek_file,
             extractor_func: crate::formats::novatek::extract_novatek,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "ruf",
             detector_func: crate::formats::ruf::is_ruf_file,
             extractor_func: crate::formats::ruf::extract_ruf,
+            keys: const {&[KeySlot::collection("RUF", &[&[16]]).note("picked by the model name prefix")]},
+            options: &[],
         },
         Format {
             name: "invincible_image",
             detector_func: crate::formats::invincible_image::is_invincible_image_file,
             extractor_func: crate::formats::invincible_image::extract_invincible_image,
+            keys: const {&[
+                KeySlot::key("INVINCIBLE_IMAGE_V1", &[&[16], &[16]]).optional("key ID 1 files"),
+                KeySlot::key("INVINCIBLE_IMAGE_V2", &[&[16], &[16]]).optional("version 2 files"),
+                KeySlot::key("INVINCIBLE_IMAGE_V3", &[&[16], &[16]]).optional("version 3 files"),
+                KeySlot::key("INVINCIBLE_IMAGE_PUBKEY", &[&[]]).optional("checking chunk signatures"),
+            ]},
+            options: &[],
         },
         Format {
             name: "slp",
             detector_func: crate::formats::slp::is_slp_file,
             extractor_func: crate::formats::slp::extract_slp,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "roku",
             detector_func: crate::formats::roku::is_roku_file,
             extractor_func: crate::formats::roku::extract_roku,
+            keys: const {&[
+                KeySlot::key("ROKU_FILE_KEY", &[&[16]]),
+                KeySlot::collection("ROKU_IMAGE", &[&[16]]).optional("encrypted images, entry named after the platform id"),
+                KeySlot::key("ROKU_MANIFEST_PUBKEY", &[&[256]]).optional("manifest signature check, RSA public key modulus"),
+            ]},
+            options: &[],
         },
         Format {
             name: "sony_bdp",
             detector_func: crate::formats::sony_bdp::is_sony_bdp_file,
             extractor_func: crate::formats::sony_bdp::extract_sony_bdp,
+            keys: const {&[
+                KeySlot::key("SONY_BDP_HEX_SUBST", &[&[256]]).note("old encryption, always tried first"),
+                KeySlot::collection("SONY_BDP_AES", &[&[16], &[16]]).optional("new encryption"),
+            ]},
+            options: &[],
         },
         Format {
             name: "rvp",
             detector_func: crate::formats::rvp::is_rvp_file,
             extractor_func: crate::formats::rvp::extract_rvp,
+            keys: const {&[
+                KeySlot::key("RVP_XOR_KEY", &[&[32]]).optional("old files"),
+                KeySlot::collection("RVP_AES", &[&[16], &[16]]).optional("newer files"),
+            ]},
+            options: &[],
         },
         Format {
             name: "funai_upg",
             detector_func: crate::formats::funai_upg::is_funai_upg_file,
             extractor_func: crate::formats::funai_upg::extract_funai_upg,
+            keys: const {&[KeySlot::collection("FUNAI_UPG", &[&[4]]).optional("encrypted entries")]},
+            options: &[],
         },
         Format {
             name: "funai_upg_phl",
             detector_func: crate::formats::funai_upg_phl::is_funai_upg_phl_file,
             extractor_func: crate::formats::funai_upg_phl::extract_funai_upg_phl,
+            keys: const {&[KeySlot::collection("FUNAI_UPG", &[&[4]]).optional("encrypted entries")]},
+            options: &[],
         },
         Format {
             name: "funai_bdp",
             detector_func: crate::formats::funai_bdp::is_funai_bdp_file,
             extractor_func: crate::formats::funai_bdp::extract_funai_bdp,
+            keys: const {&[KeySlot::collection("FUNAI_BDP", &[&[4]])]},
+            options: &[],
         },
         Format {
             name: "funai_mstar",                                               // ORDER: needs to be placed BELOW mstar_secure_old
             detector_func: crate::formats::funai_mstar::is_funai_mstar_file,   //because, it can end with mstar_secure_old payload, but because it is not aligned to the start of the file, extraction will fail
             extractor_func: crate::formats::funai_mstar::extract_funai_mstar,
+            keys: const {&[KeySlot::key("MSTAR_DEFAULT_UPGRADE_KEY", &[&[16]]).optional("inner mstar_secure_old payload")]},
+            options: &[MSTAR_SECURE_OLD_KEEP_DECRYPTED, MSTAR_KEEP_UNKNOWN, MSTAR_OFFSET],
         },
         Format {
             name: "pana_dvd",
             detector_func: crate::formats::pana_dvd::is_pana_dvd_file,
             extractor_func: crate::formats::pana_dvd::extract_pana_dvd,
+            keys: const {&[
+                KeySlot::collection("PANA_DVD_KEYONLY", &[&[8]]),
+                KeySlot::collection("PANA_DVD_AESPAIR", &[&[16], &[16], &[8]]).optional("AES encrypted files"),
+            ]},
+            options: const {&[FormatOption::flag("pana_dvd:split_main", "Automatically split the MAIN module into seperate partitions.")]},
         },
         Format {
             name: "android_ota_payload",
             detector_func: crate::formats::android_ota_payload::is_android_ota_payload_file,
             extractor_func: crate::formats::android_ota_payload::extract_android_ota_payload,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "bdl",
             detector_func: crate::formats::bdl::is_bdl_file,
             extractor_func: crate::formats::bdl::extract_bdl,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "amlogic",
             detector_func: crate::formats::amlogic::is_amlogic_file,
             extractor_func: crate::formats::amlogic::extract_amlogic,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "pup",
             detector_func: crate::formats::pup::is_pup_file,
             extractor_func: crate::formats::pup::extract_pup,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "msd10",
             detector_func: crate::formats::msd10::is_msd10_file,
             extractor_func: crate::formats::msd10::extract_msd10,
+            keys: const {&[KeySlot::collection("MSD10", &[&[16, 20]])]},
+            options: const {&[MSD_PRINT_OUITH, FormatOption::flag("msd10:save_cmac", "Save CMAC data for files that is skipped by default.")]},
         },
         Format {
             name: "msd11",
             detector_func: crate::formats::msd11::is_msd11_file,
             extractor_func: crate::formats::msd11::extract_msd11,
+            keys: const {&[KeySlot::collection("MSD11", &[&[16, 32]])]},
+            options: &[MSD_PRINT_OUITH],
         },
         Format {
             name: "bem",
             detector_func: crate::formats::bem::is_bem_file,
             extractor_func: crate::formats::bem::extract_bem,
+            keys: const {&[KeySlot::collection("MSD11", &[&[16, 32]])]},
+            options: &[],
         },
         Format {
             name: "epk",
             detector_func: crate::formats::epk::is_epk_file,
             extractor_func: crate::formats::epk::extract_epk,
+            keys: const {&[KeySlot::collection("EPK", &[&[16, 32]]).optional("encrypted headers")]},
+            options: &[],
         },
         Format {
             name: "epk1",
             detector_func: crate::formats::epk1::is_epk1_file,
             extractor_func: crate::formats::epk1::extract_epk1,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "epk2",
             detector_func: crate::formats::epk2::is_epk2_file,
             extractor_func: crate::formats::epk2::extract_epk2,
+            keys: const {&[KeySlot::collection("EPK", &[&[16, 32]]).optional("encrypted headers")]},
+            options: &[],
         },
         Format {
             name: "epk2b",
             detector_func: crate::formats::epk2b::is_epk2b_file,
             extractor_func: crate::formats::epk2b::extract_epk2b,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "epk3",
             detector_func: crate::formats::epk3::is_epk3_file,
             extractor_func: crate::formats::epk3::extract_epk3,
+            keys: const {&[KeySlot::collection("EPK", &[&[16, 32]])]},
+            options: &[],
         },
         Format {
             name: "mtk_pkg",
             detector_func: crate::formats::mtk_pkg::is_mtk_pkg_file,
             extractor_func: crate::formats::mtk_pkg::extract_mtk_pkg,
+            keys: const {&[
+                KeySlot::key("MTK_PKG_HEADER_KEY_AES", &[&[16]]),
+                KeySlot::collection("MTK_PKG_CUSTOM_KEYS", &[&[16], &[16]]).optional("parts encrypted with a vendor key"),
+            ]},
+            options: &[],
         },
         Format {
             name: "mtk_pkg_old",
             detector_func: crate::formats::mtk_pkg_old::is_mtk_pkg_old_file,
             extractor_func: crate::formats::mtk_pkg_old::extract_mtk_pkg_old,
+            keys: const {&[
+                KeySlot::key("MTK_PKG_OLD_HEADER_KEY", &[&[4], &[4]]),
+                KeySlot::key("MTK_PKG_OLD_DATA_IV", &[&[4]]).optional("encrypted parts"),
+            ]},
+            options: &[],
         },
         Format {
             name: "mtk_pkg_new",
             detector_func: crate::formats::mtk_pkg_new::is_mtk_pkg_new_file,
             extractor_func: crate::formats::mtk_pkg_new::extract_mtk_pkg_new,
+            keys: const {&[KeySlot::collection("MTK_PKG_CUSTOM_KEYS", &[&[16], &[16]])]},
+            options: &[],
         },
         Format {
             name: "philips_bdp",
             detector_func: crate::formats::philips_bdp::is_philips_bdp_file,        //ORDER: needs to be placed below mtk_bdp
             extractor_func: crate::formats::philips_bdp::extract_philips_bdp,       //because, it can end with mtk_bdp payload, but because it is not aligned to the start of the file, extraction will fail
+            keys: const {&[KeySlot::key("PHILIPS_BDP_KEY_1", &[&[32], &[16]]).optional("philips_bdp:decrypt")]},
+            options: const {&[
+                FormatOption::flag("philips_bdp:decrypt", "Decrypt main partition."),
+                FormatOption::value("philips_bdp:variant", "<old|new>", "Header type, instead of guessing it from the name."),
+            ]},
         },
         Format {
             name: "mtk_bdp",
             detector_func: crate::formats::mtk_bdp::is_mtk_bdp_file,
             extractor_func: crate::formats::mtk_bdp::extract_mtk_bdp,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "cd5",
             detector_func: crate::formats::cd5::is_cd5_file,
             extractor_func: crate::formats::cd5::extract_cd5,
+            keys: const {&[KeySlot::collection("CD5", &[&[16], &[16]]).optional("encrypted modules, saved encrypted without it")]},
+            options: &[],
         },
         Format {
             name: "gx_dvb",
             detector_func: crate::formats::gx_dvb::is_gx_dvb_file,
             extractor_func: crate::formats::gx_dvb::extract_gx_dvb,
+            keys: &[],
+            options: &[],
         },
         Format {
             name: "onkyo",
             detector_func: crate::formats::onkyo::is_onkyo_file,
             extractor_func: crate::formats::onkyo::extract_onkyo,
+            keys: const {&[
+                KeySlot::key("ONKYO_HEADER_KEY", &[&[8]]),
+                KeySlot::key("ONKYO_DATA_KEY", &[&[8]]),
+                KeySlot::collection("ONKYO_AES", &[&[16], &[16]]).optional("newer files"),
+            ]},
+            options: &[],
         },
         Format {
             name: "mstar_secure_old",
             detector_func: crate::formats::mstar_secure_old::is_mstar_secure_old_file,
             extractor_func: crate::formats::mstar_secure_old::extract_mstar_secure_old,
+            keys: const {&[KeySlot::key("MSTAR_DEFAULT_UPGRADE_KEY", &[&[16]])]},
+            options: &[MSTAR_SECURE_OLD_KEEP_DECRYPTED, MSTAR_KEEP_UNKNOWN, MSTAR_OFFSET],
         },
         Format {
             name: "tsb_bin",
             detector_func: crate::formats::tsb_bin::is_tsb_bin_file,
             extractor_func: crate::formats::tsb_bin::extract_tsb_bin,
+            keys: &[],
+            options: &[],
         },   
         Format {
             name: "novatek_bin",
             detector_func: crate::formats::novatek_bin::is_novatek_bin_file,
             extractor_func: crate::formats::novatek_bin::extract_novatek_bin,
+            keys: &[],
+            options: &[],
         }, 
         Format {
             name: "fdat",
             detector_func: crate::formats::fdat::is_fdat_file,
             extractor_func: crate::formats::fdat::extract_fdat,
+            keys: const {&[
+                KeySlot::collection("FDAT_SHA_KEYS", &[&[40]]).note("1st gen firmware"),
+                KeySlot::key("FDAT_COMMON_AES_KEY", &[&[16]]).optional("2nd and 3rd gen firmware"),
+                KeySlot::key("FDAT_CXD90014_AES_KEY", &[&[16]]).optional("3rd gen firmware"),
+                KeySlot::key("FDAT_CXD90045_AES_KEY", &[&[32]]).optional("3rd gen CXD90045 firmware"),
+            ]},
+            options: &[],
         }, 
         Format {
             name: "msfirm",
             detector_func: crate::formats::msfirm::is_msfirm_file,
             extractor_func: crate::formats::msfirm::extract_msfirm,
+            keys: const {&[KeySlot::collection("MSFIRM", &[&[64]])]},
+            options: &[],
         },
         Format {
             name: "nw_wm_upg",
             detector_func: crate::formats::nw_wm_upg::is_nw_wm_upg_file,
             extractor_func: crate::formats::nw_wm_upg::extract_nw_wm_upg,
+            keys: const {&[
+                KeySlot::key("NW_WM_UPG_AES_PASS", &[&[16], &[16]]).note("decrypts AES KAS entries"),
+                KeySlot::key("NW_WM_UPG_DES_PASSKEY", &[&[8]]).note("decrypts DES KAS entries"),
+                KeySlot::collection("NW_WM_UPG_KAS", &[&[16, 32]]),
+            ]},
+            options: &[],
         },
         Format {
             name: "utv_qterics",
             detector_func: crate::formats::utv_qterics::is_utv_qterics_file,
             extractor_func: crate::formats::utv_qterics::extract_utv_qterics,
+            keys: const {&[KeySlot::collection("UTV_QTERICS", &[&[]]).note("base64 PEM private keys")]},
+            options: &[],
         },
         Format {
             name: "nvt_fwvr",
             detector_func: crate::formats::nvt_fwvr::is_nvt_fwvr_file,
             extractor_func: crate::formats::nvt_fwvr::extract_nvt_fwvr,
+            keys: const {&[
+                KeySlot::key("NVT_FWVR_FW_DEC_KEY_BIN", &[&[512]]),
+                KeySlot::key("NVT_FW40_IMG_ENC_KEY", &[&[16]]).optional("encrypted images of the inner nvt_timg_pkg"),
+                KeySlot::key("NVT_FW40_IMG_ENC_IV", &[&[16]]).optional("encrypted images of the inner nvt_timg_pkg"),
+                KeySlot::key("NVT_FW40_IMG_ENC_V2_KEY", &[&[32]]).optional("encrypted images of the inner nvt_timg_pkg with nvt_timg_pkg:variant=v2"),
+            ]},
+            options: &[NVT_TIMG_PKG_VARIANT, NVT_TIMG_PKG_USE_V2_KEY],
         },
         Format {
             name: "epson_pj",
             detector_func: crate::formats::epson_pj::is_epson_pj_file,
             extractor_func: crate::formats::epson_pj::extract_epson_pj,
+            keys: const {&[KeySlot::key("EPSONPJ", &[&[8], &[8]])]},
+            options: &[],
         },
         Format {
             name: "ncfw",
             detector_func: crate::formats::ncfw::is_ncfw_file,
             extractor_func: crate::formats::ncfw::extract_ncfw,
+            keys: &[],
+            options: &[],
+        },
+        Format {
+            name: "uimage",
+            detector_func: crate::formats::uimage::is_uimage_file,
+            extractor_func: crate::formats::uimage::extract_uimage,
+            keys: &[],
+            options: &[],
+        },
+        Format {
+            name: "android_boot",
+            detector_func: crate::formats::android_boot::is_android_boot_file,
+            extractor_func: crate::formats::android_boot::extract_android_boot,
+            keys: &[],
+            options: &[],
+        },
+        Format {
+            name: "dtb",
+            detector_func: crate::formats::dtb::is_dtb_file,
+            extractor_func: crate::formats::dtb::extract_dtb,
+            keys: &[],
+            options: &[],
+        },
+        Format {
+            name: "ext4",
+            detector_func: crate::formats::ext4::is_ext4_file,
+            extractor_func: crate::formats::ext4::extract_ext4,
+            keys: &[],
+            options: &[],
+        },
+        Format {
+            name: "jffs2",
+            detector_func: crate::formats::jffs2::is_jffs2_file,
+            extractor_func: crate::formats::jffs2::extract_jffs2,
+            keys: &[],
+            options: &[],
+        },
+        Format {
+            name: "cramfs",
+            detector_func: crate::formats::cramfs::is_cramfs_file,
+            extractor_func: crate::formats::cramfs::extract_cramfs,
+            keys: &[],
+            options: &[],
+        },
+        //no magic, keep this last
+        Format {
+            name: "yaffs2",
+            detector_func: crate::formats::yaffs2::is_yaffs2_file,
+            extractor_func: crate::formats::yaffs2::extract_yaffs2,
+            keys: &[],
+            options: &[],
         },
     ]
-}
\ No newline at end of file
+}
+
+//key searches by format name, used by `keys which`
+pub fn get_key_search_registry() -> Vec<KeySearch> {
+    vec![
+        KeySearch {name: "epk", search_func: crate::formats::epk::find_epk_key},
+        KeySearch {name: "epk2", search_func: crate::formats::epk2::find_epk2_key},
+        KeySearch {name: "msd10", search_func: crate::formats::msd10::find_msd10_key},
+        KeySearch {name: "msd11", search_func: crate::formats::msd11::find_msd11_key},
+        KeySearch {name: "msfirm", search_func: crate::formats::msfirm::find_msfirm_key},
+        KeySearch {name: "mtk_pkg_new", search_func: crate::formats::mtk_pkg_new::find_mtk_pkg_new_key},
+        KeySearch {name: "nw_wm_upg", search_func: crate::formats::nw_wm_upg::find_nw_wm_upg_key},
+        KeySearch {name: "sony_bdp", search_func: crate::formats::sony_bdp::find_sony_bdp_key},
+        KeySearch {name: "funai_bdp", search_func: crate::formats::funai_bdp::find_funai_bdp_key},
+        KeySearch {name: "pana_dvd", search_func: crate::formats::pana_dvd::find_pana_dvd_key},
+        KeySearch {name: "sddl_sec", search_func: crate::formats::sddl_sec::find_sddl_sec_key},
+        KeySearch {name: "ruf", search_func: crate::formats::ruf::find_ruf_key},
+        KeySearch {name: "cd5", search_func: crate::formats::cd5::find_cd5_key},
+        KeySearch {name: "sdimage", search_func: crate::formats::sdimage::find_sdimage_key},
+    ]
+}
+
+pub fn get_key_hunt_registry() -> Vec<KeyHunt> {
+    vec![
+        KeyHunt {name: "epk", has_magic: true, hunt_func: crate::formats::epk::hunt_epk_key},
+        KeyHunt {name: "msd10", has_magic: true, hunt_func: crate::formats::msd10::hunt_msd10_key},
+        KeyHunt {name: "msd11", has_magic: true, hunt_func: crate::formats::msd11::hunt_msd11_key},
+        KeyHunt {name: "mtk_pkg", has_magic: true, hunt_func: crate::formats::mtk_pkg::hunt_mtk_pkg_key},
+        KeyHunt {name: "mtk_pkg_new", has_magic: false, hunt_func: crate::formats::mtk_pkg_new::hunt_mtk_pkg_new_key},
+        KeyHunt {name: "pana_dvd", has_magic: false, hunt_func: crate::formats::pana_dvd::hunt_pana_dvd_key},
+    ]
+}
@@ -12,13 +12,29 @@ pub struct ImageHeader {
     _reserved: [u8; 36],
 }
 
+//V1 and V2 entries only differ in the length of the type and name fields
 #[derive(BinRead)]
-pub struct ItemEntry {
+pub struct ItemEntryV1 {
     _item_id: u32,
-    pub file_type: u32,
+    file_type: u32,
     _current_offset_in_item: u64,
-    pub offset_in_image: u64,
-    pub item_size: u64,
+    offset_in_image: u64,
+    item_size: u64,
+    item_type_bytes: [u8; 32],
+    name_bytes: [u8; 32],
+    _verify: u32,
+    _is_backup_item: u16,
+    _backup_item_id: u16,
+    _reserved: [u8; 24],
+}
+
+#[derive(BinRead)]
+pub struct ItemEntryV2 {
+    _item_id: u32,
+    file_type: u32,
+    _current_offset_in_item: u64,
+    offset_in_image: u64,
+    item_size: u64,
     item_type_bytes: [u8; 256],
     name_bytes: [u8; 256],
     _verify: u32,
@@ -26,14 +42,43 @@ pub struct ItemEntry {
     _backup_item_id: u16,
     _reserved: [u8; 24],
 }
+
+pub struct ItemEntry {
+    pub file_type: u32,
+    pub offset_in_image: u64,
+    pub item_size: u64,
+    item_type: String,
+    name: String,
+}
 impl ItemEntry {
     pub fn item_type(&self) -> String {
-        common::string_from_bytes(&self.item_type_bytes)
+        self.item_type.clone()
     }
     pub fn name(&self) -> String {
-        common::string_from_bytes(&self.name_bytes)
+        self.name.clone()
     }
     pub fn is_sparse(&self) -> bool {
         self.file_type == 254
     }
-}
\ No newline at end of file
+}
+
+impl From<ItemEntryV1> for ItemEntry {
+    fn from(item: ItemEntryV1) -> Self {
+        ItemEntry {file_type: item.file_type, offset_in_image: item.offset_in_image, item_size: item.item_size,
+            item_type: common::string_from_bytes(&item.item_type_bytes), name: common::string_from_bytes(&item.name_bytes)}
+    }
+}
+
+impl From<ItemEntryV2> for ItemEntry {
+    fn from(item: ItemEntryV2) -> Self {
+        ItemEntry {file_type: item.file_type, offset_in_image: item.offset_in_image, item_size: item.item_size,
+            item_type: common::string_from_bytes(&item.item_type_bytes), name: common::string_from_bytes(&item.name_bytes)}
+    }
+}
+
+//verify items hold "sha1sum " followed by the hex SHA-1 of the partition with the same name
+pub fn parse_verify(data: &[u8]) -> Option<String> {
+    let text = common::string_from_bytes(data);
+    let sum = text.trim().strip_prefix("sha1sum")?.trim().to_lowercase();
+    (sum.len() == 40 && sum.chars().all(|c| c.is_ascii_hexdigit())).then_some(sum)
+}
@@ -3,9 +3,10 @@ use std::any::Any;
 use crate::AppContext;
 
 use std::path::Path;
-use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use binrw::BinReaderExt;
+use sha1::{Digest, Sha1};
 
 use crate::utils::common;
 use crate::utils::sparse::unsparse_to_file;
@@ -29,14 +30,14 @@ pub fn extract_amlogic(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), B
     println!("File info -\nImage size: {}\nItem align size: {}\nItem count: {}\nFormat version: {}", 
             header.image_size, header.item_align_size, header.item_count, header.version);
 
-    if header.version != 2 {
-        return Err("Unsupported format version! (Only 2 is supported right now)".into());
-    }
-
     let mut items: Vec<ItemEntry> = Vec::new();
 
     for _i in 0..header.item_count {
-        let item: ItemEntry = file.read_le()?;
+        let item: ItemEntry = match header.version {
+            1 => file.read_le::<ItemEntryV1>()?.into(),
+            2 => file.read_le::<ItemEntryV2>()?.into(),
+            _ => return Err(format!("Unsupported format version {}!", header.version).into()),
+        };
         items.push(item);
     }
 
@@ -46,8 +47,21 @@ pub fn extract_amlogic(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), B
 
         if item.item_type() == "VERIFY" { //verify item is SHA1 of partition item
             let sum_bytes = common::read_file(&file, item.offset_in_image, item.item_size as usize)?;
-            let sum = common::string_from_bytes(&sum_bytes);
-            println!("- Checksum for {}: {}", item.name(), sum);
+            let Some(expected) = parse_verify(&sum_bytes) else {
+                println!("- Warning: invalid checksum for {}: {}", item.name(), common::string_from_bytes(&sum_bytes));
+                continue
+            };
+            match items.iter().find(|p| p.item_type() == "PARTITION" && p.name() == item.name()) {
+                Some(partition) => {
+                    let sum = item_sha1(file, partition)?;
+                    if sum == expected {
+                        println!("- Checksum for {} OK", item.name());
+                    } else {
+                        println!("- Warning: checksum mismatch for {}! (expected {}, got {})", item.name(), expected, sum);
+                    }
+                }
+                None => println!("- Warning: no partition {} for checksum {}", item.name(), expected),
+            }
 
         } else {
             let data = common::read_file(&file, item.offset_in_image, item.item_size as usize)?;
@@ -72,4 +86,12 @@ pub fn extract_amlogic(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), B
     }
 
     Ok(())
+}
+
+//partitions can be large, so they are hashed while reading
+fn item_sha1(mut file: &File, item: &ItemEntry) -> Result<String, Box<dyn std::error::Error>> {
+    file.seek(SeekFrom::Start(item.offset_in_image))?;
+    let mut hasher = Sha1::new();
+    io::copy(&mut file.take(item.item_size), &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
 }
\ No newline at end of file
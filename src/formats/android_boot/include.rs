@@ -0,0 +1,125 @@
+use crate::utils::common;
+use binrw::BinRead;
+
+pub static BOOT_MAGIC: &[u8; 8] = b"ANDROID!";
+pub static VENDOR_BOOT_MAGIC: &[u8; 8] = b"VNDRBOOT";
+//v3 and newer boot images have a fixed page size
+pub static BOOT_V3_PAGE_SIZE: u64 = 4096;
+
+//v0 - v2, newer fields are only valid in the versions that added them
+#[derive(BinRead)]
+pub struct BootHeaderV0 {
+    _magic: [u8; 8],
+    pub kernel_size: u32,
+    pub kernel_addr: u32,
+    pub ramdisk_size: u32,
+    pub ramdisk_addr: u32,
+    pub second_size: u32,
+    pub second_addr: u32,
+    pub tags_addr: u32,
+    pub page_size: u32,
+    pub header_version: u32,
+    pub os_version: u32,
+    name_bytes: [u8; 16],
+    cmdline_bytes: [u8; 512],
+    _id: [u8; 32],
+    extra_cmdline_bytes: [u8; 1024],
+    //v1
+    pub recovery_dtbo_size: u32,
+    pub recovery_dtbo_offset: u64,
+    _header_size: u32,
+    //v2
+    pub dtb_size: u32,
+    pub dtb_addr: u64,
+}
+impl BootHeaderV0 {
+    pub fn name(&self) -> String {
+        common::string_from_bytes(&self.name_bytes)
+    }
+    pub fn cmdline(&self) -> String {
+        common::string_from_bytes(&self.cmdline_bytes) + &common::string_from_bytes(&self.extra_cmdline_bytes)
+    }
+}
+
+//v3 - v4
+#[derive(BinRead)]
+pub struct BootHeaderV3 {
+    _magic: [u8; 8],
+    pub kernel_size: u32,
+    pub ramdisk_size: u32,
+    pub os_version: u32,
+    _header_size: u32,
+    _reserved: [u32; 4],
+    pub header_version: u32,
+    cmdline_bytes: [u8; 1536],
+    //v4
+    pub signature_size: u32,
+}
+impl BootHeaderV3 {
+    pub fn cmdline(&self) -> String {
+        common::string_from_bytes(&self.cmdline_bytes)
+    }
+}
+
+#[derive(BinRead)]
+pub struct VendorBootHeader {
+    _magic: [u8; 8],
+    pub header_version: u32,
+    pub page_size: u32,
+    pub kernel_addr: u32,
+    pub ramdisk_addr: u32,
+    pub vendor_ramdisk_size: u32,
+    cmdline_bytes: [u8; 2048],
+    pub tags_addr: u32,
+    name_bytes: [u8; 16],
+    pub header_size: u32,
+    pub dtb_size: u32,
+    pub dtb_addr: u64,
+    //v4
+    pub ramdisk_table_size: u32,
+    pub ramdisk_table_entry_num: u32,
+    pub ramdisk_table_entry_size: u32,
+    pub bootconfig_size: u32,
+}
+impl VendorBootHeader {
+    pub fn name(&self) -> String {
+        common::string_from_bytes(&self.name_bytes)
+    }
+    pub fn cmdline(&self) -> String {
+        common::string_from_bytes(&self.cmdline_bytes)
+    }
+}
+
+#[derive(BinRead)]
+pub struct VendorRamdiskEntry {
+    pub size: u32,
+    pub offset: u32,            //inside the vendor ramdisk section
+    pub ramdisk_type: u32,
+    name_bytes: [u8; 32],
+    _board_id: [u32; 16],
+}
+impl VendorRamdiskEntry {
+    pub fn name(&self) -> String {
+        common::string_from_bytes(&self.name_bytes)
+    }
+    pub fn type_name(&self) -> &str {
+        match self.ramdisk_type {
+            0 => "none",
+            1 => "platform",
+            2 => "recovery",
+            3 => "dlkm",
+            _ => "unknown",
+        }
+    }
+}
+
+//os_version packs the version as three 7 bit numbers and the patch level as years since 2000 and month
+pub fn format_os_version(os_version: u32) -> String {
+    if os_version == 0 {
+        return "not set".to_string();
+    }
+    let version = os_version >> 11;
+    let patch_level = os_version & 0x7FF;
+    format!("{}.{}.{}, patch level {}-{:02}",
+            (version >> 14) & 0x7F, (version >> 7) & 0x7F, version & 0x7F, (patch_level >> 4) + 2000, patch_level & 0xF)
+}
@@ -0,0 +1,210 @@
+mod include;
+use std::any::Any;
+use crate::AppContext;
+
+use std::path::Path;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Write};
+use binrw::BinReaderExt;
+
+use crate::utils::common;
+use crate::utils::compression::{decompress_gzip, decompress_lz4_legacy, decompress_lz4_frame};
+use crate::utils::cpio;
+use crate::formats::dtb::decode_embedded_dtbs;
+use include::*;
+
+pub enum AndroidBootContext {
+    Boot,
+    VendorBoot,
+}
+
+pub fn is_android_boot_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let magic = common::read_file(file, 0, 8)?;
+    if magic == BOOT_MAGIC {
+        Ok(Some(Box::new(AndroidBootContext::Boot)))
+    } else if magic == VENDOR_BOOT_MAGIC {
+        Ok(Some(Box::new(AndroidBootContext::VendorBoot)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn align(value: u64, page_size: u64) -> u64 {
+    value.div_ceil(page_size) * page_size
+}
+
+fn save_section(file: &File, offset: u64, size: u32, output_dir: &Path, name: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    if size == 0 {
+        return Ok(None)
+    }
+    if offset + size as u64 > file.metadata()?.len() {
+        println!("- Warning: {} is out of bounds, image may be truncated!", name);
+        return Ok(None)
+    }
+
+    println!("\n{} - Offset: 0x{:x}, Size: {}", name, offset, size);
+    let data = common::read_file(file, offset, size as usize)?;
+    let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_dir.join(name))?;
+    out_file.write_all(&data)?;
+    println!("-- Saved file!");
+
+    Ok(Some(data))
+}
+
+//ramdisks are a cpio archive, usually gzip or lz4 (legacy frame) compressed
+fn unpack_ramdisk(data: &[u8], output_dir: &Path, dir_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let decompressed = if data.starts_with(b"\x1F\x8B") {
+        println!("- Decompressing gzip...");
+        decompress_gzip(data)
+    } else if data.starts_with(b"\x02\x21\x4C\x18") {
+        println!("- Decompressing lz4...");
+        decompress_lz4_legacy(data)
+    } else if data.starts_with(b"\x04\x22\x4D\x18") {
+        println!("- Decompressing lz4...");
+        decompress_lz4_frame(data).map_err(|e| e.into())
+    } else {
+        Ok(data.to_vec())
+    };
+    let decompressed = match decompressed {
+        Ok(d) => d,
+        Err(e) => {
+            println!("- Warning: failed to decompress ramdisk ({}), not unpacking", e);
+            return Ok(())
+        }
+    };
+
+    if !cpio::is_cpio(&decompressed) {
+        println!("- Unknown ramdisk format, not unpacking");
+        return Ok(())
+    }
+    let entry_count = cpio::extract_cpio(&decompressed, &output_dir.join(dir_name))?;
+    println!("- Unpacked {} entries to {}/", entry_count, dir_name);
+
+    Ok(())
+}
+
+fn extract_boot(app_ctx: &AppContext, file: &File) -> Result<(), Box<dyn std::error::Error>> {
+    //version is at the same offset in all header layouts
+    let header_bytes = common::read_file(file, 0, 4096)?;
+    let header_version = u32::from_le_bytes(header_bytes[40..44].try_into()?);
+    let output_dir = &app_ctx.output_dir;
+
+    if header_version >= 3 {
+        let header: BootHeaderV3 = Cursor::new(&header_bytes).read_le()?;
+        println!("Boot image info -\nHeader version: {}\nKernel size: {}\nRamdisk size: {}\nOS version: {}\nCmdline: {}",
+                header.header_version, header.kernel_size, header.ramdisk_size, format_os_version(header.os_version), header.cmdline());
+
+        let page_size = BOOT_V3_PAGE_SIZE;
+        let kernel_offset = page_size;
+        let ramdisk_offset = kernel_offset + align(header.kernel_size as u64, page_size);
+        let signature_offset = ramdisk_offset + align(header.ramdisk_size as u64, page_size);
+
+        save_section(file, kernel_offset, header.kernel_size, output_dir, "kernel.bin")?;
+        if let Some(ramdisk) = save_section(file, ramdisk_offset, header.ramdisk_size, output_dir, "ramdisk.bin")? {
+            unpack_ramdisk(&ramdisk, output_dir, "ramdisk")?;
+        }
+        if header.header_version >= 4 {
+            save_section(file, signature_offset, header.signature_size, output_dir, "boot_signature.bin")?;
+        }
+    } else {
+        let header: BootHeaderV0 = Cursor::new(&header_bytes).read_le()?;
+        println!("Boot image info -\nHeader version: {}\nName: {}\nPage size: {}\nKernel size: {}, address: 0x{:08x}\nRamdisk size: {}, address: 0x{:08x}\nSecond size: {}, address: 0x{:08x}\nTags address: 0x{:08x}\nOS version: {}\nCmdline: {}",
+                header.header_version, header.name(), header.page_size, header.kernel_size, header.kernel_addr, header.ramdisk_size, header.ramdisk_addr,
+                header.second_size, header.second_addr, header.tags_addr, format_os_version(header.os_version), header.cmdline());
+
+        if header.page_size == 0 {
+            return Err("Invalid page size!".into());
+        }
+        let page_size = header.page_size as u64;
+        let kernel_offset = page_size;
+        let ramdisk_offset = kernel_offset + align(header.kernel_size as u64, page_size);
+        let second_offset = ramdisk_offset + align(header.ramdisk_size as u64, page_size);
+        let dtbo_offset = second_offset + align(header.second_size as u64, page_size);
+
+        save_section(file, kernel_offset, header.kernel_size, output_dir, "kernel.bin")?;
+        if let Some(ramdisk) = save_section(file, ramdisk_offset, header.ramdisk_size, output_dir, "ramdisk.bin")? {
+            unpack_ramdisk(&ramdisk, output_dir, "ramdisk")?;
+        }
+        save_section(file, second_offset, header.second_size, output_dir, "second.bin")?;
+
+        if header.header_version >= 1 {
+            if header.recovery_dtbo_size != 0 && header.recovery_dtbo_offset != dtbo_offset {
+                println!("- Warning: recovery DTBO offset in header (0x{:x}) differs from expected (0x{:x})", header.recovery_dtbo_offset, dtbo_offset);
+            }
+            save_section(file, dtbo_offset, header.recovery_dtbo_size, output_dir, "recovery_dtbo.bin")?;
+        }
+        if header.header_version >= 2 {
+            println!("\nDTB address: 0x{:08x}", header.dtb_addr);
+            let dtb_offset = dtbo_offset + align(header.recovery_dtbo_size as u64, page_size);
+            if let Some(dtb) = save_section(file, dtb_offset, header.dtb_size, output_dir, "dtb.bin")? {
+                decode_embedded_dtbs(&dtb, output_dir, "dtb")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_vendor_boot(app_ctx: &AppContext, file: &File) -> Result<(), Box<dyn std::error::Error>> {
+    let header_bytes = common::read_file(file, 0, 4096)?;
+    let header: VendorBootHeader = Cursor::new(&header_bytes).read_le()?;
+    let output_dir = &app_ctx.output_dir;
+
+    println!("Vendor boot image info -\nHeader version: {}\nName: {}\nPage size: {}\nKernel address: 0x{:08x}\nRamdisk address: 0x{:08x}\nTags address: 0x{:08x}\nDTB address: 0x{:08x}\nVendor ramdisk size: {}\nCmdline: {}",
+            header.header_version, header.name(), header.page_size, header.kernel_addr, header.ramdisk_addr, header.tags_addr, header.dtb_addr,
+            header.vendor_ramdisk_size, header.cmdline());
+
+    if header.page_size == 0 {
+        return Err("Invalid page size!".into());
+    }
+    let page_size = header.page_size as u64;
+    let ramdisk_offset = align(header.header_size as u64, page_size);
+    let dtb_offset = ramdisk_offset + align(header.vendor_ramdisk_size as u64, page_size);
+    let table_offset = dtb_offset + align(header.dtb_size as u64, page_size);
+    let bootconfig_offset = table_offset + align(header.ramdisk_table_size as u64, page_size);
+
+    let vendor_ramdisk = save_section(file, ramdisk_offset, header.vendor_ramdisk_size, output_dir, "vendor_ramdisk.bin")?;
+    if let Some(dtb) = save_section(file, dtb_offset, header.dtb_size, output_dir, "dtb.bin")? {
+        decode_embedded_dtbs(&dtb, output_dir, "dtb")?;
+    }
+
+    //v4 splits the vendor ramdisk into named fragments, each a separate archive
+    if header.header_version >= 4 && header.ramdisk_table_entry_num > 0 {
+        let table = common::read_file(file, table_offset, header.ramdisk_table_size as usize)?;
+        let vendor_ramdisk = vendor_ramdisk.ok_or("Missing vendor ramdisk!")?;
+
+        for i in 0..header.ramdisk_table_entry_num {
+            let entry_offset = i as usize * header.ramdisk_table_entry_size as usize;
+            let entry: VendorRamdiskEntry = Cursor::new(table.get(entry_offset..).ok_or("Ramdisk table entry out of bounds!")?).read_le()?;
+            let name = if entry.name().is_empty() {format!("{}", i + 1)} else {common::output_name(&entry.name())};
+            println!("\n({}/{}) - Ramdisk {}, Type: {}, Offset: 0x{:x}, Size: {}",
+                    i + 1, header.ramdisk_table_entry_num, name, entry.type_name(), entry.offset, entry.size);
+
+            let start = entry.offset as usize;
+            let fragment = vendor_ramdisk.get(start..start + entry.size as usize).ok_or("Ramdisk fragment out of bounds!")?;
+            let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_dir.join(format!("vendor_ramdisk_{}.bin", name)))?;
+            out_file.write_all(fragment)?;
+            println!("-- Saved file!");
+            unpack_ramdisk(fragment, output_dir, &format!("vendor_ramdisk_{}", name))?;
+        }
+
+        save_section(file, bootconfig_offset, header.bootconfig_size, output_dir, "bootconfig.txt")?;
+    } else if let Some(vendor_ramdisk) = vendor_ramdisk {
+        unpack_ramdisk(&vendor_ramdisk, output_dir, "vendor_ramdisk")?;
+    }
+
+    Ok(())
+}
+
+pub fn extract_android_boot(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+    let ctx = ctx.downcast::<AndroidBootContext>().expect("Missing context");
+    fs::create_dir_all(&app_ctx.output_dir)?;
+
+    match *ctx {
+        AndroidBootContext::Boot => extract_boot(app_ctx, file),
+        AndroidBootContext::VendorBoot => extract_vendor_boot(app_ctx, file),
+    }
+}
@@ -8,6 +8,8 @@ use std::io::{Write, Seek};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::aes::{decrypt_aes128_cbc_pcks7, decrypt_aes256_cbc_pcks7};
+use crate::utils::keytrial::find_matching_key;
 use include::*;
 use crate::formats::msd::decrypt_aes_tizen;
 
@@ -32,6 +34,26 @@ pub fn is_bem_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn
     }
 }
 
+//only decrypts the last block, with the one before it as IV - the padding has to leave the original data lenght
+fn is_bem_passphrase(encrypted_data: &[u8], passphrase: &[u8], salt: &[u8], original_data_lenght: usize) -> bool {
+    let len = encrypted_data.len();
+    if len < 16 || !len.is_multiple_of(16) {
+        return false
+    }
+    let result = if len >= 32 {
+        let last_block = &encrypted_data[len - 16..];
+        let iv: [u8; 16] = encrypted_data[len - 32..len - 16].try_into().unwrap();
+        match passphrase.len() {
+            16 => decrypt_aes128_cbc_pcks7(last_block, &passphrase.try_into().unwrap(), &iv),
+            32 => decrypt_aes256_cbc_pcks7(last_block, &passphrase.try_into().unwrap(), &iv),
+            _ => return false,
+        }
+    } else {
+        decrypt_aes_tizen(encrypted_data, passphrase, salt)
+    };
+    result.is_ok_and(|last| len - 16 + last.len() == original_data_lenght)
+}
+
 pub fn extract_bem(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<BemCtx>().expect("Missing context");
@@ -46,32 +68,21 @@ pub fn extract_bem(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dy
     let _signature = common::read_exact(&mut file, bem_header.signature_lenght() as usize)?;
 
     //find passphrase
-    let mut passphrase_bytes: Option<&Vec<u8>> = None;
-    let mut passphrase_name: &str = "";
-    let mut decrypted_data: Vec<u8> = vec![];
-    for (name, keys) in app_ctx.keys.get_collection("MSD11")? {
+    let salt = bem_header.salt();
+    let original_data_lenght = bem_header.original_data_lenght() as usize;
+    let passphrases = app_ctx.get_collection("MSD11")?;
+    let found = find_matching_key(&passphrases, |keys| {
         let passphrase = keys.first().unwrap();
-        match decrypt_aes_tizen(&encrypted_data, &passphrase, &bem_header.salt()) {
-            Ok(result) => {
-                if result.len() == bem_header.original_data_lenght() as usize { //verify padding was correct
-                    passphrase_bytes = Some(passphrase);
-                    passphrase_name = name;
-                    decrypted_data = result;
-                    break
-                } else {
-                    continue
-                }
-            },
-            Err(_) => continue,
-        };
-    }
+        is_bem_passphrase(&encrypted_data, passphrase, &salt, original_data_lenght).then_some(passphrase)
+    });
     
-    let passphrase_bytes = if let Some(p) = passphrase_bytes {
+    let passphrase_bytes = if let Some((passphrase_name, p)) = found {
         println!("Using passphrase: {}", passphrase_name);
         p
     } else {
         return Err("No matching key found!".into());
     };
+    let decrypted_data = decrypt_aes_tizen(&encrypted_data, passphrase_bytes, &salt)?;
 
     //parse decrypted version
     let version_len = u32::from_le_bytes(decrypted_data[..4].try_into().unwrap()) as usize;
@@ -3,11 +3,13 @@ use std::any::Any;
 use crate::AppContext;
 
 use std::path::Path;
-use std::fs::{self, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::io::{Cursor, Seek, SeekFrom, Write};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::aes::decrypt_aes128_cbc_nopad;
+use crate::utils::keytrial::{find_first, optional_hinted_aes_pairs};
 use include::*;
 
 pub fn is_cd5_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
@@ -21,27 +23,113 @@ pub fn is_cd5_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn
     }
 }
 
-pub fn extract_cd5(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = app_ctx.file().ok_or("Extractor expected file")?;
+//name, key and iv
+type ModuleKey = (String, [u8; 16], [u8; 16]);
 
+fn read_download_header(mut file: &File) -> Result<DownloadHeader, Box<dyn std::error::Error>> {
     let dwld_hdr_desc: DescriptorHeader = file.read_be()?;
     if dwld_hdr_desc.magic != 0x11 {
         return Err("Invalid download header magic!".into());
     }
     let mut dwld_hdr_reader = Cursor::new(common::read_exact(&mut file, dwld_hdr_desc.size as usize)?);
-    let dwld_hdr: DownloadHeader = dwld_hdr_reader.read_be()?;
+    Ok(dwld_hdr_reader.read_be()?)
+}
+
+//hints are kept per manufacturer and variant
+fn key_fingerprint(dwld_hdr: &DownloadHeader) -> String {
+    format!("{:04x}:{:04x}", dwld_hdr.manufacturer_code, dwld_hdr.variant)
+}
+
+fn decrypt_module(data: &[u8], key: &ModuleKey) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    //decrypt only aligned data, rest is plain
+    let align_size = data.len() & !0xF;
+    let mut out_data = data.to_vec();
+    out_data[..align_size].copy_from_slice(&decrypt_aes128_cbc_nopad(&data[..align_size], &key.1, &key.2)?);
+    Ok(out_data)
+}
+
+//the inner header starts with the module id and its own size, so the first block shows if a key is right
+fn is_module_key(data: &[u8], module_id: u16, key: &[u8; 16], iv: &[u8; 16]) -> bool {
+    if data.len() < 16 {
+        return false
+    }
+    decrypt_aes128_cbc_nopad(&data[..16], key, iv).is_ok_and(|dec| {
+        u16::from_be_bytes([dec[0], dec[1]]) == module_id && (u16::from_be_bytes([dec[2], dec[3]]) as usize) < data.len()
+    })
+}
+
+//None if no key matches or there are no CD5 keys, then the module is saved encrypted
+fn find_module_key(app_ctx: &AppContext, fingerprint: &str, module_id: u16, data: &[u8]) -> Result<Option<ModuleKey>, Box<dyn std::error::Error>> {
+    let Some(keys) = optional_hinted_aes_pairs(app_ctx, "cd5", fingerprint, "CD5")? else {
+        return Ok(None)
+    };
+    let Some((i, _)) = find_first(&keys, |(_, (key, iv))| is_module_key(data, module_id, key, iv).then_some(())) else {
+        return Ok(None)
+    };
+    let (name, (key, iv)) = &keys[i];
+    app_ctx.remember_key("cd5", fingerprint, "CD5", name);
+    Ok(Some((name.clone(), *key, *iv)))
+}
+
+pub fn find_cd5_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut file = app_ctx.file().ok_or("Expected file")?;
+    file.seek(SeekFrom::Start(0))?;
+    let dwld_hdr = read_download_header(file)?;
+    for _ in 0..dwld_hdr.module_count {
+        let module = read_module(file)?;
+        if module.header.is_encrypted() {
+            return Ok(find_module_key(app_ctx, &key_fingerprint(&dwld_hdr), module.header.module_id, &module.data)?.map(|(name, _, _)| format!("CD5 \"{}\"", name)))
+        }
+    }
+    Ok(None)
+}
+
+//module download header and the data of all its segments
+struct Module {
+    header: ModuleDownloadHeader,
+    data: Vec<u8>,
+    segment_sizes: Vec<u16>,
+}
+
+fn read_module(mut file: &File) -> Result<Module, Box<dyn std::error::Error>> {
+    let mod_hdr_desc: DescriptorHeader = file.read_be()?;
+    if mod_hdr_desc.magic != 0x22 {
+        return Err("Invalid module download header magic!".into());
+    }
+    let mut mod_hdr_reader = Cursor::new(common::read_exact(&mut file, mod_hdr_desc.size as usize)?);
+    let mod_hdr: ModuleDownloadHeader = mod_hdr_reader.read_be()?;
+
+    let mut module_data: Vec<u8> = Vec::new();
+    let mut segment_sizes = Vec::new();
+    for _ in 0..mod_hdr.segment_count {
+        let mut segment: DownloadSegment = file.read_be()?;
+        if segment.magic != 0x33 {
+            return Err("Invalid segment magic!".into());
+        }
+        if segment.module_id != mod_hdr.module_id {
+            return Err("Module id mismatch in segment and module header!".into());
+        }
+        segment_sizes.push(segment.data_size);
+        module_data.append(&mut segment.data);
+    }
+    Ok(Module {header: mod_hdr, data: module_data, segment_sizes})
+}
+
+pub fn extract_cd5(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+
+    let dwld_hdr = read_download_header(file)?;
+    let fingerprint = key_fingerprint(&dwld_hdr);
 
     // like Loader Data screen
     println!("File info -\nManufacturer code: {}\nHardware Version: {}\nVersion(DSN): {}(0x{:02x})\nVariant/Sub-variant: 0x{:02x}/0x{:02x}\nModule count: {}",
             dwld_hdr.manufacturer_code, dwld_hdr.hardware_version, dwld_hdr.version, dwld_hdr.version, dwld_hdr.variant, dwld_hdr.sub_variant, dwld_hdr.module_count);
 
+    //all modules of a file should use the same key
+    let mut module_key: Option<ModuleKey> = None;
+
     for (i, module) in dwld_hdr.module_entries.iter().enumerate() {
-        let mod_hdr_desc: DescriptorHeader = file.read_be()?;
-        if mod_hdr_desc.magic != 0x22 {
-            return Err("Invalid module download header magic!".into());
-        }
-        let mut mod_hdr_reader = Cursor::new(common::read_exact(&mut file, mod_hdr_desc.size as usize)?);
-        let mod_hdr: ModuleDownloadHeader = mod_hdr_reader.read_be()?;
+        let Module {header: mod_hdr, data: module_data, segment_sizes} = read_module(file)?;
         if mod_hdr.module_id != module.module_id {
             return Err("Module id mismatch in download header and module header!".into());
         }
@@ -50,33 +138,31 @@ pub fn extract_cd5(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<d
                 i+1, dwld_hdr.module_count, mod_hdr.module_id, mod_hdr.module_id, module.version, module.version, mod_hdr.out_size, mod_hdr.segment_size, mod_hdr.segment_count,
                 if mod_hdr.is_encrypted() {"[ENCRYPTED]"} else {""});
 
-        let mut module_data: Vec<u8> = Vec::new();
-
-        for s_i in 0..mod_hdr.segment_count {
-            let mut segment: DownloadSegment = file.read_be()?;
-            if segment.magic != 0x33 {
-                return Err("Invalid segment magic!".into());
-            }
-            if segment.module_id != mod_hdr.module_id {
-                return Err("Module id mismatch in segment and module header!".into());
-            }
-
-            println!("  Segment {}/{} - Size: {}", s_i+1, mod_hdr.segment_count, segment.data_size);
-            module_data.append(&mut segment.data);
+        for (s_i, segment_size) in segment_sizes.iter().enumerate() {
+            println!("  Segment {}/{} - Size: {}", s_i+1, mod_hdr.segment_count, segment_size);
         }
 
-        let out_data;
-        if mod_hdr.is_encrypted() {
-            println!("- Warning: data is encrypted, so cannot read inner header - saving ENCRYPTED data!");
-            out_data = module_data;
+        if mod_hdr.is_encrypted() && !module_key.as_ref().is_some_and(|(_, key, iv)| is_module_key(&module_data, mod_hdr.module_id, key, iv)) {
+            module_key = find_module_key(app_ctx, &fingerprint, mod_hdr.module_id, &module_data)?;
         }
-        else {
+
+        let out_data = if mod_hdr.is_encrypted() && module_key.is_none() {
+            println!("- Warning: no matching CD5 key, so cannot read inner header - saving ENCRYPTED data!");
+            module_data
+        } else {
+            let module_data = match &module_key {
+                Some(key) if mod_hdr.is_encrypted() => {
+                    println!("- Decrypting with key: {}...", key.0);
+                    decrypt_module(&module_data, key)?
+                }
+                _ => module_data,
+            };
             let mut mod_data_rdr = Cursor::new(module_data);
             let inner_mod_hdr: InnerModuleHeader = mod_data_rdr.read_be()?;
             println!("- Inner header size: {}, Data size: {}", inner_mod_hdr.header_size, inner_mod_hdr.data_size);
             mod_data_rdr.seek(SeekFrom::Start(inner_mod_hdr.header_size as u64))?;
-            out_data = common::read_exact(&mut mod_data_rdr, inner_mod_hdr.data_size as usize)?;
-        }
+            common::read_exact(&mut mod_data_rdr, inner_mod_hdr.data_size as usize)?
+        };
 
         let output_path = Path::new(&app_ctx.output_dir).join(format!("{}.bin", mod_hdr.module_id));
         fs::create_dir_all(&app_ctx.output_dir)?;
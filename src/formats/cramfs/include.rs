@@ -0,0 +1,74 @@
+use crate::utils::common;
+use binrw::{BinRead, Endian};
+
+pub static CRAMFS_MAGIC: u32 = 0x28CD3D45;
+pub static CRAMFS_SIGNATURE: &[u8; 16] = b"Compressed ROMFS";
+pub static PAGE_SIZE: u64 = 4096;
+
+//superblock flags
+pub static FLAG_FSID_VERSION_2: u32 = 0x00000001;
+pub static FLAG_EXT_BLOCK_POINTERS: u32 = 0x00000800;
+
+//block pointer flags, only with FLAG_EXT_BLOCK_POINTERS
+pub static BLK_FLAG_UNCOMPRESSED: u32 = 1 << 31;
+pub static BLK_FLAG_DIRECT_PTR: u32 = 1 << 30;
+pub static BLK_FLAGS: u32 = BLK_FLAG_UNCOMPRESSED | BLK_FLAG_DIRECT_PTR;
+
+#[derive(BinRead)]
+pub struct Superblock {
+    _magic: u32,
+    pub size: u32,
+    pub flags: u32,
+    _future: u32,
+    _signature: [u8; 16],
+    pub crc: u32,
+    pub edition: u32,
+    pub blocks: u32,
+    pub files: u32,
+    name_bytes: [u8; 16],
+    pub root: RawInode,
+}
+impl Superblock {
+    pub fn name(&self) -> String {
+        common::string_from_bytes(&self.name_bytes)
+    }
+}
+
+//the inode is made of bitfields, which the compiler lays out from the other end on big endian machines
+#[derive(BinRead)]
+pub struct RawInode {
+    mode_uid: u32,
+    size_gid: u32,
+    namelen_offset: u32,
+}
+impl RawInode {
+    pub fn decode(&self, endian: Endian) -> Inode {
+        match endian {
+            Endian::Little => Inode {
+                mode: self.mode_uid & 0xFFFF,
+                uid: self.mode_uid >> 16,
+                size: self.size_gid & 0xFFFFFF,
+                gid: self.size_gid >> 24,
+                name_len: (self.namelen_offset & 0x3F) * 4,
+                offset: (self.namelen_offset >> 6) as u64 * 4,
+            },
+            Endian::Big => Inode {
+                mode: self.mode_uid >> 16,
+                uid: self.mode_uid & 0xFFFF,
+                size: self.size_gid >> 8,
+                gid: self.size_gid & 0xFF,
+                name_len: (self.namelen_offset >> 26) * 4,
+                offset: (self.namelen_offset & 0x3FFFFFF) as u64 * 4,
+            },
+        }
+    }
+}
+
+pub struct Inode {
+    pub mode: u32,
+    pub uid: u32,
+    pub size: u32,              //device number for device nodes
+    pub gid: u32,
+    pub name_len: u32,          //padded to 4 bytes
+    pub offset: u64,            //of the data, directory entries or block pointers
+}
@@ -0,0 +1,241 @@
+mod include;
+use std::any::Any;
+use crate::AppContext;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::fs::{self, OpenOptions};
+use std::io::{Cursor, Write};
+use binrw::{BinReaderExt, Endian};
+
+use crate::utils::common;
+use crate::utils::compression::decompress_zlib;
+use crate::utils::manifest::{Manifest, FileType};
+use include::*;
+
+pub struct CramfsContext {
+    sb_offset: u64,
+    endian: Endian,
+}
+
+pub fn is_cramfs_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+    let file_size = file.metadata()?.len();
+
+    //superblock can be preceded by 512 bytes of padding for a boot loader
+    for sb_offset in [0, 512] {
+        if file_size < sb_offset + 64 {continue}
+        let header = common::read_file(file, sb_offset, 32)?;
+        if &header[16..32] != CRAMFS_SIGNATURE {continue}
+
+        let magic: [u8; 4] = header[0..4].try_into()?;
+        if u32::from_le_bytes(magic) == CRAMFS_MAGIC {
+            return Ok(Some(Box::new(CramfsContext {sb_offset, endian: Endian::Little})))
+        } else if u32::from_be_bytes(magic) == CRAMFS_MAGIC {
+            return Ok(Some(Box::new(CramfsContext {sb_offset, endian: Endian::Big})))
+        }
+    }
+
+    Ok(None)
+}
+
+fn read_u32(data: &[u8], offset: u64, endian: Endian) -> Result<u32, Box<dyn std::error::Error>> {
+    let bytes: [u8; 4] = data.get(offset as usize..offset as usize + 4).ok_or("Offset out of bounds!")?.try_into()?;
+    Ok(match endian {
+        Endian::Little => u32::from_le_bytes(bytes),
+        Endian::Big => u32::from_be_bytes(bytes),
+    })
+}
+
+//compressed length in front of blocks with a direct pointer
+fn read_u16(data: &[u8], offset: u64, endian: Endian) -> Result<u16, Box<dyn std::error::Error>> {
+    let bytes: [u8; 2] = slice(data, offset, 2)?.try_into()?;
+    Ok(match endian {
+        Endian::Little => u16::from_le_bytes(bytes),
+        Endian::Big => u16::from_be_bytes(bytes),
+    })
+}
+
+fn slice(data: &[u8], offset: u64, len: u64) -> Result<&[u8], Box<dyn std::error::Error>> {
+    data.get(offset as usize..(offset + len) as usize).ok_or_else(|| "Offset out of bounds!".into())
+}
+
+//files are split into 4KB pages, each compressed separately and located through a pointer table to their ends
+fn read_file_data(data: &[u8], inode: &Inode, ext_pointers: bool, endian: Endian) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let size = inode.size as u64;
+    let block_count = size.div_ceil(PAGE_SIZE);
+    let mut out = Vec::with_capacity(size as usize);
+
+    for i in 0..block_count {
+        let mut block_ptr = read_u32(data, inode.offset + i * 4, endian)?;
+        let page_len = (size - i * PAGE_SIZE).min(PAGE_SIZE);
+        let mut uncompressed = false;
+
+        let (block_start, block_len) = if ext_pointers && (block_ptr & BLK_FLAG_DIRECT_PTR) != 0 {
+            uncompressed = (block_ptr & BLK_FLAG_UNCOMPRESSED) != 0;
+            let start = ((block_ptr & !BLK_FLAGS) as u64) << 2;
+            if uncompressed {
+                (start, page_len)
+            } else {
+                (start + 2, read_u16(data, start, endian)? as u64)
+            }
+        } else {
+            if ext_pointers {
+                uncompressed = (block_ptr & BLK_FLAG_UNCOMPRESSED) != 0;
+                block_ptr &= !BLK_FLAGS;
+            }
+            //a block starts where the previous one ended, the first one right after the table
+            let mut start = inode.offset + block_count * 4;
+            if i > 0 {
+                let prev_ptr = read_u32(data, inode.offset + (i - 1) * 4, endian)?;
+                if ext_pointers && (prev_ptr & BLK_FLAG_DIRECT_PTR) != 0 {
+                    let prev_start = ((prev_ptr & !BLK_FLAGS) as u64) << 2;
+                    start = if (prev_ptr & BLK_FLAG_UNCOMPRESSED) != 0 {
+                        prev_start + PAGE_SIZE
+                    } else {
+                        prev_start + 2 + read_u16(data, prev_start, endian)? as u64
+                    };
+                } else {
+                    start = (if ext_pointers {prev_ptr & !BLK_FLAGS} else {prev_ptr}) as u64;
+                }
+            }
+            (start, (block_ptr as u64).saturating_sub(start))
+        };
+
+        if block_len == 0 {
+            //hole
+            out.resize(out.len() + page_len as usize, 0);
+        } else if uncompressed {
+            out.extend_from_slice(slice(data, block_start, page_len)?);
+        } else {
+            let page = decompress_zlib(slice(data, block_start, block_len)?)?;
+            if (page.len() as u64) < page_len {
+                return Err(format!("Block {} decompressed to {} bytes, expected {}!", i, page.len(), page_len).into());
+            }
+            out.extend_from_slice(&page[..page_len as usize]);
+        }
+    }
+
+    Ok(out)
+}
+
+fn read_dir_entries(data: &[u8], dir: &Inode, endian: Endian) -> Result<Vec<(Inode, String)>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    let mut pos = dir.offset;
+    let end = dir.offset + dir.size as u64;
+
+    while pos < end {
+        let mut reader = Cursor::new(slice(data, pos, 12)?);
+        let raw: RawInode = reader.read_type(endian)?;
+        let inode = raw.decode(endian);
+        let name = common::string_from_bytes(slice(data, pos + 12, inode.name_len as u64)?);
+        pos += 12 + inode.name_len as u64;
+        entries.push((inode, name));
+    }
+
+    Ok(entries)
+}
+
+struct WalkState {
+    manifest: Manifest,
+    visited: HashSet<u64>,
+    file_count: u32,
+}
+
+fn walk_dir(data: &[u8], dir: &Inode, ext_pointers: bool, endian: Endian, rel_path: &Path, output_dir: &Path, state: &mut WalkState) -> Result<(), Box<dyn std::error::Error>> {
+    for (inode, name) in read_dir_entries(data, dir, endian)? {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            println!("- Warning: invalid entry name {:?}, skipping...", name);
+            continue
+        }
+        let entry_path = rel_path.join(&name);
+        let file_type = FileType::from_mode(inode.mode);
+
+        let mut extra = String::new();
+        let output_path = output_dir.join(&entry_path);
+        match file_type {
+            FileType::Directory => {
+                fs::create_dir_all(&output_path)?;
+            }
+            FileType::Regular => {
+                match read_file_data(data, &inode, ext_pointers, endian) {
+                    Ok(file_data) => {
+                        let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(&output_path)?;
+                        out_file.write_all(&file_data)?;
+                        state.file_count += 1;
+                    }
+                    Err(e) => println!("- Warning: failed to read {}: {}", entry_path.display(), e),
+                }
+            }
+            FileType::Symlink => {
+                let target = read_file_data(data, &inode, ext_pointers, endian)?;
+                extra.push_str(&format!(" -> {}", String::from_utf8_lossy(&target)));
+            }
+            FileType::CharDevice | FileType::BlockDevice => {
+                //size holds the old style 16 bit device number
+                extra.push_str(&format!(" [{},{}]", (inode.size >> 8) & 0xFF, inode.size & 0xFF));
+            }
+            _ => {}
+        }
+
+        let size = if matches!(file_type, FileType::CharDevice | FileType::BlockDevice) {0} else {inode.size as u64};
+        state.manifest.add_entry(file_type, inode.mode, (inode.uid, inode.gid), size, &entry_path, &extra);
+
+        if file_type == FileType::Directory && inode.size > 0 {
+            if !state.visited.insert(inode.offset) {
+                println!("- Warning: directory loop at {}, skipping...", entry_path.display());
+                continue
+            }
+            walk_dir(data, &inode, ext_pointers, endian, &entry_path, output_dir, state)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn extract_cramfs(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+    let ctx = ctx.downcast::<CramfsContext>().expect("Missing context");
+
+    //offsets in the image count from the start of the file, including any padding
+    let data = common::read_file(file, 0, file.metadata()?.len() as usize)?;
+    let mut sb_reader = Cursor::new(slice(&data, ctx.sb_offset, 76)?);
+    let sb: Superblock = sb_reader.read_type(ctx.endian)?;
+
+    println!("Filesystem info -\nName: {}\nEndianness: {}\nSize: {}\nEdition: {}\nFiles: {}\nBlocks: {}\nFlags: 0x{:x}",
+            sb.name(), if ctx.endian == Endian::Little {"Little"} else {"Big"}, sb.size, sb.edition, sb.files, sb.blocks, sb.flags);
+
+    //crc covers the filesystem from the superblock on with the crc field zeroed, size counts the padding too
+    if (sb.flags & FLAG_FSID_VERSION_2) != 0 {
+        match data.get(ctx.sb_offset as usize..sb.size as usize) {
+            Some(fs_data) if fs_data.len() >= 36 => {
+                let mut crc = flate2::Crc::new();
+                crc.update(&fs_data[..32]);
+                crc.update(&[0u8; 4]);
+                crc.update(&fs_data[36..]);
+                if crc.sum() == sb.crc {
+                    println!("- CRC OK");
+                } else {
+                    println!("- Warning: CRC mismatch, image may be corrupted! (expected 0x{:08x}, got 0x{:08x})", sb.crc, crc.sum());
+                }
+            }
+            _ => println!("- Warning: image is shorter than the filesystem size, it may be truncated!"),
+        }
+    }
+
+    let output_dir = PathBuf::from(&app_ctx.output_dir);
+    fs::create_dir_all(&output_dir)?;
+
+    let ext_pointers = (sb.flags & FLAG_EXT_BLOCK_POINTERS) != 0;
+    let root = sb.root.decode(ctx.endian);
+    let mut state = WalkState {manifest: Manifest::new(), visited: HashSet::new(), file_count: 0};
+    state.visited.insert(root.offset);
+    state.manifest.add_entry(FileType::Directory, root.mode, (root.uid, root.gid), root.size as u64, Path::new(""), "");
+
+    println!("\nExtracting files...");
+    walk_dir(&data, &root, ext_pointers, ctx.endian, Path::new(""), &output_dir, &mut state)?;
+    println!("- Extracted {} files, {} entries total", state.file_count, state.manifest.entry_count());
+    state.manifest.save(&output_dir)?;
+
+    Ok(())
+}
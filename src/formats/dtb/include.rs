@@ -0,0 +1,47 @@
+use binrw::BinRead;
+
+pub static AML_DTB_MAGIC: &[u8; 4] = b"AML_";
+//android dtb.img/dtbo.img table
+pub static DT_TABLE_MAGIC: &[u8; 4] = b"\xD7\xB7\xAB\x1E";
+
+//amlogic multi-dtb, written by their dtbTool
+#[derive(BinRead)]
+pub struct AmlDtbHeader {
+    _magic: u32,
+    pub version: u32,
+    pub entry_count: u32,
+}
+
+//soc, platform and variant names are stored as byte swapped words padded with spaces
+pub fn aml_id(bytes: &[u8]) -> String {
+    let swapped: Vec<u8> = bytes.chunks(4).flat_map(|w| w.iter().rev().copied()).collect();
+    String::from_utf8_lossy(&swapped).trim_matches(|c| c == ' ' || c == '\0').to_string()
+}
+
+#[derive(BinRead)]
+pub struct DtTableHeader {
+    _magic: u32,
+    pub total_size: u32,
+    _header_size: u32,
+    pub entry_size: u32,
+    pub entry_count: u32,
+    pub entries_offset: u32,
+    pub page_size: u32,
+    pub version: u32,
+}
+
+#[derive(BinRead)]
+pub struct DtTableEntry {
+    pub size: u32,
+    pub offset: u32,
+    pub id: u32,
+    pub rev: u32,
+    //only used for compression since v1, custom data before that
+    pub flags: u32,
+    _custom: [u32; 3],
+}
+impl DtTableEntry {
+    pub fn compression(&self, table_version: u32) -> u32 {
+        if table_version >= 1 {self.flags & 0xF} else {0}
+    }
+}
@@ -0,0 +1,205 @@
+mod include;
+use std::any::Any;
+use crate::AppContext;
+
+use std::path::Path;
+use std::fs::{self, OpenOptions};
+use std::io::{Cursor, Read, Write};
+use binrw::BinReaderExt;
+use flate2::read::GzDecoder;
+
+use crate::utils::common;
+use crate::utils::compression::{decompress_gzip, decompress_zlib};
+use crate::utils::fdt::{self, FDT_MAGIC};
+use include::*;
+
+pub struct DtbContext {
+    gzip: bool,
+}
+
+pub struct Dtb {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+fn is_dtb_container(magic: &[u8]) -> bool {
+    magic == FDT_MAGIC || magic == AML_DTB_MAGIC || magic == DT_TABLE_MAGIC
+}
+
+pub fn is_dtb_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let header = common::read_file(file, 0, 0x10000)?;
+    if is_dtb_container(&header[0..4]) {
+        return Ok(Some(Box::new(DtbContext {gzip: false})))
+    }
+
+    //amlogic _aml_dtb partitions are often gzip compressed, only the start is needed to check what is inside
+    if header.starts_with(b"\x1F\x8B") {
+        let mut magic = [0u8; 4];
+        if GzDecoder::new(header.as_slice()).read_exact(&mut magic).is_ok() && is_dtb_container(&magic) {
+            return Ok(Some(Box::new(DtbContext {gzip: true})))
+        }
+    }
+
+    Ok(None)
+}
+
+fn split_aml(data: &[u8]) -> Result<Vec<Dtb>, Box<dyn std::error::Error>> {
+    let header: AmlDtbHeader = Cursor::new(data).read_le()?;
+    let id_len = match header.version {
+        1 => 4,
+        2 => 16,
+        _ => return Err(format!("Unsupported AML multi-DTB version {}!", header.version).into()),
+    };
+    println!("AML multi-DTB info -\nVersion: {}\nDTB count: {}", header.version, header.entry_count);
+
+    let entry_size = id_len * 3 + 8;
+    let mut dtbs = Vec::new();
+    for i in 0..header.entry_count as usize {
+        let entry_offset = 12 + i * entry_size;
+        let entry = data.get(entry_offset..entry_offset + entry_size).ok_or("AML multi-DTB entry out of bounds!")?;
+        let soc = aml_id(&entry[0..id_len]);
+        let platform = aml_id(&entry[id_len..id_len * 2]);
+        let variant = aml_id(&entry[id_len * 2..id_len * 3]);
+        let offset = u32::from_le_bytes(entry[id_len * 3..id_len * 3 + 4].try_into()?) as usize;
+        let size = u32::from_le_bytes(entry[id_len * 3 + 4..id_len * 3 + 8].try_into()?) as usize;
+
+        let dtb_data = data.get(offset..offset + size).ok_or("AML multi-DTB entry data out of bounds!")?;
+        dtbs.push(Dtb {name: format!("{}_{}_{}", soc, platform, variant), data: dtb_data.to_vec()});
+    }
+
+    Ok(dtbs)
+}
+
+fn split_dt_table(data: &[u8]) -> Result<Vec<Dtb>, Box<dyn std::error::Error>> {
+    let header: DtTableHeader = Cursor::new(data).read_be()?;
+    println!("DT table info -\nVersion: {}\nTotal size: {}\nPage size: {}\nEntry count: {}",
+            header.version, header.total_size, header.page_size, header.entry_count);
+
+    let mut dtbs = Vec::new();
+    for i in 0..header.entry_count {
+        let entry_offset = header.entries_offset as u64 + i as u64 * header.entry_size as u64;
+        let entry: DtTableEntry = Cursor::new(usize::try_from(entry_offset).ok().and_then(|o| data.get(o..)).ok_or("DT table entry out of bounds!")?).read_be()?;
+        let start = entry.offset as usize;
+        let entry_data = data.get(start..start + entry.size as usize).ok_or("DT table entry data out of bounds!")?;
+
+        let dtb_data = match entry.compression(header.version) {
+            0 => entry_data.to_vec(),
+            1 => decompress_zlib(entry_data)?,
+            2 => decompress_gzip(entry_data)?,
+            c => return Err(format!("Unsupported DT table entry compression {}!", c).into()),
+        };
+        dtbs.push(Dtb {name: format!("dt_{}_id{:x}_rev{:x}", i + 1, entry.id, entry.rev), data: dtb_data});
+    }
+
+    Ok(dtbs)
+}
+
+//plain DTBs may be appended one after another, sometimes with padding in between
+fn split_concatenated(data: &[u8]) -> Result<Vec<Dtb>, Box<dyn std::error::Error>> {
+    let mut blobs = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() && &data[pos..pos + 4] == FDT_MAGIC {
+        let total_size = u32::from_be_bytes(data[pos + 4..pos + 8].try_into()?) as usize;
+        if total_size < 40 {
+            return Err(format!("Invalid device tree size {}!", total_size).into());
+        }
+        let blob = data.get(pos..pos + total_size).ok_or("Device tree is truncated!")?;
+        blobs.push(blob.to_vec());
+        pos += total_size;
+        while pos < data.len() && data[pos] == 0 {
+            pos += 1;
+        }
+    }
+    if pos < data.len() {
+        println!("- Warning: {} bytes of unknown data after the last device tree", data.len() - pos);
+    }
+
+    if blobs.len() == 1 {
+        return Ok(vec![Dtb {name: "devicetree".to_string(), data: blobs.remove(0)}])
+    }
+    Ok(blobs.into_iter().enumerate().map(|(i, data)| Dtb {name: format!("dtb_{}", i + 1), data}).collect())
+}
+
+//splits any supported container into the device trees inside
+pub fn split_dtbs(data: &[u8]) -> Result<Vec<Dtb>, Box<dyn std::error::Error>> {
+    if data.starts_with(b"\x1F\x8B") {
+        println!("- Decompressing gzip...");
+        return split_dtbs(&decompress_gzip(data)?)
+    }
+    match data.get(0..4) {
+        Some(magic) if magic == AML_DTB_MAGIC => split_aml(data),
+        Some(magic) if magic == DT_TABLE_MAGIC => split_dt_table(data),
+        Some(magic) if magic == FDT_MAGIC => split_concatenated(data),
+        _ => Err("Unknown device tree container!".into()),
+    }
+}
+
+fn output_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {c} else {'_'}).collect()
+}
+
+//prints what board the tree describes and writes it as DTS text, the blob itself is only written when save_blob is set
+pub fn save_dtb(dtb: &Dtb, output_dir: &Path, save_blob: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let name = output_name(&dtb.name);
+    if save_blob {
+        let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_dir.join(format!("{}.dtb", name)))?;
+        out_file.write_all(&dtb.data)?;
+    }
+
+    match fdt::parse_fdt(&dtb.data) {
+        Ok((_, root)) => {
+            let compatible: Vec<String> = root.prop("compatible").unwrap_or_default().split(|&b| b == 0).filter(|s| !s.is_empty())
+                .map(|s| String::from_utf8_lossy(s).to_string()).collect();
+            println!("- Model: {}\n- Compatible: {}", root.prop_str("model").unwrap_or_default(), compatible.join(", "));
+            let dts = fdt::to_dts(&dtb.data)?;
+            fs::write(output_dir.join(format!("{}.dts", name)), dts)?;
+            println!("-- Saved file!");
+        }
+        Err(e) => println!("- Warning: failed to parse device tree ({}), not decoding", e),
+    }
+
+    Ok(())
+}
+
+//for dtbs embedded in other images which are already saved, only writes the DTS text next to them
+pub fn decode_embedded_dtbs(data: &[u8], output_dir: &Path, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dtbs = match split_dtbs(data) {
+        Ok(d) => d,
+        Err(e) => {
+            println!("- Warning: failed to read device tree ({}), not decoding", e);
+            return Ok(())
+        }
+    };
+
+    let count = dtbs.len();
+    for (i, dtb) in dtbs.iter_mut().enumerate() {
+        dtb.name = if count == 1 {name.to_string()} else {format!("{}_{}", name, i + 1)};
+        save_dtb(dtb, output_dir, false)?;
+    }
+
+    Ok(())
+}
+
+pub fn extract_dtb(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+    let ctx = ctx.downcast::<DtbContext>().expect("Missing context");
+
+    let data = common::read_file(file, 0, file.metadata()?.len() as usize)?;
+    let data = if ctx.gzip {
+        println!("- Decompressing gzip...");
+        decompress_gzip(&data)?
+    } else {
+        data
+    };
+    let dtbs = split_dtbs(&data)?;
+    fs::create_dir_all(&app_ctx.output_dir)?;
+
+    for (i, dtb) in dtbs.iter().enumerate() {
+        println!("\n({}/{}) - {}, Size: {}", i + 1, dtbs.len(), dtb.name, dtb.data.len());
+        save_dtb(dtb, &app_ctx.output_dir, true)?;
+    }
+
+    Ok(())
+}
@@ -1,9 +1,12 @@
 use std::any::Any;
-use std::io::Seek;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
 
 use crate::utils::aes::{decrypt_aes128_ecb, decrypt_aes256_ecb};
 use crate::utils::common;
+use crate::utils::keytrial::find_matching_key;
 use crate::formats;
 
 pub struct EpkContext {
@@ -13,7 +16,7 @@ pub struct EpkContext {
 pub fn is_epk_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
 
-    let versions = common::read_file(&file, 1712, 36)?;
+    let versions = common::read_file(file, 1712, 36)?;
     if let Some(epk_version) = check_epk_version(&versions) {
         Ok(Some(Box::new(EpkContext {epk_version})))
     } else {
@@ -57,7 +60,7 @@ pub fn extract_epk(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dy
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<EpkContext>().expect("Missing context");
 
-    let versions = common::read_file(&file, 1712, 36)?;
+    let versions = common::read_file(file, 1712, 36)?;
 
     let platform_version = common::string_from_bytes(&versions[4..20]);
     let sdk_version = common::string_from_bytes(&versions[20..36]);
@@ -76,20 +79,59 @@ pub fn extract_epk(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dy
     Ok(())
 }
 
+pub fn find_epk_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Expected file")?;
+
+    let versions = common::read_file(file, 1712, 36)?;
+    match check_epk_version(&versions) {
+        Some(2) => formats::epk2::find_epk2_key(app_ctx),
+        Some(3) => formats::epk3::find_epk3_key(app_ctx),
+        _ => Ok(None),
+    }
+}
+
+pub fn hunt_epk_key(app_ctx: &AppContext) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let versions = common::read_file(file, 1712, 36)?;
+    match check_epk_version(&versions) {
+        Some(2) => formats::epk2::epk2_hunt_target(file),
+        Some(3) => formats::epk3::epk3_hunt_target(file),
+        _ => Ok(None),
+    }
+}
+
 //COMMON EPK FUNCTIONS
-pub fn find_key(key_array: &Vec<(String, Vec<Vec<u8>>)>, data: &[u8], expected_magic: &[u8]) -> Result<Option<(String, Vec<u8>)>, Box<dyn std::error::Error>> {
-    for (name, keys) in key_array {
+//the platform version is the only thing stored in plain, so it is what key hints are saved for
+fn epk_fingerprint(mut file: &File) -> Result<String, Box<dyn std::error::Error>> {
+    let position = file.stream_position()?;
+    let versions = common::read_file(file, 1712, 36)?;
+    file.seek(SeekFrom::Start(position))?;
+    Ok(common::string_from_bytes(&versions[4..20]))
+}
+
+//entry name and key
+pub type EpkKey = (String, Vec<u8>);
+
+pub fn find_key(app_ctx: &AppContext, data: &[u8], expected_magic: &[u8]) -> Result<Option<EpkKey>, Box<dyn std::error::Error>> {
+    let fingerprint = epk_fingerprint(app_ctx.file().ok_or("Expected file")?)?;
+    //ECB, so only the blocks holding the magic need to be decrypted
+    let block = &data[..(expected_magic.len().div_ceil(16) * 16).min(data.len())];
+    let found = find_matching_key(&app_ctx.get_hinted_collection("epk", &fingerprint, "EPK")?, |keys| {
         let key_bytes = keys.first().unwrap();
-        let decrypted = match decrypt_aes_ecb_auto(&key_bytes, data) {
-            Ok(d) => d,
-            Err(_) => continue,
-        };
-     
-        if decrypted.starts_with(expected_magic) {
-            return Ok(Some((name.to_string(), key_bytes.to_vec())));
-        }
+        decrypt_aes_ecb_auto(key_bytes, block).ok()?.starts_with(expected_magic).then(|| key_bytes.to_vec())
+    });
+    if let Some((name, _)) = &found {
+        app_ctx.remember_key("epk", &fingerprint, "EPK", name);
     }
-    Ok(None)
+    Ok(found)
+}
+
+//ECB blocks decrypt independently, so checking the block with the magic is enough
+pub fn epk_hunt_target(block: Vec<u8>, expected_magic: Vec<u8>) -> HuntTarget {
+    HuntTarget::new("EPK", vec![vec![16], vec![32]], move |key| {
+        decrypt_aes_ecb_auto(key, &block).is_ok_and(|d| d.starts_with(&expected_magic))
+    })
 }
 
 pub fn decrypt_aes_ecb_auto(key: &[u8], encrypted_data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
@@ -1,15 +1,16 @@
 mod include;
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
 
-use std::fs::{self, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::path::Path;
 use std::io::{Write, Seek, SeekFrom, Cursor};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
 use crate::utils::global::opt_dump_dec_hdr;
-use crate::formats::epk::{decrypt_aes_ecb_auto, find_key};
+use crate::formats::epk::{decrypt_aes_ecb_auto, epk_hunt_target, find_key};
 use include::*;
 
 pub fn is_epk2_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
@@ -23,6 +24,43 @@ pub fn is_epk2_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dy
     }
 }
 
+//the header starts with "epak" once decrypted, when it is stored in plain the first pak header is used instead
+pub fn find_epk2_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Expected file")?;
+
+    let stored_header = common::read_file(file, SIGNATURE_SIZE as u64, 1584)?;
+    if &stored_header[0..4] != b"epak" {
+        return Ok(find_key(app_ctx, &stored_header, b"epak")?.map(|(key_name, _)| format!("EPK \"{}\"", key_name)))
+    }
+
+    let mut hdr_reader = Cursor::new(&stored_header);
+    let hdr: Header = hdr_reader.read_le()?;
+    if hdr.pak_count == 0 {
+        return Ok(None)
+    }
+    let pak: PakEntry = hdr_reader.read_le()?;
+    //the first pak starts after the file signature and its own segment signature
+    let encrypted_header = common::read_file(file, (pak.offset + SIGNATURE_SIZE * 2) as u64, 128)?;
+    Ok(find_key(app_ctx, &encrypted_header, pak.name().as_bytes())?.map(|(key_name, _)| format!("EPK \"{}\"", key_name)))
+}
+
+//same known plaintext as find_epk2_key
+pub fn epk2_hunt_target(file: &File) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let stored_header = common::read_file(file, SIGNATURE_SIZE as u64, 1584)?;
+    if &stored_header[0..4] != b"epak" {
+        return Ok(Some(epk_hunt_target(stored_header[0..16].to_vec(), b"epak".to_vec())))
+    }
+
+    let mut hdr_reader = Cursor::new(&stored_header);
+    let hdr: Header = hdr_reader.read_le()?;
+    if hdr.pak_count == 0 {
+        return Ok(None)
+    }
+    let pak: PakEntry = hdr_reader.read_le()?;
+    let encrypted_header = common::read_file(file, (pak.offset + SIGNATURE_SIZE * 2) as u64, 16)?;
+    Ok(Some(epk_hunt_target(encrypted_header, pak.name().as_bytes().to_vec())))
+}
+
 pub fn extract_epk2(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
 
@@ -41,7 +79,7 @@ pub fn extract_epk2(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
         println!("Header is encrypted...");
         println!("\nFinding key...");
         //find the key, knowing that the header should start with "epak"
-        if let Some((key_name, key_bytes)) = find_key(app_ctx.keys.get_collection("EPK")?, &stored_header, b"epak")? {
+        if let Some((key_name, key_bytes)) = find_key(app_ctx, &stored_header, b"epak")? {
             println!("Found valid key: {}", key_name);
             matching_key = Some(key_bytes);
             header = decrypt_aes_ecb_auto(matching_key.as_ref().unwrap(), &stored_header)?;
@@ -82,7 +120,7 @@ pub fn extract_epk2(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
         if matching_key.is_none() {
             println!("\nFinding key...");
             //find the key, knowing that the header should start with with the paks name
-            if let Some((key_name, key_bytes)) = find_key(app_ctx.keys.get_collection("EPK")?, &encrypted_header, pak.name.as_bytes())? {
+            if let Some((key_name, key_bytes)) = find_key(app_ctx, &encrypted_header, pak.name.as_bytes())? {
                 println!("Found correct key: {}", key_name);
                 matching_key = Some(key_bytes);
             } else {
@@ -1,21 +1,43 @@
 mod include;
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
 
-use std::fs::{self, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::path::Path;
 use std::io::{Write, Cursor};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
 use crate::utils::global::opt_dump_dec_hdr;
-use crate::formats::epk::{decrypt_aes_ecb_auto, find_key};
+use crate::formats::epk::{decrypt_aes_ecb_auto, epk_hunt_target, find_key};
 use include::*;
 
 pub fn is_epk3_file(_app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
     Ok(None)
 }
 
+//old type has a 128 byte signature before the header, new type 256
+pub fn find_epk3_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Expected file")?;
+
+    let stored_header = common::read_file(file, 0, 1712)?;
+    for signature_size in [128, 256] {
+        if let Some((key_name, _)) = find_key(app_ctx, &stored_header[signature_size..], b"EPK3")? {
+            return Ok(Some(format!("EPK \"{}\"", key_name)))
+        }
+    }
+    Ok(None)
+}
+
+//the signature size is not known without the key, so a candidate may match after either
+pub fn epk3_hunt_target(file: &File) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let stored_header = common::read_file(file, 0, 272)?;
+    let old = epk_hunt_target(stored_header[128..144].to_vec(), b"EPK3".to_vec());
+    let new = epk_hunt_target(stored_header[256..272].to_vec(), b"EPK3".to_vec());
+    Ok(Some(HuntTarget::new("EPK", old.layouts.clone(), move |key| (old.check)(key) || (new.check)(key))))
+}
+
 pub fn extract_epk3(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
 
@@ -28,7 +50,7 @@ pub fn extract_epk3(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
     println!("Finding key...");
 
     // find the key, knowing that the header should start with "EPK3" (old type 128 byte signature)
-    if let Some((key_name, key_bytes)) = find_key(app_ctx.keys.get_collection("EPK")?, &stored_header[128..], b"EPK3")? {
+    if let Some((key_name, key_bytes)) = find_key(app_ctx, &stored_header[128..], b"EPK3")? {
         println!("Found valid key: {}", key_name);
         matching_key = Some(key_bytes);
         _header_signature = &stored_header[..128];
@@ -36,7 +58,7 @@ pub fn extract_epk3(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
         opt_dump_dec_hdr(app_ctx, &header, "header")?;
 
     //try for new format epk3 (new type 256 byte signature)
-    } else if let Some((key_name, key_bytes)) = find_key(app_ctx.keys.get_collection("EPK")?, &stored_header[256..], b"EPK3")? {
+    } else if let Some((key_name, key_bytes)) = find_key(app_ctx, &stored_header[256..], b"EPK3")? {
         println!("Found valid key: {}", key_name);
         matching_key = Some(key_bytes);
         _header_signature = &stored_header[..256];
@@ -0,0 +1,279 @@
+use crate::utils::common;
+use crate::utils::manifest::FileType;
+use binrw::BinRead;
+
+pub static SUPERBLOCK_OFFSET: u64 = 1024;
+pub static EXT_MAGIC: &[u8; 2] = b"\x53\xEF";
+pub static ROOT_INODE: u32 = 2;
+
+//feature flags
+pub static INCOMPAT_FILETYPE: u32 = 0x0002;
+pub static INCOMPAT_META_BG: u32 = 0x0010;
+pub static INCOMPAT_EXTENTS: u32 = 0x0040;
+pub static INCOMPAT_64BIT: u32 = 0x0080;
+pub static INCOMPAT_INLINE_DATA: u32 = 0x8000;
+pub static COMPAT_HAS_JOURNAL: u32 = 0x0004;
+
+//inode flags
+pub static INODE_FLAG_ENCRYPT: u32 = 0x00000800;
+pub static INODE_FLAG_EXTENTS: u32 = 0x00080000;
+pub static INODE_FLAG_INLINE_DATA: u32 = 0x10000000;
+
+pub static EXTENT_MAGIC: u16 = 0xF30A;
+pub static XATTR_MAGIC: u32 = 0xEA020000;
+
+#[derive(BinRead)]
+pub struct Superblock {
+    pub inodes_count: u32,
+    pub blocks_count_lo: u32,
+    _r_blocks_count_lo: u32,
+    _free_blocks_count_lo: u32,
+    _free_inodes_count: u32,
+    pub first_data_block: u32,
+    pub log_block_size: u32,
+    _log_cluster_size: u32,
+    pub blocks_per_group: u32,
+    _clusters_per_group: u32,
+    pub inodes_per_group: u32,
+    _mtime: u32,
+    _wtime: u32,
+    _mnt_count: u16,
+    _max_mnt_count: u16,
+    _magic: u16,                    //0xEF53
+    _state: u16,
+    _errors: u16,
+    _minor_rev_level: u16,
+    _lastcheck: u32,
+    _checkinterval: u32,
+    _creator_os: u32,
+    pub rev_level: u32,             //0 = original format with fixed 128 byte inodes
+    _def_resuid: u16,
+    _def_resgid: u16,
+    _first_ino: u32,
+    inode_size: u16,
+    _block_group_nr: u16,
+    pub feature_compat: u32,
+    pub feature_incompat: u32,
+    pub feature_ro_compat: u32,
+    pub uuid: [u8; 16],
+    volume_name_bytes: [u8; 16],
+    last_mounted_bytes: [u8; 64],
+    _algorithm_usage_bitmap: u32,
+    _prealloc_blocks: u8,
+    _prealloc_dir_blocks: u8,
+    _reserved_gdt_blocks: u16,
+    _journal_uuid: [u8; 16],
+    _journal_inum: u32,
+    _journal_dev: u32,
+    _last_orphan: u32,
+    _hash_seed: [u32; 4],
+    _def_hash_version: u8,
+    _jnl_backup_type: u8,
+    desc_size: u16,
+    _default_mount_opts: u32,
+    pub first_meta_bg: u32,
+    _unused: [u8; 72],
+    pub blocks_count_hi: u32,
+}
+impl Superblock {
+    pub fn block_size(&self) -> u64 {
+        1024 << self.log_block_size
+    }
+    pub fn inode_size(&self) -> u64 {
+        if self.rev_level == 0 {128} else {self.inode_size as u64}
+    }
+    pub fn is_64bit(&self) -> bool {
+        (self.feature_incompat & INCOMPAT_64BIT) != 0
+    }
+    pub fn desc_size(&self) -> u64 {
+        if self.is_64bit() && self.desc_size >= 64 {self.desc_size as u64} else {32}
+    }
+    pub fn blocks_count(&self) -> u64 {
+        let hi = if self.is_64bit() {self.blocks_count_hi as u64} else {0};
+        (hi << 32) | self.blocks_count_lo as u64
+    }
+    //the layout is computed from these, so they have to be usable and the group descriptors have to fit the image
+    pub fn is_valid(&self, file_size: u64) -> bool {
+        self.blocks_per_group != 0 && self.inodes_per_group != 0 && self.log_block_size <= 6
+            && self.blocks_count() > self.first_data_block as u64
+            && self.group_count().saturating_mul(self.desc_size()) <= file_size
+    }
+    pub fn group_count(&self) -> u64 {
+        (self.blocks_count() - self.first_data_block as u64).div_ceil(self.blocks_per_group as u64)
+    }
+    pub fn has_filetype(&self) -> bool {
+        (self.feature_incompat & INCOMPAT_FILETYPE) != 0
+    }
+    pub fn volume_name(&self) -> String {
+        common::string_from_bytes(&self.volume_name_bytes)
+    }
+    pub fn last_mounted(&self) -> String {
+        common::string_from_bytes(&self.last_mounted_bytes)
+    }
+    //ext2 has no journal, ext3 adds it, extents/64bit/flex_bg and friends mean ext4
+    pub fn fs_type(&self) -> &str {
+        if (self.feature_incompat & !(INCOMPAT_FILETYPE | INCOMPAT_META_BG | 0x0004)) != 0 {
+            "ext4"
+        } else if (self.feature_compat & COMPAT_HAS_JOURNAL) != 0 {
+            "ext3"
+        } else {
+            "ext2"
+        }
+    }
+}
+
+#[derive(BinRead)]
+pub struct GroupDesc {
+    _block_bitmap_lo: u32,
+    _inode_bitmap_lo: u32,
+    pub inode_table_lo: u32,
+    _rest: [u8; 20],
+}
+
+#[derive(BinRead)]
+pub struct GroupDescHi {
+    _block_bitmap_hi: u32,
+    _inode_bitmap_hi: u32,
+    pub inode_table_hi: u32,
+}
+
+#[derive(BinRead)]
+pub struct Inode {
+    pub mode: u16,
+    uid_lo: u16,
+    size_lo: u32,
+    _atime: u32,
+    _ctime: u32,
+    _mtime: u32,
+    _dtime: u32,
+    gid_lo: u16,
+    pub links_count: u16,
+    _blocks_lo: u32,
+    pub flags: u32,
+    _osd1: u32,
+    pub block: [u8; 60],            //block map, extent tree root, inline data or fast symlink target
+    _generation: u32,
+    file_acl_lo: u32,
+    size_high: u32,
+    _obso_faddr: u32,
+    _blocks_high: u16,
+    file_acl_high: u16,
+    uid_high: u16,
+    gid_high: u16,
+    _checksum_lo: u16,
+    _reserved: u16,
+}
+impl Inode {
+    pub fn size(&self) -> u64 {
+        ((self.size_high as u64) << 32) | self.size_lo as u64
+    }
+    pub fn uid(&self) -> u32 {
+        ((self.uid_high as u32) << 16) | self.uid_lo as u32
+    }
+    pub fn gid(&self) -> u32 {
+        ((self.gid_high as u32) << 16) | self.gid_lo as u32
+    }
+    pub fn file_acl(&self) -> u64 {
+        ((self.file_acl_high as u64) << 32) | self.file_acl_lo as u64
+    }
+    pub fn file_type(&self) -> FileType {
+        FileType::from_mode(self.mode as u32)
+    }
+    pub fn has_extents(&self) -> bool {
+        (self.flags & INODE_FLAG_EXTENTS) != 0
+    }
+    pub fn has_inline_data(&self) -> bool {
+        (self.flags & INODE_FLAG_INLINE_DATA) != 0
+    }
+    pub fn is_encrypted(&self) -> bool {
+        (self.flags & INODE_FLAG_ENCRYPT) != 0
+    }
+    pub fn block_u32(&self, idx: usize) -> u32 {
+        u32::from_le_bytes(self.block[idx * 4..idx * 4 + 4].try_into().unwrap())
+    }
+    //old and new style device number encoding
+    pub fn device_number(&self) -> (u32, u32) {
+        let old = self.block_u32(0);
+        if old != 0 {
+            ((old >> 8) & 0xFF, old & 0xFF)
+        } else {
+            let new = self.block_u32(1);
+            ((new & 0xFFF00) >> 8, (new & 0xFF) | ((new >> 12) & 0xFFF00))
+        }
+    }
+}
+
+#[derive(BinRead)]
+pub struct ExtentHeader {
+    pub magic: u16,             //0xF30A
+    pub entries: u16,
+    _max: u16,
+    pub depth: u16,             //0 = entries are leaves
+    _generation: u32,
+}
+
+#[derive(BinRead)]
+pub struct ExtentIndex {
+    _block: u32,
+    leaf_lo: u32,
+    leaf_hi: u16,
+    _unused: u16,
+}
+impl ExtentIndex {
+    pub fn leaf(&self) -> u64 {
+        ((self.leaf_hi as u64) << 32) | self.leaf_lo as u64
+    }
+}
+
+#[derive(BinRead)]
+pub struct Extent {
+    pub block: u32,             //first logical block
+    len: u16,
+    start_hi: u16,
+    start_lo: u32,
+}
+impl Extent {
+    pub fn start(&self) -> u64 {
+        ((self.start_hi as u64) << 32) | self.start_lo as u64
+    }
+    //lengths above 32768 mark preallocated, uninitialized extents that read back as zeros
+    pub fn len(&self) -> u64 {
+        if self.len > 32768 {(self.len - 32768) as u64} else {self.len as u64}
+    }
+    pub fn is_uninit(&self) -> bool {
+        self.len > 32768
+    }
+}
+
+#[derive(BinRead)]
+pub struct XattrEntry {
+    pub name_len: u8,
+    pub name_index: u8,
+    pub value_offs: u16,
+    pub value_inum: u32,        //value stored in a separate inode (ea_inode feature)
+    pub value_size: u32,
+    _hash: u32,
+    #[br(count = name_len)] name_bytes: Vec<u8>,
+}
+impl XattrEntry {
+    pub fn is_end(&self) -> bool {
+        self.name_len == 0 && self.name_index == 0 && self.value_offs == 0 && self.value_inum == 0
+    }
+    //entries are padded to 4 bytes
+    pub fn entry_size(&self) -> u64 {
+        (16 + self.name_len as u64 + 3) & !3
+    }
+    pub fn full_name(&self) -> String {
+        let prefix = match self.name_index {
+            1 => "user.",
+            2 => "system.posix_acl_access",
+            3 => "system.posix_acl_default",
+            4 => "trusted.",
+            6 => "security.",
+            7 => "system.",
+            8 => "system.richacl",
+            _ => "",
+        };
+        format!("{}{}", prefix, String::from_utf8_lossy(&self.name_bytes))
+    }
+}
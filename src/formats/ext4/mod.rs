@@ -0,0 +1,447 @@
+mod include;
+use std::any::Any;
+use crate::AppContext;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use binrw::BinReaderExt;
+
+use crate::utils::common;
+use crate::utils::manifest::{Manifest, FileType};
+use include::*;
+
+pub fn is_ext4_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let mut file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let magic = common::read_file(file, SUPERBLOCK_OFFSET + 0x38, 2)?;
+    if magic != EXT_MAGIC {
+        return Ok(None)
+    }
+
+    //the magic alone is only two bytes, garbage superblocks are not extracted
+    file.seek(SeekFrom::Start(SUPERBLOCK_OFFSET))?;
+    let Ok(sb) = file.read_le::<Superblock>() else {
+        return Ok(None)
+    };
+    if sb.is_valid(file.metadata()?.len()) {
+        Ok(Some(Box::new(())))
+    } else {
+        Ok(None)
+    }
+}
+
+//(logical block, physical block, length), physical 0 is a hole
+type BlockRun = (u64, u64, u64);
+//(full name, value)
+type Xattr = (String, Vec<u8>);
+
+struct ExtFs<'a> {
+    file: &'a File,
+    sb: Superblock,
+    inode_tables: Vec<u64>,
+}
+impl<'a> ExtFs<'a> {
+    fn open(mut file: &'a File) -> Result<Self, Box<dyn std::error::Error>> {
+        file.seek(SeekFrom::Start(SUPERBLOCK_OFFSET))?;
+        let sb: Superblock = file.read_le()?;
+
+        let mut fs = ExtFs {file, sb, inode_tables: Vec::new()};
+        fs.inode_tables = fs.read_group_descs()?;
+        Ok(fs)
+    }
+
+    fn block_size(&self) -> u64 {
+        self.sb.block_size()
+    }
+
+    fn read_blocks(&self, block: u64, count: u64) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        common::read_file(self.file, block * self.block_size(), (count * self.block_size()) as usize)
+    }
+
+    //location of the group descriptors depends on meta_bg, without it they follow the superblock
+    fn read_group_descs(&self) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        let desc_size = self.sb.desc_size();
+        let descs_per_block = self.block_size() / desc_size;
+        let meta_bg = (self.sb.feature_incompat & INCOMPAT_META_BG) != 0;
+
+        let mut tables = Vec::new();
+        for group in 0..self.sb.group_count() {
+            let desc_offset = if meta_bg && group / descs_per_block >= self.sb.first_meta_bg as u64 {
+                //descriptors of a meta group live in its first group, after the backup superblock if there is one
+                let first_group = (group / descs_per_block) * descs_per_block;
+                let group_start = first_group * self.sb.blocks_per_group as u64 + self.sb.first_data_block as u64;
+                let has_sb = if self.group_has_superblock(first_group) {1} else {0};
+                (group_start + has_sb) * self.block_size() + (group % descs_per_block) * desc_size
+            } else {
+                (self.sb.first_data_block as u64 + 1) * self.block_size() + group * desc_size
+            };
+
+            let desc_bytes = common::read_file(self.file, desc_offset, desc_size as usize)?;
+            let mut desc_reader = Cursor::new(&desc_bytes);
+            let desc: GroupDesc = desc_reader.read_le()?;
+            let mut inode_table = desc.inode_table_lo as u64;
+            if desc_size >= 64 {
+                desc_reader.seek(SeekFrom::Start(0x20))?;
+                let desc_hi: GroupDescHi = desc_reader.read_le()?;
+                inode_table |= (desc_hi.inode_table_hi as u64) << 32;
+            }
+            tables.push(inode_table);
+        }
+
+        Ok(tables)
+    }
+
+    //sparse_super: backups only in groups 0, 1 and powers of 3, 5 and 7
+    fn group_has_superblock(&self, group: u64) -> bool {
+        if (self.sb.feature_ro_compat & 0x0001) == 0 || group <= 1 {
+            return true
+        }
+        [3, 5, 7].iter().any(|&base| {
+            let mut n = base;
+            while n < group {n *= base}
+            n == group
+        })
+    }
+
+    fn read_inode(&self, ino: u32) -> Result<(Inode, Vec<u8>), Box<dyn std::error::Error>> {
+        if ino == 0 || ino > self.sb.inodes_count {
+            return Err(format!("Invalid inode number {}!", ino).into());
+        }
+        let group = (ino - 1) / self.sb.inodes_per_group;
+        let index = (ino - 1) % self.sb.inodes_per_group;
+        let table = self.inode_tables.get(group as usize).ok_or("Inode group out of range!")?;
+
+        let offset = table * self.block_size() + index as u64 * self.sb.inode_size();
+        let raw = common::read_file(self.file, offset, self.sb.inode_size() as usize)?;
+        let inode: Inode = Cursor::new(&raw).read_le()?;
+        Ok((inode, raw))
+    }
+
+    fn block_runs(&self, inode: &Inode) -> Result<Vec<BlockRun>, Box<dyn std::error::Error>> {
+        let mut runs = Vec::new();
+        if inode.has_extents() {
+            self.walk_extent_node(&inode.block, &mut runs, 0)?;
+        } else {
+            let block_count = inode.size().div_ceil(self.block_size());
+            let mut logical = 0;
+            for i in 0..12 {
+                if logical >= block_count {break}
+                push_run(&mut runs, logical, inode.block_u32(i) as u64, 1);
+                logical += 1;
+            }
+            for (i, depth) in [(12, 1), (13, 2), (14, 3)] {
+                if logical >= block_count {break}
+                self.walk_indirect(inode.block_u32(i) as u64, depth, &mut logical, block_count, &mut runs)?;
+            }
+        }
+        Ok(runs)
+    }
+
+    fn walk_extent_node(&self, node: &[u8], runs: &mut Vec<BlockRun>, level: u32) -> Result<(), Box<dyn std::error::Error>> {
+        if level > 5 {
+            return Err("Extent tree is too deep!".into());
+        }
+        let mut reader = Cursor::new(node);
+        let header: ExtentHeader = reader.read_le()?;
+        if header.magic != EXTENT_MAGIC {
+            return Err("Invalid extent header magic!".into());
+        }
+
+        for _ in 0..header.entries {
+            if header.depth == 0 {
+                let extent: Extent = reader.read_le()?;
+                let physical = if extent.is_uninit() {0} else {extent.start()};
+                push_run(runs, extent.block as u64, physical, extent.len());
+            } else {
+                let index: ExtentIndex = reader.read_le()?;
+                let child = self.read_blocks(index.leaf(), 1)?;
+                self.walk_extent_node(&child, runs, level + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn walk_indirect(&self, block: u64, depth: u32, logical: &mut u64, block_count: u64, runs: &mut Vec<BlockRun>) -> Result<(), Box<dyn std::error::Error>> {
+        let per_block = self.block_size() / 4;
+        if block == 0 {
+            //whole indirect subtree is a hole
+            *logical += per_block.pow(depth);
+            return Ok(())
+        }
+
+        let data = self.read_blocks(block, 1)?;
+        for ptr in data.chunks_exact(4) {
+            if *logical >= block_count {break}
+            let ptr = u32::from_le_bytes(ptr.try_into().unwrap()) as u64;
+            if depth == 1 {
+                push_run(runs, *logical, ptr, 1);
+                *logical += 1;
+            } else {
+                self.walk_indirect(ptr, depth - 1, logical, block_count, runs)?;
+            }
+        }
+        Ok(())
+    }
+
+    //reads whole inode data into memory, only for small things like directories and symlinks
+    fn read_data(&self, inode: &Inode, raw: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if inode.has_inline_data() {
+            return self.read_inline_data(inode, raw);
+        }
+
+        let mut data = vec![0u8; inode.size() as usize];
+        for (logical, physical, len) in self.block_runs(inode)? {
+            let start = (logical * self.block_size()) as usize;
+            if physical == 0 || start >= data.len() {continue}
+            let chunk = self.read_blocks(physical, len)?;
+            let end = (start + chunk.len()).min(data.len());
+            data[start..end].copy_from_slice(&chunk[..end - start]);
+        }
+        Ok(data)
+    }
+
+    //first 60 bytes are in i_block, the rest in the system.data xattr
+    fn read_inline_data(&self, inode: &Inode, raw: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut data = inode.block.to_vec();
+        for (name, value) in self.read_xattrs(inode, raw)? {
+            if name == "system.data" {
+                data.extend_from_slice(&value);
+            }
+        }
+        data.truncate(inode.size() as usize);
+        Ok(data)
+    }
+
+    fn write_file_data(&self, inode: &Inode, raw: &[u8], output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_path)?;
+        if inode.has_inline_data() {
+            out_file.write_all(&self.read_inline_data(inode, raw)?)?;
+            return Ok(())
+        }
+
+        //read in chunks so large partitions don't have to fit in memory
+        let max_chunk_blocks = (16 * 1024 * 1024) / self.block_size();
+        for (logical, physical, len) in self.block_runs(inode)? {
+            if physical == 0 {continue}
+            let mut done = 0;
+            while done < len {
+                let count = (len - done).min(max_chunk_blocks);
+                let chunk = self.read_blocks(physical + done, count)?;
+                out_file.seek(SeekFrom::Start((logical + done) * self.block_size()))?;
+                out_file.write_all(&chunk)?;
+                done += count;
+            }
+        }
+        out_file.set_len(inode.size())?;
+        Ok(())
+    }
+
+    fn read_xattrs(&self, inode: &Inode, raw: &[u8]) -> Result<Vec<Xattr>, Box<dyn std::error::Error>> {
+        let mut xattrs = Vec::new();
+
+        //in-inode xattrs, after the extra inode fields. value offsets are relative to the first entry
+        if raw.len() > 132 {
+            let extra_isize = u16::from_le_bytes(raw[128..130].try_into().unwrap()) as usize;
+            let start = 128 + extra_isize;
+            if start + 4 <= raw.len() && u32::from_le_bytes(raw[start..start + 4].try_into().unwrap()) == XATTR_MAGIC {
+                self.parse_xattr_entries(&raw[start + 4..], 0, &mut xattrs)?;
+            }
+        }
+
+        //external xattr block, value offsets are relative to the block start
+        if inode.file_acl() != 0 {
+            let block = self.read_blocks(inode.file_acl(), 1)?;
+            if u32::from_le_bytes(block[0..4].try_into().unwrap()) == XATTR_MAGIC {
+                self.parse_xattr_entries(&block, 32, &mut xattrs)?;
+            }
+        }
+
+        Ok(xattrs)
+    }
+
+    fn parse_xattr_entries(&self, region: &[u8], entries_start: u64, xattrs: &mut Vec<Xattr>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut reader = Cursor::new(region);
+        reader.seek(SeekFrom::Start(entries_start))?;
+
+        while reader.position() + 16 <= region.len() as u64 {
+            let entry: XattrEntry = reader.read_le()?;
+            if entry.is_end() {break}
+            reader.seek(SeekFrom::Current(entry.entry_size() as i64 - (16 + entry.name_len as i64)))?;
+
+            let value = if entry.value_inum != 0 {
+                let (value_inode, value_raw) = self.read_inode(entry.value_inum)?;
+                let mut value = self.read_data(&value_inode, &value_raw)?;
+                value.truncate(entry.value_size as usize);
+                value
+            } else {
+                let start = entry.value_offs as usize;
+                let end = start + entry.value_size as usize;
+                region.get(start..end).ok_or("Xattr value out of range!")?.to_vec()
+            };
+            xattrs.push((entry.full_name(), value));
+        }
+        Ok(())
+    }
+
+    fn read_dir_entries(&self, inode: &Inode, raw: &[u8]) -> Result<Vec<(u32, String)>, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+        if inode.has_inline_data() {
+            //inline directories start with the parent inode number instead of . and .. entries
+            let data = self.read_inline_data(inode, raw)?;
+            let split = data.len().min(60);
+            self.parse_dir_block(&data[4.min(split)..split], &mut entries);
+            self.parse_dir_block(&data[split..], &mut entries);
+        } else {
+            let data = self.read_data(inode, raw)?;
+            for block in data.chunks(self.block_size() as usize) {
+                self.parse_dir_block(block, &mut entries);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn parse_dir_block(&self, data: &[u8], entries: &mut Vec<(u32, String)>) {
+        let mut pos = 0;
+        while pos + 8 <= data.len() {
+            let ino = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+            let rec_len = u16::from_le_bytes(data[pos + 4..pos + 6].try_into().unwrap()) as usize;
+            let name_len = if self.sb.has_filetype() {data[pos + 6] as usize} else {u16::from_le_bytes(data[pos + 6..pos + 8].try_into().unwrap()) as usize};
+            if rec_len < 8 {break}
+
+            if ino != 0 && pos + 8 + name_len <= data.len() {
+                let name = String::from_utf8_lossy(&data[pos + 8..pos + 8 + name_len]).to_string();
+                if name != "." && name != ".." {
+                    entries.push((ino, name));
+                }
+            }
+            pos += rec_len;
+        }
+    }
+}
+
+fn push_run(runs: &mut Vec<BlockRun>, logical: u64, physical: u64, len: u64) {
+    if let Some(last) = runs.last_mut() {
+        let contiguous = last.0 + last.2 == logical && ((last.1 == 0 && physical == 0) || (last.1 != 0 && last.1 + last.2 == physical));
+        if contiguous {
+            last.2 += len;
+            return
+        }
+    }
+    runs.push((logical, physical, len));
+}
+
+struct WalkState {
+    manifest: Manifest,
+    visited: HashSet<u32>,
+    file_count: u32,
+}
+
+fn walk_dir(extfs: &ExtFs, dir_ino: u32, rel_path: &Path, output_dir: &Path, state: &mut WalkState) -> Result<(), Box<dyn std::error::Error>> {
+    let (dir_inode, dir_raw) = extfs.read_inode(dir_ino)?;
+    let entries = extfs.read_dir_entries(&dir_inode, &dir_raw)?;
+
+    for (ino, name) in entries {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            println!("- Warning: invalid entry name {:?}, skipping...", name);
+            continue
+        }
+        let entry_path = rel_path.join(&name);
+        let (inode, raw) = match extfs.read_inode(ino) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("- Warning: failed to read inode {} of {}: {}", ino, entry_path.display(), e);
+                continue
+            }
+        };
+        let file_type = inode.file_type();
+
+        let mut extra = String::new();
+        let output_path = output_dir.join(&entry_path);
+        match file_type {
+            FileType::Directory => {
+                fs::create_dir_all(&output_path)?;
+            }
+            FileType::Regular => {
+                if inode.is_encrypted() {
+                    println!("- Warning: {} is encrypted, skipping...", entry_path.display());
+                    extra.push_str(" [ENCRYPTED]");
+                } else {
+                    extfs.write_file_data(&inode, &raw, &output_path)?;
+                    state.file_count += 1;
+                }
+                if inode.has_inline_data() {
+                    extra.push_str(" [INLINE]");
+                }
+            }
+            FileType::Symlink => {
+                let target = if !inode.has_inline_data() && !inode.has_extents() && inode.size() < 60 {
+                    inode.block[..inode.size() as usize].to_vec()     //fast symlink
+                } else {
+                    extfs.read_data(&inode, &raw)?
+                };
+                extra.push_str(&format!(" -> {}", String::from_utf8_lossy(&target)));
+            }
+            FileType::CharDevice | FileType::BlockDevice => {
+                let (major, minor) = inode.device_number();
+                extra.push_str(&format!(" [{},{}]", major, minor));
+            }
+            _ => {}
+        }
+
+        if inode.links_count > 1 && file_type != FileType::Directory {
+            extra.push_str(&format!(" [ino {}]", ino));
+        }
+        state.manifest.add_entry(file_type, inode.mode as u32, (inode.uid(), inode.gid()), inode.size(), &entry_path, &extra);
+
+        for (xattr_name, value) in extfs.read_xattrs(&inode, &raw)? {
+            if xattr_name == "system.data" {continue}   //inline data storage, not a real attribute
+            state.manifest.add_xattr(&xattr_name, &value);
+        }
+
+        if file_type == FileType::Directory {
+            if !state.visited.insert(ino) {
+                println!("- Warning: directory loop at {}, skipping...", entry_path.display());
+                continue
+            }
+            walk_dir(extfs, ino, &entry_path, output_dir, state)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn extract_ext4(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+
+    let extfs = ExtFs::open(file)?;
+    println!("Filesystem info -\nType: {}\nVolume name: {}\nLast mounted on: {}\nUUID: {}\nBlock size: {}\nBlock count: {}\nInode count: {}\nInode size: {}\nGroup count: {}",
+            extfs.sb.fs_type(), extfs.sb.volume_name(), extfs.sb.last_mounted(), hex::encode(extfs.sb.uuid), extfs.block_size(), extfs.sb.blocks_count(),
+            extfs.sb.inodes_count, extfs.sb.inode_size(), extfs.sb.group_count());
+
+    let supported = INCOMPAT_FILETYPE | INCOMPAT_META_BG | INCOMPAT_EXTENTS | INCOMPAT_64BIT | INCOMPAT_INLINE_DATA
+                    | 0x0004 /* recover */ | 0x0200 /* flex_bg */ | 0x0400 /* ea_inode */ | 0x1000 /* dirdata */ | 0x2000 /* csum_seed */ | 0x4000 /* largedir */ | 0x20000 /* casefold */;
+    if (extfs.sb.feature_incompat & !supported) != 0 {
+        println!("Warning: unsupported incompatible features 0x{:x}, output may be incomplete!", extfs.sb.feature_incompat & !supported);
+    }
+
+    let output_dir = PathBuf::from(&app_ctx.output_dir);
+    fs::create_dir_all(&output_dir)?;
+
+    let mut state = WalkState {manifest: Manifest::new(), visited: HashSet::new(), file_count: 0};
+    state.visited.insert(ROOT_INODE);
+
+    let (root_inode, root_raw) = extfs.read_inode(ROOT_INODE)?;
+    state.manifest.add_entry(FileType::Directory, root_inode.mode as u32, (root_inode.uid(), root_inode.gid()), root_inode.size(), Path::new(""), "");
+    for (xattr_name, value) in extfs.read_xattrs(&root_inode, &root_raw)? {
+        state.manifest.add_xattr(&xattr_name, &value);
+    }
+
+    println!("\nExtracting files...");
+    walk_dir(&extfs, ROOT_INODE, Path::new(""), &output_dir, &mut state)?;
+    println!("- Extracted {} files, {} entries total", state.file_count, state.manifest.entry_count());
+    state.manifest.save(&output_dir)?;
+
+    Ok(())
+}
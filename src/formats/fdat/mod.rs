@@ -8,6 +8,8 @@ use std::io::{Cursor, Seek, SeekFrom, Write};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, prepare_keys};
+use crate::ukf::part_as_arr;
 use crate::utils::aes::{decrypt_aes128_ecb, decrypt_aes256_cbc_nopad};
 use crate::utils::global::opt_dump_dec_hdr;
 use include::*;
@@ -58,17 +60,17 @@ pub fn extract_fdat(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
     
     //1st gen (custom sha derived cipher)
     let first_block_encrypted = common::read_exact(&mut file, 1000 /* sha block size is 1000 */)?;
-    for (name, keys) in app_ctx.keys.get_collection("FDAT_SHA_KEYS")? {
-        let key_bytes = keys.first().unwrap().as_slice();
-        let mut crypter = ShaCrypter::new(key_bytes.try_into().unwrap());
+    let sha_keys = prepare_keys(&app_ctx.get_collection("FDAT_SHA_KEYS")?, |name, keys| part_as_arr::<40>(&format!("FDAT_SHA_KEYS \"{}\"", name), keys, 0))?;
+    let found = find_first(&sha_keys, |(_, key)| {
+        let mut crypter = ShaCrypter::new(*key);
         let decrypted = crypter.decrypt_block(&first_block_encrypted);
         //block starts with checksum, check to see if decryption was correct
-        if u16::from_le_bytes([decrypted[0], decrypted[1]]) == calc_sum(&decrypted[2..]) {
-            println!("- 1st gen firmware ({}) detected!", name);
-            encryption_mode = Some(EncryptionMode::Sha(crypter));
-            first_block_decrypted = decrypted[4..].to_vec();
-            break
-        }
+        (u16::from_le_bytes([decrypted[0], decrypted[1]]) == calc_sum(&decrypted[2..])).then_some((crypter, decrypted))
+    });
+    if let Some((i, (crypter, decrypted))) = found {
+        println!("- 1st gen firmware ({}) detected!", sha_keys[i].0);
+        encryption_mode = Some(EncryptionMode::Sha(crypter));
+        first_block_decrypted = decrypted[4..].to_vec();
     }
 
     //try aes types
@@ -8,11 +8,14 @@ use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use binrw::BinReaderExt;
 
 use crate::utils::common::{self, read_exact};
+use crate::utils::keytrial::{find_first, prepare_keys};
+use crate::ukf::part_as_arr;
 use crate::formats::funai_upg::funai_des::funai_des_decrypt;
 use include::*;
 use crate::utils::compression::decompress_zlib;
 
 pub struct FunaiBdpContext {
+    key_name: String,
     key: u32,
 }
 
@@ -20,19 +23,21 @@ pub fn is_funai_bdp_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, B
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
     let header = common::read_file(&file, 0, 16)?;
 
-    for (_, keys) in app_ctx.keys.get_collection("FUNAI_BDP")? {
-        let key_bytes = keys.first().unwrap();
-        let key_u32 = u32::from_le_bytes(key_bytes.as_slice().try_into()?);
-        let decrypted = funai_des_decrypt(&header, key_u32);
-
-        if decrypted == b"index_table\x00\x00\x00\x00\x00"{
-            return Ok(Some(Box::new(FunaiBdpContext {key: key_u32})))
-        }
+    let keys = prepare_keys(&app_ctx.get_collection("FUNAI_BDP")?, |name, keys| {
+        Ok(u32::from_le_bytes(part_as_arr(&format!("FUNAI_BDP \"{}\"", name), keys, 0)?))
+    })?;
+    if let Some((i, _)) = find_first(&keys, |(_, key_u32)| (funai_des_decrypt(&header, *key_u32) == b"index_table\x00\x00\x00\x00\x00").then_some(())) {
+        let (name, key_u32) = &keys[i];
+        return Ok(Some(Box::new(FunaiBdpContext {key_name: name.clone(), key: *key_u32})))
     }
 
     Ok(None)
 }
 
+pub fn find_funai_bdp_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(is_funai_bdp_file(app_ctx)?.map(|ctx| format!("FUNAI_BDP \"{}\"", ctx.downcast::<FunaiBdpContext>().expect("Missing context").key_name)))
+}
+
 pub fn extract_funai_bdp(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<FunaiBdpContext>().expect("Missing context");
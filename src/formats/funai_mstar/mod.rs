@@ -72,6 +72,7 @@ pub fn extract_funai_mstar(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<()
                 output_dir: app_ctx.output_dir.join("SoC"), 
                 options: app_ctx.options,
                 keys: app_ctx.keys,
+                hints: app_ctx.hints,
             };
 
             //do check and extarct
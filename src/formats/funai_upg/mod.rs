@@ -9,6 +9,8 @@ use std::io::Write;
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, prepare_keys};
+use crate::ukf::part_as_arr;
 use include::*;
 use funai_des::funai_des_decrypt;
 
@@ -40,17 +42,17 @@ pub fn extract_funai_upg(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(),
 
         //find key using descriptor entry
         if entry.entry_type == 0 && entry.encryption_flag == 1 && key.is_none() {
-            for (name, keys) in app_ctx.keys.get_collection("FUNAI_UPG")? {
-                let key_bytes = keys.first().unwrap();
-                let key_u32 = u32::from_le_bytes(key_bytes.as_slice().try_into()?);
-                let decrypted = funai_des_decrypt(&data, key_u32);
-
-                if is_valid_ver_string(&decrypted) {
-                    println!("Matched key: {}\nFirmware info: {}", 
-                            name, common::string_from_bytes(&decrypted));
-                    key = Some(key_u32);
-                    break
-                }
+            let keys = prepare_keys(&app_ctx.get_collection("FUNAI_UPG")?, |name, keys| {
+                Ok(u32::from_le_bytes(part_as_arr(&format!("FUNAI_UPG \"{}\"", name), keys, 0)?))
+            })?;
+            let found = find_first(&keys, |(_, key_u32)| {
+                let decrypted = funai_des_decrypt(&data, *key_u32);
+                is_valid_ver_string(&decrypted).then_some(decrypted)
+            });
+            if let Some((i, decrypted)) = found {
+                println!("Matched key: {}\nFirmware info: {}", 
+                        keys[i].0, common::string_from_bytes(&decrypted));
+                key = Some(keys[i].1);
             }
         }
 
@@ -8,6 +8,8 @@ use std::io::Write;
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, prepare_keys};
+use crate::ukf::part_as_arr;
 use crate::formats::funai_upg::funai_des::funai_des_decrypt;
 use crate::formats::funai_upg::include::is_valid_ver_string;
 use include::*;
@@ -39,17 +41,16 @@ pub fn extract_funai_upg_phl(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result
 
         //find key using descriptor entry
         if entry.body_type == 0 && key.is_none() {
-            for (name, keys) in app_ctx.keys.get_collection("FUNAI_UPG")? {
-                let key_bytes = keys.first().unwrap();
-                let key_u32 = u32::from_le_bytes(key_bytes.as_slice().try_into()?);
-                let decrypted = funai_des_decrypt(&data, key_u32);
-
-                if is_valid_ver_string(&decrypted[..16]) {
-                    println!("Matched key: {}\nFirmware info: {}\nFirmware date: {}", 
-                            name, common::string_from_bytes(&decrypted[..16]), common::string_from_bytes(&decrypted[16..]));
-                    key = Some(key_u32);
-                    break
-                }
+            let keys = prepare_keys(&app_ctx.get_collection("FUNAI_UPG")?, |name, keys| {
+                Ok(u32::from_le_bytes(part_as_arr(&format!("FUNAI_UPG \"{}\"", name), keys, 0)?))
+            })?;
+            //the version string is in the first 16 bytes
+            if let Some((i, _)) = find_first(&keys, |(_, key_u32)| is_valid_ver_string(&funai_des_decrypt(&data[..16], *key_u32)).then_some(())) {
+                let (name, key_u32) = &keys[i];
+                let decrypted = funai_des_decrypt(&data, *key_u32);
+                println!("Matched key: {}\nFirmware info: {}\nFirmware date: {}", 
+                        name, common::string_from_bytes(&decrypted[..16]), common::string_from_bytes(&decrypted[16..]));
+                key = Some(*key_u32);
             }
         }
 
@@ -2,29 +2,70 @@ mod include;
 use std::any::Any;
 use crate::AppContext;
 
-use std::path::{Path};
-use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
 use std::io::{Write, Read, Seek, SeekFrom, Cursor};
 use binrw::BinReaderExt;
 
 use crate::utils::aes::{decrypt_aes128_cbc_pcks7};
 use crate::utils::common;
+use crate::utils::signature::verify_pkcs1;
+use crate::ukf::MissingKey;
 use include::*;
 
+struct InvincibleCtx {
+    parts: Vec<PathBuf>,    //ROM parts when a folder is given
+}
+
+//one payload can be split over the ROM parts, each part holds a piece starting at start_offset
+struct Piece {
+    start_offset: u32,
+    data: Vec<u8>,
+}
+
+fn is_invincible_image(file: &File) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(common::read_file(file, 0, 16)? == b"INVINCIBLE_IMAGE")
+}
+
 pub fn is_invincible_image_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
-    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+    if let Some(file) = app_ctx.file() {
+        return Ok(is_invincible_image(file)?.then(|| Box::new(InvincibleCtx {parts: Vec::new()}) as Box<dyn Any>))
+    }
 
-    let header = common::read_file(&file, 0, 16)?;
-    if header == b"INVINCIBLE_IMAGE" {
-        Ok(Some(Box::new(())))
-    } else {
+    //folder with the parts of a split ROM, like .ROM-00 and .ROM-01
+    let Some(dir) = app_ctx.dir() else {
+        return Ok(None)
+    };
+    let mut parts = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_invincible_image(&File::open(&path)?)? {
+            parts.push(path);
+        }
+    }
+    parts.sort();
+    if parts.is_empty() {
         Ok(None)
+    } else {
+        Ok(Some(Box::new(InvincibleCtx {parts})))
     }
 }
 
-pub fn extract_invincible_image(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = app_ctx.file().ok_or("Extractor expected file")?;
+//each chunk is followed by a signature of it, checked when the public key is loaded
+fn check_chunk_signature(modulus: Option<&[u8]>, chunk_i: u8, chunk: &[u8], signature: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(modulus) = modulus else {
+        return Ok(())
+    };
+    if verify_pkcs1(modulus, signature, chunk)? {
+        println!("Chunk {} signature OK", chunk_i + 1);
+    } else {
+        println!("Warning: chunk {} signature does not match INVINCIBLE_IMAGE_PUBKEY!", chunk_i + 1);
+    }
+    Ok(())
+}
 
+fn read_part(app_ctx: &AppContext, mut file: &File) -> Result<Vec<(String, Piece)>, Box<dyn std::error::Error>> {
+    file.seek(SeekFrom::Start(0))?;
     let header: Header = file.read_le()?;
     println!("File info -\nKey ID: {}\nVersion(1): {}\nVersion(2): {}\nVersion(3): {}\nVersion(4): {}\nData size: {}\nChunk count: {}\nChunk size: {}\n\nPayload Count: {}",
             header.file_infos[0], header.ver1(), header.ver2(), header.ver3(), header.ver4(), header.data_size, header.chunk_count, header.chunk_size, header.payload_count);
@@ -33,14 +74,14 @@ pub fn extract_invincible_image(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Res
 
     for i in 0..header.payload_count {
         let entry: Entry = file.read_le()?;
-        println!("{}. {} - Start offset: {}, Size: {}", 
+        println!("{}. {} - Start offset: {}, Size: {}",
                 i + 1, entry.name(), entry.start_offset, entry.size);
         entries.push(entry);
     }
 
+    //key ID 1 (<2010) keys are not included, but can be added in a key file
     let (aes_key, aes_iv) = match header.file_infos[0] {
-        3 => app_ctx.keys.get_double_key_as_arr::<16, 16>("INVINCIBLE_IMAGE_V3")?,
-        2 => app_ctx.keys.get_double_key_as_arr::<16, 16>("INVINCIBLE_IMAGE_V2")?,
+        id @ 1..=3 => app_ctx.keys.get_double_key_as_arr::<16, 16>(&format!("INVINCIBLE_IMAGE_V{}", id))?,
         _ => return Err("Unsupported Key ID!".into())
     };
 
@@ -52,16 +93,26 @@ pub fn extract_invincible_image(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Res
         encrypted_data = common::read_exact(&mut file, header.data_size as usize)?;
 
     } else {
+        let modulus = match app_ctx.keys.get_key("INVINCIBLE_IMAGE_PUBKEY") {
+            Ok(parts) => Some(parts.first().ok_or("INVINCIBLE_IMAGE_PUBKEY is empty!")?.clone()),
+            Err(e) if e.is::<MissingKey>() => {
+                println!("\nChunk signatures not checked, INVINCIBLE_IMAGE_PUBKEY is not loaded");
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
         let mut buffer = vec![0u8; header.chunk_size as usize];
         let mut remain = header.data_size as usize;
 
-        for _ in 0..header.chunk_count {
+        for chunk_i in 0..header.chunk_count {
             let read_size = remain.min(buffer.len());
             let bytes_read = file.read(&mut buffer[..read_size])?;
             encrypted_data.extend_from_slice(&buffer[..bytes_read]);
             remain -= bytes_read;
 
-            file.seek(SeekFrom::Current(header.signature_size.into()))?; // skip signature in each chunk
+            let signature = common::read_exact(&mut file, header.signature_size as usize)?;
+            check_chunk_signature(modulus.as_deref(), chunk_i, &buffer[..bytes_read], &signature)?;
         }
     }
 
@@ -69,21 +120,71 @@ pub fn extract_invincible_image(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Res
     let decrypted_data = decrypt_aes128_cbc_pcks7(&encrypted_data, &aes_key, &aes_iv)?;
 
     let mut data_reader = Cursor::new(decrypted_data);
+    let mut pieces = Vec::new();
+    for entry in entries {
+        let data = common::read_exact(&mut data_reader, entry.size as usize)?;
+        pieces.push((entry.name(), Piece {start_offset: entry.start_offset, data}));
+    }
 
-    for (i , entry) in entries.iter().enumerate() {
-        println!("\n({}/{}) - {}, Size: {}, Start offset: {}", i+1, header.payload_count, entry.name(), entry.size, entry.start_offset);
+    Ok(pieces)
+}
 
-        let data = common::read_exact(&mut data_reader, entry.size as usize)?;
+//gaps mean a part is missing
+fn report_gaps(name: &str, pieces: &[Piece]) {
+    let mut ranges: Vec<(u64, u64)> = pieces.iter().map(|p| (p.start_offset as u64, p.start_offset as u64 + p.data.len() as u64)).collect();
+    ranges.sort();
+    let mut end = 0;
+    for (start, piece_end) in ranges {
+        if start > end {
+            println!("Warning: {} is missing bytes {}..{}, is a ROM part missing?", name, end, start);
+        }
+        end = end.max(piece_end);
+    }
+}
 
-        let output_path = Path::new(&app_ctx.output_dir).join(entry.name() + ".bin");
+pub fn extract_invincible_image(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let ctx = ctx.downcast::<InvincibleCtx>().expect("Missing context");
+
+    //payload name and its pieces, in the order they first appear
+    let mut payloads: Vec<(String, Vec<Piece>)> = Vec::new();
+    if let Some(file) = app_ctx.file() {
+        for (name, piece) in read_part(app_ctx, file)? {
+            payloads.push((name, vec![piece]));
+        }
+    } else {
+        for path in &ctx.parts {
+            println!("\nROM part: {}", path.file_name().unwrap_or_default().to_string_lossy());
+            for (name, piece) in read_part(app_ctx, &File::open(path)?)? {
+                match payloads.iter_mut().find(|(n, _)| *n == name) {
+                    Some((_, pieces)) => pieces.push(piece),
+                    None => payloads.push((name, vec![piece])),
+                }
+            }
+        }
+    }
+
+    let payload_count = payloads.len();
+    for (i, (name, pieces)) in payloads.iter().enumerate() {
+        let size: usize = pieces.iter().map(|p| p.data.len()).sum();
+        println!("\n({}/{}) - {}, Size: {}, Parts: {}", i+1, payload_count, name, size, pieces.len());
+
+        let output_path = Path::new(&app_ctx.output_dir).join(name.clone() + ".bin");
         fs::create_dir_all(&app_ctx.output_dir)?;
-        let mut out_file = OpenOptions::new().write(true).create(true).open(output_path)?;
+        //a single part is written into an existing file, so parts extracted one by one into the same folder still get combined
+        let mut out_file = OpenOptions::new().write(true).create(true).truncate(app_ctx.file().is_none()).open(output_path)?;
 
-        out_file.seek(SeekFrom::Start(entry.start_offset.into()))?;
-        out_file.write_all(&data)?;
+        for piece in pieces {
+            out_file.seek(SeekFrom::Start(piece.start_offset.into()))?;
+            out_file.write_all(&piece.data)?;
+        }
+        if app_ctx.file().is_none() {
+            report_gaps(name, pieces);
+        } else if pieces.iter().any(|p| p.start_offset != 0) {
+            println!("Tip: this is a piece of a split ROM, pass the folder with all ROM parts to combine them");
+        }
 
         println!("- Saved file!");
     }
 
     Ok(())
-}
\ No newline at end of file
+}
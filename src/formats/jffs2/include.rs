@@ -0,0 +1,130 @@
+use binrw::BinRead;
+
+pub static JFFS2_MAGIC: u16 = 0x1985;
+
+//node types
+pub static NODETYPE_DIRENT: u16 = 0xE001;
+pub static NODETYPE_INODE: u16 = 0xE002;
+pub static NODETYPE_CLEANMARKER: u16 = 0x2003;
+pub static NODETYPE_PADDING: u16 = 0x2004;
+pub static NODETYPE_SUMMARY: u16 = 0x2006;
+pub static NODETYPE_XATTR: u16 = 0xE008;
+pub static NODETYPE_XREF: u16 = 0xE009;
+
+pub static NODE_HEADER_SIZE: usize = 12;
+pub static ROOT_INO: u32 = 1;
+
+#[derive(BinRead)]
+pub struct NodeHeader {
+    pub magic: u16,             //0x1985
+    pub node_type: u16,
+    pub total_len: u32,         //including this header
+    _hdr_crc: u32,
+}
+
+#[derive(BinRead)]
+pub struct Dirent {
+    pub pino: u32,              //parent directory
+    pub version: u32,
+    pub ino: u32,               //0 = entry was unlinked
+    _mctime: u32,
+    _nsize: u8,
+    _type: u8,
+    _unused: [u8; 2],
+    _node_crc: u32,
+    _name_crc: u32,
+    #[br(count = _nsize)] name_bytes: Vec<u8>,
+}
+impl Dirent {
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.name_bytes).to_string()
+    }
+}
+
+//data node, every one of them carries the full metadata of the inode at that version
+#[derive(BinRead)]
+pub struct RawInode {
+    pub ino: u32,
+    pub version: u32,
+    pub mode: u32,
+    pub uid: u16,
+    pub gid: u16,
+    pub isize: u32,
+    _atime: u32,
+    _mtime: u32,
+    _ctime: u32,
+    pub offset: u32,            //where the data of this node goes in the file
+    pub csize: u32,
+    pub dsize: u32,
+    pub compr: u8,
+    _usercompr: u8,
+    _flags: u16,
+    _data_crc: u32,
+    _node_crc: u32,
+}
+pub static RAW_INODE_SIZE: usize = 68;
+
+#[derive(BinRead)]
+pub struct XattrNode {
+    pub xid: u32,
+    pub version: u32,
+    pub xprefix: u8,
+    _name_len: u8,
+    _value_len: u16,
+    _data_crc: u32,
+    _node_crc: u32,
+    #[br(count = _name_len)] name_bytes: Vec<u8>,
+    _name_terminator: u8,
+    #[br(count = _value_len)] pub value: Vec<u8>,
+}
+impl XattrNode {
+    pub fn full_name(&self) -> String {
+        let prefix = match self.xprefix {
+            1 => "user.",
+            2 => "security.",
+            3 => "trusted.",
+            4 => "system.posix_acl_access",
+            5 => "system.posix_acl_default",
+            _ => "",
+        };
+        format!("{}{}", prefix, String::from_utf8_lossy(&self.name_bytes))
+    }
+}
+
+#[derive(BinRead)]
+pub struct XrefNode {
+    pub ino: u32,
+    pub xid: u32,
+    _xseqno: u32,
+    _node_crc: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Compression {
+    None,
+    Zero,
+    Rtime,
+    Rubin,
+    Copy,
+    DynRubin,
+    Zlib,
+    Lzo,
+    Lzma,
+    Unknown(u8),
+}
+impl Compression {
+    pub fn from_id(id: u8) -> Self {
+        match id {
+            0 => Compression::None,
+            1 => Compression::Zero,
+            2 => Compression::Rtime,
+            3 => Compression::Rubin,
+            4 => Compression::Copy,
+            5 => Compression::DynRubin,
+            6 => Compression::Zlib,
+            7 => Compression::Lzo,
+            8 => Compression::Lzma,
+            _ => Compression::Unknown(id),
+        }
+    }
+}
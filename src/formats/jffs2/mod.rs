@@ -0,0 +1,335 @@
+mod include;
+use std::any::Any;
+use crate::AppContext;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::fs::{self, OpenOptions};
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use binrw::{BinReaderExt, Endian};
+
+use crate::utils::common;
+use crate::utils::compression::{decompress_zlib, decompress_lzma};
+use crate::utils::manifest::{Manifest, FileType};
+use include::*;
+
+pub struct Jffs2Context {
+    endian: Endian,
+}
+
+pub fn is_jffs2_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let header = common::read_file(file, 0, 4)?;
+    //magic is stored in the byte order of the cpu that created the image
+    let endian = if u16::from_le_bytes([header[0], header[1]]) == JFFS2_MAGIC {
+        Endian::Little
+    } else if u16::from_be_bytes([header[0], header[1]]) == JFFS2_MAGIC {
+        Endian::Big
+    } else {
+        return Ok(None)
+    };
+
+    let node_type = match endian {
+        Endian::Little => u16::from_le_bytes([header[2], header[3]]),
+        Endian::Big => u16::from_be_bytes([header[2], header[3]]),
+    };
+    let known_types = [NODETYPE_DIRENT, NODETYPE_INODE, NODETYPE_CLEANMARKER, NODETYPE_PADDING, NODETYPE_SUMMARY, NODETYPE_XATTR, NODETYPE_XREF];
+    if known_types.contains(&node_type) {
+        Ok(Some(Box::new(Jffs2Context {endian})))
+    } else {
+        Ok(None)
+    }
+}
+
+struct DataNode {
+    version: u32,
+    offset: u32,
+    csize: u32,
+    dsize: u32,
+    compr: u8,
+    data_pos: usize,            //position of the compressed data in the image
+}
+
+struct InodeInfo {
+    version: u32,               //of the newest node, which holds the current metadata
+    mode: u32,
+    uid: u16,
+    gid: u16,
+    isize: u32,
+    nodes: Vec<DataNode>,
+}
+
+#[derive(Default)]
+struct Jffs2Image {
+    inodes: HashMap<u32, InodeInfo>,
+    dirents: HashMap<(u32, String), (u32, u32)>,   //(parent, name) -> (version, ino)
+    xattrs: HashMap<u32, (u32, String, Vec<u8>)>,  //xid -> (version, name, value)
+    xrefs: Vec<(u32, u32)>,                         //(ino, xid)
+    node_count: u32,
+}
+
+//the log has no index, so every node in the image is read and the newest version of everything wins
+fn scan_nodes(data: &[u8], endian: Endian) -> Result<Jffs2Image, Box<dyn std::error::Error>> {
+    let mut image = Jffs2Image::default();
+    let mut reader = Cursor::new(data);
+    let mut pos = 0;
+
+    while pos + NODE_HEADER_SIZE <= data.len() {
+        reader.seek(SeekFrom::Start(pos as u64))?;
+        let header: NodeHeader = reader.read_type(endian)?;
+        //erased space and garbage between nodes is skipped word by word
+        if header.magic != JFFS2_MAGIC || (header.total_len as usize) < NODE_HEADER_SIZE || pos + header.total_len as usize > data.len() {
+            pos += 4;
+            continue
+        }
+        image.node_count += 1;
+
+        if header.node_type == NODETYPE_DIRENT {
+            let dirent: Dirent = reader.read_type(endian)?;
+            let key = (dirent.pino, dirent.name());
+            if image.dirents.get(&key).is_none_or(|(version, _)| dirent.version > *version) {
+                image.dirents.insert(key, (dirent.version, dirent.ino));
+            }
+        } else if header.node_type == NODETYPE_INODE {
+            let raw: RawInode = reader.read_type(endian)?;
+            let node = DataNode {version: raw.version, offset: raw.offset, csize: raw.csize, dsize: raw.dsize, compr: raw.compr, data_pos: pos + RAW_INODE_SIZE};
+            if pos + RAW_INODE_SIZE + raw.csize as usize > data.len() {
+                println!("- Warning: data node of inode {} at 0x{:x} is truncated, skipping...", raw.ino, pos);
+            } else {
+                let info = image.inodes.entry(raw.ino).or_insert(InodeInfo {version: 0, mode: 0, uid: 0, gid: 0, isize: 0, nodes: Vec::new()});
+                if raw.version >= info.version {
+                    info.version = raw.version;
+                    info.mode = raw.mode;
+                    info.uid = raw.uid;
+                    info.gid = raw.gid;
+                    info.isize = raw.isize;
+                }
+                info.nodes.push(node);
+            }
+        } else if header.node_type == NODETYPE_XATTR {
+            let xattr: XattrNode = reader.read_type(endian)?;
+            if image.xattrs.get(&xattr.xid).is_none_or(|(version, _, _)| xattr.version > *version) {
+                image.xattrs.insert(xattr.xid, (xattr.version, xattr.full_name(), xattr.value.clone()));
+            }
+        } else if header.node_type == NODETYPE_XREF {
+            let xref: XrefNode = reader.read_type(endian)?;
+            image.xrefs.push((xref.ino, xref.xid));
+        }
+
+        pos += (header.total_len as usize + 3) & !3;
+    }
+
+    Ok(image)
+}
+
+//the simple "repeat since last occurence of this byte" compressor
+fn decompress_rtime(data: &[u8], dsize: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(dsize);
+    let mut positions = [0usize; 256];
+    let mut pos = 0;
+
+    while out.len() < dsize && pos + 1 < data.len() {
+        let value = data[pos];
+        let repeat = data[pos + 1] as usize;
+        pos += 2;
+
+        out.push(value);
+        let backoffs = positions[value as usize];
+        positions[value as usize] = out.len();
+        for i in 0..repeat {
+            let byte = out[backoffs + i];
+            out.push(byte);
+        }
+    }
+
+    out.truncate(dsize);
+    out
+}
+
+fn decompress_node(data: &[u8], node: &DataNode) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let compressed = &data[node.data_pos..node.data_pos + node.csize as usize];
+    let dsize = node.dsize as usize;
+
+    let decompressed = match Compression::from_id(node.compr) {
+        Compression::None | Compression::Copy => compressed.to_vec(),
+        Compression::Zero => vec![0u8; dsize],
+        Compression::Rtime => decompress_rtime(compressed, dsize),
+        Compression::Zlib => decompress_zlib(compressed)?,
+        Compression::Lzo => minilzo_rs::LZO::init()?.decompress(compressed, dsize)?,
+        Compression::Lzma => {
+            //raw stream with the fixed kernel parameters (lc=0, lp=0, pb=0, 8KB dictionary), give it a header
+            let mut lzma_data = vec![0x00, 0x00, 0x20, 0x00, 0x00];
+            lzma_data.extend_from_slice(&(dsize as u64).to_le_bytes());
+            lzma_data.extend_from_slice(compressed);
+            decompress_lzma(&lzma_data)?
+        }
+        other => return Err(format!("Unsupported compression {:?}!", other).into()),
+    };
+
+    if decompressed.len() < dsize {
+        return Err("Decompressed data is too short!".into());
+    }
+    Ok(decompressed)
+}
+
+fn read_inode_data(data: &[u8], info: &InodeInfo) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut nodes: Vec<&DataNode> = info.nodes.iter().collect();
+    nodes.sort_by_key(|n| n.version);
+
+    //newer nodes overwrite the ranges of older ones
+    let mut out = Vec::new();
+    for node in nodes {
+        if node.dsize == 0 {continue}
+        let chunk = decompress_node(data, node)?;
+        let start = node.offset as usize;
+        let end = start + node.dsize as usize;
+        if out.len() < end {
+            out.resize(end, 0);
+        }
+        out[start..end].copy_from_slice(&chunk[..node.dsize as usize]);
+    }
+
+    out.resize(info.isize as usize, 0);
+    Ok(out)
+}
+
+struct WalkState {
+    manifest: Manifest,
+    children: HashMap<u32, Vec<(String, u32)>>,
+    link_counts: HashMap<u32, u32>,
+    visited: HashSet<u32>,
+    file_count: u32,
+}
+
+fn add_xattrs(image: &Jffs2Image, ino: u32, manifest: &mut Manifest) {
+    for (xref_ino, xid) in &image.xrefs {
+        if *xref_ino != ino {continue}
+        if let Some((_, name, value)) = image.xattrs.get(xid) {
+            manifest.add_xattr(name, value);
+        }
+    }
+}
+
+fn walk_dir(image: &Jffs2Image, data: &[u8], endian: Endian, dir_ino: u32, rel_path: &Path, output_dir: &Path, state: &mut WalkState) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = state.children.get(&dir_ino).cloned().unwrap_or_default();
+
+    for (name, ino) in entries {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            println!("- Warning: invalid entry name {:?}, skipping...", name);
+            continue
+        }
+        let entry_path = rel_path.join(&name);
+        let info = match image.inodes.get(&ino) {
+            Some(i) => i,
+            None => {
+                println!("- Warning: no data nodes for inode {} of {}, skipping...", ino, entry_path.display());
+                continue
+            }
+        };
+        let file_type = FileType::from_mode(info.mode);
+
+        let mut extra = String::new();
+        let output_path = output_dir.join(&entry_path);
+        match file_type {
+            FileType::Directory => {
+                fs::create_dir_all(&output_path)?;
+            }
+            FileType::Regular => {
+                match read_inode_data(data, info) {
+                    Ok(file_data) => {
+                        let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(&output_path)?;
+                        out_file.write_all(&file_data)?;
+                        state.file_count += 1;
+                    }
+                    Err(e) => println!("- Warning: failed to read {}: {}", entry_path.display(), e),
+                }
+            }
+            FileType::Symlink => {
+                let target = read_inode_data(data, info)?;
+                extra.push_str(&format!(" -> {}", String::from_utf8_lossy(&target)));
+            }
+            FileType::CharDevice | FileType::BlockDevice => {
+                //device number is the data of the newest node, old 16 bit or new 32 bit encoding, isize is 0
+                let dev_bytes = match info.nodes.iter().max_by_key(|n| n.version) {
+                    Some(node) => decompress_node(data, node).unwrap_or_default(),
+                    None => Vec::new(),
+                };
+                let (major, minor) = if dev_bytes.len() >= 4 {
+                    let dev = match endian {
+                        Endian::Little => u32::from_le_bytes(dev_bytes[..4].try_into()?),
+                        Endian::Big => u32::from_be_bytes(dev_bytes[..4].try_into()?),
+                    };
+                    ((dev & 0xFFF00) >> 8, (dev & 0xFF) | ((dev >> 12) & 0xFFF00))
+                } else if dev_bytes.len() >= 2 {
+                    let dev = match endian {
+                        Endian::Little => u16::from_le_bytes(dev_bytes[..2].try_into()?),
+                        Endian::Big => u16::from_be_bytes(dev_bytes[..2].try_into()?),
+                    };
+                    ((dev >> 8) as u32, (dev & 0xFF) as u32)
+                } else {
+                    (0, 0)
+                };
+                extra.push_str(&format!(" [{},{}]", major, minor));
+            }
+            _ => {}
+        }
+
+        if state.link_counts.get(&ino).copied().unwrap_or(0) > 1 && file_type != FileType::Directory {
+            extra.push_str(&format!(" [ino {}]", ino));
+        }
+        state.manifest.add_entry(file_type, info.mode, (info.uid as u32, info.gid as u32), info.isize as u64, &entry_path, &extra);
+        add_xattrs(image, ino, &mut state.manifest);
+
+        if file_type == FileType::Directory {
+            if !state.visited.insert(ino) {
+                println!("- Warning: directory loop at {}, skipping...", entry_path.display());
+                continue
+            }
+            walk_dir(image, data, endian, ino, &entry_path, output_dir, state)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn extract_jffs2(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+    let ctx = ctx.downcast::<Jffs2Context>().expect("Missing context");
+
+    let data = common::read_file(file, 0, file.metadata()?.len() as usize)?;
+    println!("Scanning nodes...");
+    let image = scan_nodes(&data, ctx.endian)?;
+
+    let mut children: HashMap<u32, Vec<(String, u32)>> = HashMap::new();
+    let mut link_counts: HashMap<u32, u32> = HashMap::new();
+    for ((pino, name), (_, ino)) in &image.dirents {
+        if *ino == 0 {continue}     //unlinked
+        children.entry(*pino).or_default().push((name.clone(), *ino));
+        *link_counts.entry(*ino).or_default() += 1;
+    }
+    for entries in children.values_mut() {
+        entries.sort();
+    }
+
+    println!("Filesystem info -\nEndianness: {}\nNodes: {}\nInodes: {}\nDirectory entries: {}",
+            if ctx.endian == Endian::Little {"Little"} else {"Big"}, image.node_count, image.inodes.len(), link_counts.values().sum::<u32>());
+
+    let output_dir = PathBuf::from(&app_ctx.output_dir);
+    fs::create_dir_all(&output_dir)?;
+
+    let mut state = WalkState {manifest: Manifest::new(), children, link_counts, visited: HashSet::new(), file_count: 0};
+    state.visited.insert(ROOT_INO);
+
+    //the root directory usually has no node of its own
+    let (root_mode, root_uid, root_gid) = image.inodes.get(&ROOT_INO).map_or((0o40755, 0, 0), |i| (i.mode, i.uid as u32, i.gid as u32));
+    state.manifest.add_entry(FileType::Directory, root_mode, (root_uid, root_gid), 0, Path::new(""), "");
+    add_xattrs(&image, ROOT_INO, &mut state.manifest);
+
+    println!("\nExtracting files...");
+    walk_dir(&image, &data, ctx.endian, ROOT_INO, Path::new(""), &output_dir, &mut state)?;
+    println!("- Extracted {} files, {} entries total", state.file_count, state.manifest.entry_count());
+    state.manifest.save(&output_dir)?;
+
+    Ok(())
+}
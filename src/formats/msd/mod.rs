@@ -5,14 +5,28 @@ pub mod msd_ouith_parser_tizen_1_9;
 
 use sha2::{Digest, Sha256};
 
-use crate::utils::aes::{decrypt_aes128_cbc_pcks7, decrypt_aes256_cbc_pcks7};
+use crate::utils::aes::{decrypt_aes128_cbc_pcks7, decrypt_aes128_cbc_nopad, decrypt_aes256_cbc_pcks7, decrypt_aes256_cbc_nopad};
 
 pub fn decrypt_aes_salted_old(encrypted_data: &[u8], passphrase_bytes: &Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     if &encrypted_data[0..8] != b"Salted__" {
         return Err("Invalid encrypted data!".into());
     }
-    let salt = &encrypted_data[8..16];
+    let (key_md5, iv_md5) = derive_old_key_iv(passphrase_bytes, &encrypted_data[8..16]);
+    decrypt_aes128_cbc_pcks7(&encrypted_data[16..], &key_md5, &iv_md5)
+}
+
+//the old format can only be checked by its padding, which is in the last block. the block before it is its iv
+pub fn is_old_passphrase(encrypted_data: &[u8], passphrase_bytes: &Vec<u8>) -> bool {
+    if encrypted_data.len() < 32 || !encrypted_data.len().is_multiple_of(16) || &encrypted_data[0..8] != b"Salted__" {
+        return false
+    }
+    let (key_md5, iv_md5) = derive_old_key_iv(passphrase_bytes, &encrypted_data[8..16]);
+    let data = &encrypted_data[16..];
+    let iv = if data.len() >= 32 {data[data.len() - 32..data.len() - 16].try_into().unwrap()} else {iv_md5};
+    decrypt_aes128_cbc_pcks7(&data[data.len() - 16..], &key_md5, &iv).is_ok()
+}
 
+fn derive_old_key_iv(passphrase_bytes: &Vec<u8>, salt: &[u8]) -> ([u8; 16], [u8; 16]) {
     //key = md5 of (passphrase + salt)
     let mut key = Vec::new();
     key.extend_from_slice(&passphrase_bytes);
@@ -26,7 +40,7 @@ pub fn decrypt_aes_salted_old(encrypted_data: &[u8], passphrase_bytes: &Vec<u8>)
     iv.extend_from_slice(&salt);
     let iv_md5 = md5::compute(&iv).0;
 
-    decrypt_aes128_cbc_pcks7(&encrypted_data[16..], &key_md5, &iv_md5)
+    (key_md5, iv_md5)
 }
 
 pub fn decrypt_aes_salted_tizen(encrypted_data: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
@@ -64,6 +78,25 @@ pub fn decrypt_aes_tizen(encrypted_data: &[u8], passphrase: &[u8], salt: &[u8])
     };
 }
 
+//only decrypts as much of the TOC as is_valid_ouith looks at, for trying many passphrases
+pub fn is_tizen_passphrase(encrypted_data: &[u8], passphrase: &[u8]) -> bool {
+    if encrypted_data.len() < 16 + 576 || &encrypted_data[0..8] != b"Salted__" {
+        return false
+    }
+    let salt = &encrypted_data[8..16];
+    let data = &encrypted_data[16..16 + 576];
+
+    let decrypted = if passphrase.len() == 16 {
+        decrypt_aes128_cbc_nopad(data, &passphrase.try_into().unwrap(), &md5::compute(salt).0)
+    } else if passphrase.len() == 32 {
+        let digest: [u8; 32] = Sha256::digest(salt).into();
+        decrypt_aes256_cbc_nopad(data, &passphrase.try_into().unwrap(), &digest[..16].try_into().unwrap())
+    } else {
+        return false
+    };
+    decrypted.is_ok_and(|d| is_valid_ouith(&d))
+}
+
 pub fn is_valid_ouith(data: &[u8]) -> bool{
     return &data[256..306] == b"Tizen Software Upgrade Tree Binary Format ver. 1.8" || 
            &data[262..312] == b"Tizen Software Upgrade Tree Binary Format ver. 1.9" ||
@@ -1,15 +1,17 @@
 mod include;
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
 
-use std::fs::{self, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::path::Path;
 use std::io::{Write, Seek, SeekFrom};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::keytrial::find_matching_key;
 use crate::utils::global::opt_dump_dec_hdr;
-use crate::formats::msd::{decrypt_aes_salted_old, decrypt_aes_salted_tizen, decrypt_aes_tizen, is_valid_ouith};
+use crate::formats::msd::{decrypt_aes_salted_old, decrypt_aes_salted_tizen, decrypt_aes_tizen, is_old_passphrase, is_tizen_passphrase};
 use crate::formats::msd::msd_ouith_parser_old::{parse_ouith_blob};
 use crate::formats::msd::msd_ouith_parser_tizen_1_8::{parse_blob_1_8};
 use include::*;
@@ -25,6 +27,64 @@ pub fn is_msd10_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<d
     }
 }
 
+//name, passphrase and the firmware type it decrypted
+type Passphrase = (String, Vec<u8>, FirmwareType);
+
+//tizen TOCs can be verified, the old format only by decrypting without error
+fn find_passphrase(app_ctx: &AppContext, firmware_name: &str, toc_data: &[u8]) -> Result<Option<Passphrase>, Box<dyn std::error::Error>> {
+    let found = find_matching_key(&app_ctx.get_hinted_collection("msd10", firmware_name, "MSD10")?, |keys| {
+        let key_bytes= keys.first().unwrap();
+        if key_bytes.len() == 20 && is_old_passphrase(toc_data, key_bytes) {  //TODO: figure out how to verify pre-tizen OUITH
+            Some((key_bytes.clone(), FirmwareType::Old))
+        }
+        else if key_bytes.len() == 16 && is_tizen_passphrase(toc_data, key_bytes) {
+            Some((key_bytes.clone(), FirmwareType::Tizen))
+        } else {
+            None
+        }
+    });
+    if let Some((name, _)) = &found {
+        app_ctx.remember_key("msd10", firmware_name, "MSD10", name);
+    }
+    Ok(found.map(|(name, (key_bytes, firmware_type))| (name, key_bytes, firmware_type)))
+}
+
+//the firmware name and encrypted TOC of the first header, which every passphrase check is done against
+type FirstToc = (String, Vec<u8>);
+
+fn read_first_toc(mut file: &File) -> Result<Option<FirstToc>, Box<dyn std::error::Error>> {
+    file.seek(SeekFrom::Start(0))?;
+
+    let header: FileHeader = file.read_le()?;
+    for _i in 0..header.section_count {
+        let _section: SectionEntry = file.read_le()?;
+    }
+    let _zero_padding = common::read_exact(&mut file, 4)?;
+    let header_count: u32 = file.read_le()?;
+    if header_count == 0 {
+        return Ok(None)
+    }
+    let first_header: HeaderEntry = file.read_le()?;
+
+    Ok(Some((first_header.name(), common::read_file(file, first_header.offset as u64, first_header.size as usize)?)))
+}
+
+pub fn find_msd10_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Expected file")?;
+    let Some((firmware_name, toc_data)) = read_first_toc(file)? else {return Ok(None)};
+    Ok(find_passphrase(app_ctx, &firmware_name, &toc_data)?.map(|(name, _, _)| format!("MSD10 \"{}\"", name)))
+}
+
+//only tizen passphrases can be verified, an old format one would match almost anything
+pub fn hunt_msd10_key(app_ctx: &AppContext) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+    if is_msd10_file(app_ctx)?.is_none() {
+        return Ok(None)
+    }
+    let Some((_, toc_data)) = read_first_toc(file)? else {return Ok(None)};
+    Ok(Some(HuntTarget::new("MSD10", vec![vec![16]], move |key| is_tizen_passphrase(&toc_data, key))))
+}
+
 pub fn extract_msd10(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
 
@@ -58,38 +118,7 @@ pub fn extract_msd10(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box
     let toc_data = common::read_file(&file, toc_offset as u64, toc_size as usize)?;
 
     //find passphrase
-    let mut passphrase_bytes: Option<&Vec<u8>> = None;
-    let mut passphrase_name: &str = "";
-    let mut firmware_type: Option<FirmwareType> = None;
-    for (name, keys) in app_ctx.keys.get_collection("MSD10")? {
-        let key_bytes= keys.first().unwrap();
-        if key_bytes.len() == 20 {
-            match decrypt_aes_salted_old(&toc_data, &key_bytes) {
-                Ok(_) => {  //TODO: figure out how to verify pre-tizen OUITH
-                    passphrase_bytes = Some(key_bytes);
-                    passphrase_name = name;
-                    firmware_type = Some(FirmwareType::Old);
-                    break
-                },
-                Err(_) => continue,
-            };
-        }
-        else if key_bytes.len() == 16 {
-            match decrypt_aes_salted_tizen(&toc_data, &key_bytes) {
-                Ok(decrypted) => {
-                    if is_valid_ouith(&decrypted) {
-                        passphrase_bytes = Some(key_bytes);
-                        passphrase_name = name;
-                        firmware_type = Some(FirmwareType::Tizen);
-                        break
-                    }
-                },
-                Err(_) => continue,
-            };
-        }
-    }
-
-    let (passphrase_bytes, firmware_type) = if let (Some(p), Some(t)) = (passphrase_bytes, firmware_type) {
+    let (passphrase_bytes, firmware_type) = if let Some((passphrase_name, p, t)) = find_passphrase(app_ctx, firmware_name, &toc_data)? {
         println!("Using passphrase: {}", passphrase_name);
         (p, t)
     } else {
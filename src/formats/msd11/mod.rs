@@ -1,15 +1,17 @@
 mod include;
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
 
-use std::fs::{self, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::path::Path;
-use std::io::Write;
+use std::io::{Write, Seek, SeekFrom};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::keytrial::find_matching_key;
 use crate::utils::global::opt_dump_dec_hdr;
-use crate::formats::msd::{decrypt_aes_salted_tizen, decrypt_aes_tizen, is_valid_ouith};
+use crate::formats::msd::{decrypt_aes_salted_tizen, decrypt_aes_tizen, is_tizen_passphrase};
 use crate::formats::msd::msd_ouith_parser_tizen_1_9::{parse_blob_1_9};
 use include::*;
 
@@ -24,6 +26,54 @@ pub fn is_msd11_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<d
     }
 }
 
+//name and passphrase
+type Passphrase = (String, Vec<u8>);
+
+fn find_passphrase(app_ctx: &AppContext, firmware_name: &str, toc_data: &[u8]) -> Result<Option<Passphrase>, Box<dyn std::error::Error>> {
+    let found = find_matching_key(&app_ctx.get_hinted_collection("msd11", firmware_name, "MSD11")?, |keys| {
+        let key_bytes= keys.first().unwrap();
+        is_tizen_passphrase(toc_data, key_bytes).then(|| key_bytes.clone())
+    });
+    if let Some((name, _)) = &found {
+        app_ctx.remember_key("msd11", firmware_name, "MSD11", name);
+    }
+    Ok(found)
+}
+
+//the firmware name and encrypted TOC of the first header, which every passphrase check is done against
+type FirstToc = (String, Vec<u8>);
+
+fn read_first_toc(mut file: &File) -> Result<Option<FirstToc>, Box<dyn std::error::Error>> {
+    file.seek(SeekFrom::Start(0))?;
+
+    let header: FileHeader = file.read_le()?;
+    for _i in 0..header.section_count {
+        let _section: SectionEntry = file.read_le()?;
+    }
+    let header_count: u32 = file.read_le()?;
+    if header_count == 0 {
+        return Ok(None)
+    }
+    let first_header: HeaderEntry = file.read_le()?;
+
+    Ok(Some((first_header.name(), common::read_file(file, first_header.offset + 8, (first_header.size as usize).saturating_sub(8))?)))
+}
+
+pub fn find_msd11_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Expected file")?;
+    let Some((firmware_name, toc_data)) = read_first_toc(file)? else {return Ok(None)};
+    Ok(find_passphrase(app_ctx, &firmware_name, &toc_data)?.map(|(name, _)| format!("MSD11 \"{}\"", name)))
+}
+
+pub fn hunt_msd11_key(app_ctx: &AppContext) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+    if is_msd11_file(app_ctx)?.is_none() {
+        return Ok(None)
+    }
+    let Some((_, toc_data)) = read_first_toc(file)? else {return Ok(None)};
+    Ok(Some(HuntTarget::new("MSD11", vec![vec![16], vec![32]], move |key| is_tizen_passphrase(&toc_data, key))))
+}
+
 pub fn extract_msd11(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
 
@@ -56,23 +106,7 @@ pub fn extract_msd11(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box
     let toc_data = common::read_file(&file, toc_offset as u64, toc_size as usize)?;
 
     //find passphrase
-    let mut passphrase_bytes: Option<&Vec<u8>> = None;
-    let mut passphrase_name: &str = "";
-    for (name, keys) in app_ctx.keys.get_collection("MSD11")? {
-        let key_bytes= keys.first().unwrap();
-        match decrypt_aes_salted_tizen(&toc_data, &key_bytes) {
-            Ok(decrypted) => {
-                if is_valid_ouith(&decrypted) {
-                    passphrase_bytes = Some(key_bytes);
-                    passphrase_name = name;
-                    break
-                }  
-            },
-            Err(_) => continue,
-        };
-    }
-
-    let passphrase_bytes = if let Some(p) = passphrase_bytes {
+    let passphrase_bytes = if let Some((passphrase_name, p)) = find_passphrase(app_ctx, firmware_name, &toc_data)? {
         println!("Using passphrase: {}", passphrase_name);
         p
     } else {
@@ -8,6 +8,8 @@ use std::fs::{self, OpenOptions};
 use std::io::Write;
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, prepare_keys};
+use crate::ukf::part_as_arr;
 use include::*;
 
 struct MsFirmCtx {
@@ -21,17 +23,20 @@ pub fn is_msfirm_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<
     let header = common::read_file(&file, 0, 0x80)?;
     if header.len() != 0x80 {return Ok(None)};
     //check hash for all keys
-    for (key_name, keys) in app_ctx.keys.get_collection("MSFIRM")? {
-        let key: [u8; 64] = keys.first().unwrap().as_slice().try_into().unwrap();
-        let mut hash_data = header[..108].to_vec();
-        hash_data.extend_from_slice(&[0u8; 20]);
-        if calc_hash(&key, &hash_data) == header[108..] {
-            return Ok(Some(Box::new(MsFirmCtx { key_name: key_name.to_string(), key })));
-        }
+    let mut hash_data = header[..108].to_vec();
+    hash_data.extend_from_slice(&[0u8; 20]);
+    let keys = prepare_keys(&app_ctx.get_collection("MSFIRM")?, |name, keys| part_as_arr::<64>(&format!("MSFIRM \"{}\"", name), keys, 0))?;
+    if let Some((i, _)) = find_first(&keys, |(_, key)| (calc_hash(key, &hash_data) == header[108..]).then_some(())) {
+        let (key_name, key) = &keys[i];
+        return Ok(Some(Box::new(MsFirmCtx { key_name: key_name.clone(), key: *key })));
     }
     Ok(None)
 }
 
+pub fn find_msfirm_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(is_msfirm_file(app_ctx)?.map(|ctx| format!("MSFIRM \"{}\"", ctx.downcast::<MsFirmCtx>().expect("Missing context").key_name)))
+}
+
 pub fn extract_msfirm(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<MsFirmCtx>().expect("Missing context");
@@ -16,7 +16,7 @@ use include::*;
 pub fn is_mstar_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
 
-    let header = common::read_file(&file, 0, 32768)?;
+    let header = common::read_file(file, app_ctx.option_number("mstar:offset")?.unwrap_or(0), 32768)?;
     let header_string = String::from_utf8_lossy(&header);
     if header_string.contains("filepartload"){
         Ok(Some(Box::new(())))
@@ -28,29 +28,28 @@ pub fn is_mstar_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<d
 pub fn extract_mstar(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let file = app_ctx.file().ok_or("Extractor expected file")?;
 
-    let mut script = common::read_file(&file, 0, 32768)?;
-
-    if let Some(pos) = script.iter().position(|x| [0x00, 0xFF].contains(x)) {
-        script.truncate(pos);
-    }
-
-    let mut script_string = String::from_utf8_lossy(&script);
-    //println!("{}", script_string);
-    if script_string == "" {
-        //try for hisense
-        println!("Failed to get script at 0x0, trying 0x1000...");
-        script = common::read_file(&file, 4096, 32768)?;
-
+    //the script is at 0x0, or at 0x1000 for hisense. the offset option skips the guessing
+    let offsets = match app_ctx.option_number("mstar:offset")? {
+        Some(offset) => vec![offset],
+        None => vec![0, 0x1000],
+    };
+    let mut script = Vec::new();
+    for offset in offsets {
+        script = common::read_file(file, offset, 32768)?;
         if let Some(pos) = script.iter().position(|x| [0x00, 0xFF].contains(x)) {
             script.truncate(pos);
         }
-
-        script_string = String::from_utf8_lossy(&script);
-
-        if script_string == "" {
-            return Err("Failed to get script".into());
+        if !script.is_empty() {
+            break
         }
+        println!("Failed to get script at 0x{:x}", offset);
+    }
+    if script.is_empty() {
+        return Err("Failed to get script".into());
     }
+
+    let script_string = String::from_utf8_lossy(&script);
+    //println!("{}", script_string);
     opt_dump_dec_hdr(app_ctx, &script, "script")?;
 
     let lines: Vec<&str> = script_string.lines().map(|l| l.trim()).collect();
@@ -58,6 +58,7 @@ pub fn extract_mstar_secure_old(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Resu
         output_dir: app_ctx.output_dir.clone(), 
         options: app_ctx.options,
         keys: app_ctx.keys,
+        hints: app_ctx.hints,
     };
 
     //do check just in case and extract
@@ -3,6 +3,8 @@ pub mod lzhs;
 mod huffman_tables;
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
+use crate::ukf::{Collection, part_as_arr};
 
 use std::path::Path;
 use std::fs::{self, OpenOptions};
@@ -12,6 +14,7 @@ use binrw::BinReaderExt;
 use crate::utils::common;
 use crate::utils::global::opt_dump_dec_hdr;
 use crate::utils::aes::{decrypt_aes128_cbc_nopad};
+use crate::utils::keytrial::{find_first, prepare_keys};
 use lzhs::{decompress_mtk_to_file};
 use include::*;
 
@@ -41,6 +44,59 @@ pub fn is_mtk_pkg_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box
     }
 }
 
+//name, key and iv
+type CustomKey = (String, [u8; 16], [u8; 16]);
+
+//the MTK_PKG_CUSTOM_KEYS entry that decrypts block to something check accepts, block should be the first one of the data
+pub fn find_custom_key(collection: &Collection, block: &[u8], check: impl Fn(&[u8]) -> bool + Sync) -> Result<Option<CustomKey>, Box<dyn std::error::Error>> {
+    let custom_keys = prepare_keys(collection, |name, keys| {
+        let owner = format!("MTK_PKG_CUSTOM_KEYS \"{}\"", name);
+        Ok((part_as_arr::<16>(&owner, keys, 0)?, part_as_arr::<16>(&owner, keys, 1)?))
+    })?;
+    let found = find_first(&custom_keys, |(_, (key, iv))| decrypt_aes128_cbc_nopad(block, key, iv).is_ok_and(|d| check(&d)).then_some(()));
+    Ok(found.map(|(i, _)| {
+        let (name, (key, iv)) = &custom_keys[i];
+        (name.clone(), *key, *iv)
+    }))
+}
+
+//the first encrypted part that the vendor magic key does not decrypt is the one a custom key is needed for
+pub fn hunt_mtk_pkg_key(app_ctx: &AppContext) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let mut file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+    let ctx = match is_mtk_pkg_file(app_ctx)? {
+        Some(ctx) => ctx.downcast::<MtkPkgContext>().expect("Missing context"),
+        None => return Ok(None),
+    };
+    let file_size = file.metadata()?.len();
+    let hdr: Header = Cursor::new(&ctx.decrypted_header).read_le()?;
+
+    let mut vendor_key = [0u8; 16];
+    for i in 0..4 {
+        vendor_key[i * 4..(i + 1) * 4].copy_from_slice(&hdr.vendor_magic_bytes);
+    }
+
+    let first_part = HEADER_SIZE as u64 + if ctx.is_philips_variant {PHILIPS_EXTRA_HEADER_SIZE as u64} else {0};
+    file.seek(SeekFrom::Start(first_part))?;
+    while file.stream_position()? < file_size {
+        let part_entry: PartEntry = file.read_le()?;
+        if !part_entry.is_valid() {break};
+
+        let part_start = file.stream_position()?;
+        if part_entry.is_encrypted() && part_entry.size != 0 {
+            //one CBC block only depends on the key and iv, so it is enough to check the magic
+            let block = common::read_exact(&mut file, 16)?;
+            if !decrypt_aes128_cbc_nopad(&block, &vendor_key, &[0x00; 16])?.starts_with(MTK_RESERVED_MAGIC) {
+                return Ok(Some(HuntTarget::new("MTK_PKG_CUSTOM_KEYS", vec![vec![16, 16]], move |key| {
+                    decrypt_aes128_cbc_nopad(&block, key[..16].try_into().unwrap(), key[16..].try_into().unwrap())
+                        .is_ok_and(|d| d.starts_with(MTK_RESERVED_MAGIC))
+                })))
+            }
+        }
+        file.seek(SeekFrom::Start(part_start + part_entry.size as u64 + CRYPTED_HEADER_SIZE as u64))?;
+    }
+    Ok(None)
+}
+
 pub fn extract_mtk_pkg(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<MtkPkgContext>().expect("Missing context");
@@ -97,17 +153,12 @@ pub fn extract_mtk_pkg(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Bo
 
             } else {
                 //try decrypting with one of custom keys
-                for (name, keys) in app_ctx.keys.get_collection("MTK_PKG_CUSTOM_KEYS")? {
-                    let key_array: [u8; 16] = keys[0].as_slice().try_into()?;
-                    let iv_array: [u8; 16] = keys[1].as_slice().try_into()?;
-                    let try_decrypt = decrypt_aes128_cbc_nopad(&crypted_header, &key_array, &iv_array)?;
-
-                    if try_decrypt.starts_with(MTK_RESERVED_MAGIC) {
-                        println!("- Decrypting with key {}...", name);
-                        matching_key = Some(key_array);
-                        matching_iv = Some(iv_array);
-                        break
-                    }
+                let custom_keys = app_ctx.get_hinted_collection("mtk_pkg", &hdr.vendor_magic(), "MTK_PKG_CUSTOM_KEYS")?;
+                if let Some((name, key_array, iv_array)) = find_custom_key(&custom_keys, &crypted_header[..16], |d| d.starts_with(MTK_RESERVED_MAGIC))? {
+                    println!("- Decrypting with key {}...", name);
+                    app_ctx.remember_key("mtk_pkg", &hdr.vendor_magic(), "MTK_PKG_CUSTOM_KEYS", &name);
+                    matching_key = Some(key_array);
+                    matching_iv = Some(iv_array);
                 }
             }
 
@@ -1,6 +1,7 @@
 mod include;
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
 
 use std::path::Path;
 use std::fs::{self, OpenOptions};
@@ -10,6 +11,7 @@ use binrw::BinReaderExt;
 use crate::utils::common;
 use crate::utils::global::opt_dump_dec_hdr;
 use crate::utils::aes::{decrypt_aes128_cbc_nopad};
+use crate::formats::mtk_pkg::find_custom_key;
 use crate::formats::mtk_pkg::lzhs::{decompress_mtk_to_file};
 use crate::formats::mtk_pkg::include::{Header, PartEntry, MTK_HEADER_MAGIC, MTK_META_MAGIC, MTK_META_PAD_MAGIC};
 use include::*;
@@ -25,24 +27,36 @@ pub fn is_mtk_pkg_new_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>,
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
 
     let encrypted_header = common::read_file(&file, 0, HEADER_SIZE)?;
-    for (name, keys) in app_ctx.keys.get_collection("MTK_PKG_CUSTOM_KEYS")? {
-        let key_array: [u8; 16] = keys[0].as_slice().try_into()?;
-        let iv_array: [u8; 16] = keys[1].as_slice().try_into()?;
-        let try_decrypt = decrypt_aes128_cbc_nopad(&encrypted_header, &key_array, &iv_array)?;
-
-        if &try_decrypt[4..12] == MTK_HEADER_MAGIC {    
-            return Ok(Some(Box::new(MtkPkgNewContext {
-                matching_key_name: name.to_string(),
-                matching_key_key: key_array,
-                matching_key_iv: iv_array,
-                decrypted_header: try_decrypt
-            })));
-        }
+    //the vendor magic is encrypted too, so key hints are kept for the whole format
+    let custom_keys = app_ctx.get_hinted_collection("mtk_pkg_new", "", "MTK_PKG_CUSTOM_KEYS")?;
+    if let Some((name, key_array, iv_array)) = find_custom_key(&custom_keys, &encrypted_header[..16], |d| &d[4..12] == MTK_HEADER_MAGIC)? {
+        app_ctx.remember_key("mtk_pkg_new", "", "MTK_PKG_CUSTOM_KEYS", &name);
+        return Ok(Some(Box::new(MtkPkgNewContext {
+            matching_key_name: name,
+            matching_key_key: key_array,
+            matching_key_iv: iv_array,
+            decrypted_header: decrypt_aes128_cbc_nopad(&encrypted_header, &key_array, &iv_array)?
+        })));
     }
 
     Ok(None)
 }
 
+pub fn find_mtk_pkg_new_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(is_mtk_pkg_new_file(app_ctx)?.map(|ctx| format!("MTK_PKG_CUSTOM_KEYS \"{}\"", ctx.downcast::<MtkPkgNewContext>().expect("Missing context").matching_key_name)))
+}
+
+//the magic is inside the first CBC block, so the rest of the header is not needed
+pub fn hunt_mtk_pkg_new_key(app_ctx: &AppContext) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let block = common::read_file(file, 0, 16)?;
+    Ok(Some(HuntTarget::new("MTK_PKG_CUSTOM_KEYS", vec![vec![16, 16]], move |key| {
+        decrypt_aes128_cbc_nopad(&block, key[..16].try_into().unwrap(), key[16..].try_into().unwrap())
+            .is_ok_and(|d| &d[4..12] == MTK_HEADER_MAGIC)
+    })))
+}
+
 pub fn extract_mtk_pkg_new(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<MtkPkgNewContext>().expect("Missing context");
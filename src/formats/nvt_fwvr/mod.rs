@@ -72,6 +72,7 @@ pub fn extract_nvt_fwvr(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(),
         output_dir: app_ctx.output_dir.clone(), 
         options: app_ctx.options,
         keys: app_ctx.keys,
+        hints: app_ctx.hints,
     };
     if let Some(result) = is_nvt_timg_pkg_file(&in_ctx)? {
         extract_nvt_timg_pkg(&in_ctx, result)?;
@@ -75,7 +75,8 @@ pub fn extract_nvt_timg_pkg(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(
             //decrypt only aligned data, rest is plain
             let align_size = pimg.flen as usize & !0xF;
             let iv = app_ctx.keys.get_key_as_arr::<16>("NVT_FW40_IMG_ENC_IV", 0)?;
-            let decrypted= if app_ctx.has_option("nvt_timg_pkg:use_v2_key") {   //cannot detect if v2 key is used, have to specify manually
+            let use_v2_key = app_ctx.has_option("nvt_timg_pkg:use_v2_key") || app_ctx.option_choice("nvt_timg_pkg:variant", &["v1", "v2"])? == Some("v2");
+            let decrypted= if use_v2_key {   //cannot detect if v2 key is used, have to specify manually
                 let key = app_ctx.keys.get_key_as_arr::<32>("NVT_FW40_IMG_ENC_V2_KEY", 0)?;
                 decrypt_aes256_cbc_nopad(&data[..align_size], &key, &iv)?
 
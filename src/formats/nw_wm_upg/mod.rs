@@ -8,6 +8,7 @@ use std::fs::{self, OpenOptions};
 use std::io::Write;
 
 use crate::utils::common;
+use crate::utils::keytrial::find_matching_key;
 use include::*;
 
 struct NwWmUpgCtx {
@@ -22,34 +23,37 @@ pub fn is_nw_wm_upg_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, B
 
     let (aes_passkey, aes_passiv) = app_ctx.keys.get_double_key_as_arr::<16, 16>("NW_WM_UPG_AES_PASS")?;
     let des_passkey = app_ctx.keys.get_key_as_arr::<8>("NW_WM_UPG_DES_PASSKEY", 0)?;
-    for (name, keys) in app_ctx.keys.get_collection("NW_WM_UPG_KAS")? {
-        let kas_bytes= keys.first().unwrap();
+    let found = find_matching_key(&app_ctx.get_collection("NW_WM_UPG_KAS")?, |keys| {
+        let kas_bytes = keys.first()?;
         if kas_bytes.len() == 32 {  //AES
-            let decrypted_kas = decrypt_aes128_cbc_nopad(&kas_bytes, &aes_passkey, &aes_passiv)?;
+            let decrypted_kas = decrypt_aes128_cbc_nopad(kas_bytes, &aes_passkey, &aes_passiv).ok()?;
             let aes_key: [u8; 16] = decrypted_kas[..16].try_into().unwrap();
             let signature = &decrypted_kas[16..];
 
-            let decrypted_hdr = decrypt_aes128_ecb(&enc_hdr, &aes_key)?;
-            if &decrypted_hdr[..16] == signature {
-                return Ok(Some(Box::new(NwWmUpgCtx {key_name: name.to_string(), encryption: EncryptionMode::Aes(aes_key)})));
-            }
+            //only the first block is compared
+            let decrypted_hdr = decrypt_aes128_ecb(&enc_hdr[..16], &aes_key).ok()?;
+            (decrypted_hdr[..16] == *signature).then_some(EncryptionMode::Aes(aes_key))
 
         } else if kas_bytes.len() == 16 { //DES
-            let decrypted_kas = decrypt_des_ecb(&des_passkey, &kas_bytes)?;
+            let decrypted_kas = decrypt_des_ecb(&des_passkey, kas_bytes).ok()?;
             let des_key: [u8; 8] = decrypted_kas[..8].try_into().unwrap();
             let signature = &decrypted_kas[8..];
 
-            let decrypted_hdr = decrypt_des_ecb(&des_key, &enc_hdr)?;
-            if &decrypted_hdr[..8] == signature {
-                return Ok(Some(Box::new(NwWmUpgCtx {key_name: name.to_string(), encryption: EncryptionMode::Des(des_key)})));
-            }
+            let decrypted_hdr = decrypt_des_ecb(&des_key, &enc_hdr[..8]).ok()?;
+            (decrypted_hdr[..8] == *signature).then_some(EncryptionMode::Des(des_key))
+        } else {
+            None
         }
-    }
+    });
 
-    Ok(None)
+    Ok(found.map(|(name, encryption)| Box::new(NwWmUpgCtx {key_name: name, encryption}) as Box<dyn Any>))
     
 }
 
+pub fn find_nw_wm_upg_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(is_nw_wm_upg_file(app_ctx)?.map(|ctx| format!("NW_WM_UPG_KAS \"{}\"", ctx.downcast::<NwWmUpgCtx>().expect("Missing context").key_name)))
+}
+
 pub fn extract_nw_wm_upg(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<NwWmUpgCtx>().expect("Missing context");
@@ -1,4 +1,5 @@
 use super::include::ONKYO_MAGIC;
+use crate::utils::aes::decrypt_aes128_cbc_nopad;
 
 pub fn ub_encrypte_block(input: &[u8], key: &[u8; 8]) -> Vec<u8> {
     let mut output = Vec::with_capacity(input.len());
@@ -50,4 +51,92 @@ pub fn calc_key(ciphertext: &[u8]) -> [u8; 8] {
     }
 
     key
-}
\ No newline at end of file
+}
+
+//older files use the XOR stream with a header and a data key, newer ones AES-128-CBC
+pub enum Cipher {
+    Old {header_key: [u8; 8], data_key: [u8; 8]},
+    Aes {name: String, key: [u8; 16], iv: [u8; 16]},
+}
+
+pub enum EntryData {
+    Decrypted(Vec<u8>),
+    Pack(Vec<u8>),      //package inside a package, still encrypted
+    Raw(Vec<u8>),
+}
+
+impl Cipher {
+    //AES can only decrypt whole blocks, so the header is read rounded up
+    pub fn header_read_size(&self, header_size: u32) -> usize {
+        match self {
+            Cipher::Old {..} => header_size as usize,
+            Cipher::Aes {..} => (header_size as usize).div_ceil(16) * 16,
+        }
+    }
+
+    pub fn decrypt_header(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Cipher::Old {header_key, ..} => ub_encrypte_block(data, header_key),
+            Cipher::Aes {key, iv, ..} => decrypt_aes_aligned(data, key, iv),
+        }
+    }
+
+    pub fn decrypt_entry(&self, data: Vec<u8>) -> EntryData {
+        if data.len() < 32 {
+            return EntryData::Raw(data)
+        }
+        match self {
+            Cipher::Old {header_key, data_key} => {
+                //try using standard data key
+                if ub_encrypte_block(&data[..16], data_key).starts_with(ONKYO_MAGIC) {
+                    return EntryData::Decrypted(strip_magic(ub_encrypte_block(&data, data_key)))
+                }
+                //try header key, if success it means this is a package inside a package
+                if ub_encrypte_block(&data[..16], header_key).starts_with(ONKYO_MAGIC) {
+                    return EntryData::Pack(data)
+                }
+                //if not matched with data key, try to calc key
+                let calced_key = calc_key(&data[..8]);
+                if ub_encrypte_block(&data[..16], &calced_key).starts_with(ONKYO_MAGIC) {
+                    return EntryData::Decrypted(strip_magic(ub_encrypte_block(&data, &calced_key)))
+                }
+                EntryData::Raw(data)
+            }
+            //data and inner packages share the key, a package is told apart by its header
+            Cipher::Aes {key, iv, ..} => {
+                let first = decrypt_aes_aligned(&data[..32], key, iv);
+                if !first.starts_with(ONKYO_MAGIC) {
+                    return EntryData::Raw(data)
+                }
+                let header_size = u32::from_le_bytes(first[16..20].try_into().unwrap()) as usize;
+                let read_size = header_size.div_ceil(16) * 16;
+                if header_size >= 48 && read_size <= data.len() && is_pack_header(&decrypt_aes_aligned(&data[..read_size], key, iv), header_size) {
+                    return EntryData::Pack(data)
+                }
+                EntryData::Decrypted(strip_magic(decrypt_aes_aligned(&data, key, iv)))
+            }
+        }
+    }
+}
+
+//section offsets of the header all point inside it
+fn is_pack_header(dec_hdr: &[u8], header_size: usize) -> bool {
+    [24, 28, 32].iter().all(|&o| {
+        let offset = u32::from_le_bytes(dec_hdr[o..o + 4].try_into().unwrap()) as usize;
+        (48..header_size).contains(&offset)
+    })
+}
+
+//only the block aligned part is encrypted
+fn decrypt_aes_aligned(data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> Vec<u8> {
+    let aligned = data.len() - data.len() % 16;
+    let mut out = decrypt_aes128_cbc_nopad(&data[..aligned], key, iv).expect("aligned data");
+    out.extend_from_slice(&data[aligned..]);
+    out
+}
+
+//remove ONKYO Encryption heading
+fn strip_magic(mut data: Vec<u8>) -> Vec<u8> {
+    data.drain(0..16);
+    data
+}
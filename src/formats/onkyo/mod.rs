@@ -4,44 +4,97 @@ use std::any::Any;
 use crate::AppContext;
 use crate::utils::global::opt_dump_dec_hdr;
 
-use std::path::Path;
-use std::fs::{self, OpenOptions};
-use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, optional_aes_pairs};
 use include::*;
 use crypto::*;
 
+trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
 struct OnkyoCtx {
-    header_size: u32,
+    cipher: Cipher,
+    pack_files: Vec<PathBuf>,   //all .of* files of the release when a folder is given
 }
 
-pub fn is_onkyo_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
-    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
-
-    let enc_inihdr = common::read_file(&file, 0, 20)?;
-    let hdr_key = app_ctx.keys.get_key_as_arr::<8>("ONKYO_HEADER_KEY", 0)?;
-    let dec_inihdr= ub_encrypte_block(&enc_inihdr, &hdr_key);
+struct Pack<'a> {
+    reader: Box<dyn ReadSeek + 'a>,
+    info: PackInfo,
+    entries: Vec<IDsVersionsEntry>,
+    table: Vec<TableEntry>,
+}
 
-    if dec_inihdr.starts_with(ONKYO_MAGIC) {
-        let header_size = u32::from_le_bytes(dec_inihdr[16..20].try_into().unwrap());
-        Ok(Some(Box::new(OnkyoCtx {header_size})))
-    } else {
-        Ok(None)
+//the first two blocks hold the magic and the header size with either cipher
+fn detect_cipher(app_ctx: &AppContext, enc_inihdr: &[u8]) -> Result<Option<Cipher>, Box<dyn std::error::Error>> {
+    if enc_inihdr.len() < 32 {
+        return Ok(None)
+    }
+    let header_key = app_ctx.keys.get_key_as_arr::<8>("ONKYO_HEADER_KEY", 0)?;
+    if ub_encrypte_block(&enc_inihdr[..20], &header_key).starts_with(ONKYO_MAGIC) {
+        let data_key = app_ctx.keys.get_key_as_arr::<8>("ONKYO_DATA_KEY", 0)?;
+        return Ok(Some(Cipher::Old {header_key, data_key}))
     }
+
+    //newer files
+    let Some(keys) = optional_aes_pairs(app_ctx, "ONKYO_AES")? else {
+        return Ok(None)
+    };
+    let Some((i, _)) = find_first(&keys, |(_, (key, iv))| {
+        Cipher::Aes {name: String::new(), key: *key, iv: *iv}.decrypt_header(&enc_inihdr[..32]).starts_with(ONKYO_MAGIC).then_some(())
+    }) else {
+        return Ok(None)
+    };
+    let (name, (key, iv)) = &keys[i];
+    Ok(Some(Cipher::Aes {name: name.clone(), key: *key, iv: *iv}))
 }
 
-pub fn extract_onkyo(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = app_ctx.file().ok_or("Extractor expected file")?;
-    let ctx = ctx.downcast::<OnkyoCtx>().expect("Missing context");
+//"FW.of1", "FW.of2"... - sorted by the pack number
+fn pack_number(path: &Path) -> Option<u32> {
+    path.extension()?.to_str()?.to_ascii_lowercase().strip_prefix("of")?.parse().ok()
+}
+
+fn find_pack_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && pack_number(p).is_some())
+        .collect();
+    files.sort_by_key(|p| pack_number(p));
+    Ok(files)
+}
+
+pub fn is_onkyo_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    if let Some(file) = app_ctx.file() {
+        let enc_inihdr = common::read_file(file, 0, 32)?;
+        return Ok(detect_cipher(app_ctx, &enc_inihdr)?.map(|cipher| Box::new(OnkyoCtx {cipher, pack_files: Vec::new()}) as Box<dyn Any>))
+    }
+
+    //folder with the pack files of one release
+    let Some(dir) = app_ctx.dir() else {
+        return Ok(None)
+    };
+    let pack_files = find_pack_files(dir)?;
+    let Some(first) = pack_files.first() else {
+        return Ok(None)
+    };
+    let enc_inihdr = common::read_file(&File::open(first)?, 0, 32)?;
+    Ok(detect_cipher(app_ctx, &enc_inihdr)?.map(|cipher| Box::new(OnkyoCtx {cipher, pack_files}) as Box<dyn Any>))
+}
 
-    println!("Header size: {}", ctx.header_size);
+fn read_pack<'a>(app_ctx: &AppContext, mut reader: Box<dyn ReadSeek + 'a>, cipher: &Cipher, name: &str) -> Result<Pack<'a>, Box<dyn std::error::Error>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let enc_inihdr = common::read_exact(&mut reader, 32)?;
+    let header_size = u32::from_le_bytes(cipher.decrypt_header(&enc_inihdr)[16..20].try_into().unwrap());
+    println!("Header size: {}", header_size);
 
-    let enc_hdr = common::read_exact(&mut file, ctx.header_size as usize)?;
-    let hdr_key = app_ctx.keys.get_key_as_arr::<8>("ONKYO_HEADER_KEY", 0)?;
-    let dec_hdr = ub_encrypte_block(&enc_hdr, &hdr_key);
-    opt_dump_dec_hdr(app_ctx, &dec_hdr, "header")?;
+    reader.seek(SeekFrom::Start(0))?;
+    let enc_hdr = common::read_exact(&mut reader, cipher.header_read_size(header_size))?;
+    let dec_hdr = cipher.decrypt_header(&enc_hdr);
+    opt_dump_dec_hdr(app_ctx, &dec_hdr, name)?;
     let mut hdr_rdr = Cursor::new(dec_hdr);
 
     let hdr: Header = hdr_rdr.read_le()?;
@@ -52,10 +105,6 @@ pub fn extract_onkyo(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<
     println!("Info -\nPackage ID: {}\nVersion: {}\nEntry count: {}\nEntries in file: {}\nPack: {}/{}",
             info.package_id(), info.package_version(), info.entry_count, info.entries_in_file, info.pack_id, info.pack_count,);
 
-    if info.entries_in_file == 0 {
-        return Err("There is nothing to extract in this pack".into())   //should this be an error?
-    }
-
     //..."IDsVersions" section
     hdr_rdr.seek(SeekFrom::Start(hdr.ids_versions_offset as u64))?;
     let mut entries: Vec<IDsVersionsEntry> = Vec::new();
@@ -66,72 +115,111 @@ pub fn extract_onkyo(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<
 
     //...table section
     hdr_rdr.seek(SeekFrom::Start(hdr.table_offset as u64))?;
-    let mut data_entries: Vec<TableEntry> = Vec::new();
+    let mut table: Vec<TableEntry> = Vec::new();
     for _ in 0..info.entry_count {
         let entry: TableEntry = hdr_rdr.read_le()?;
-        data_entries.push(entry);
+        table.push(entry);
     }
 
-    //
-    let mut act_ei = 0;
-    for (i, entry) in entries.iter().enumerate() {
-        let data_entry = &data_entries[i];
+    Ok(Pack {reader, info, entries, table})
+}
+
+//the entry list of the first pack decides what is extracted, the data is taken from the pack it is located in
+fn extract_packs(app_ctx: &AppContext, packs: &mut [Pack], cipher: &Cipher, output_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let entries_in_release: u32 = packs.iter().map(|p| p.info.entries_in_file as u32).sum();
+    if entries_in_release == 0 {
+        return Err("There is nothing to extract in this pack".into())   //should this be an error?
+    }
+
+    //entries are matched to the other tables by index, so all packs have to be of the same release
+    let (package_id, entry_count) = (packs[0].info.package_id(), packs[0].info.entry_count);
+    if let Some(other) = packs.iter().find(|p| p.info.package_id() != package_id || p.info.entry_count != entry_count) {
+        return Err(format!("Pack {} is from another release (package ID {}, {} entries), expected package ID {} with {} entries!",
+                other.info.pack_id, other.info.package_id(), other.info.entry_count, package_id, entry_count).into())
+    }
 
-        if entry.pack_location == 0 || (data_entry.offset == 0 && data_entry.checksum == 0){
+    let mut act_ei = 0;
+    for i in 0..packs[0].entries.len() {
+        let entry = &packs[0].entries[i];
+        if entry.pack_location == 0 {
+            continue;
+        }
+        let id = entry.id();
+        let pack_location = entry.pack_location;
+
+        //without the other packs, entries located elsewhere have an empty table entry here
+        let source = match packs.iter().position(|p| p.info.pack_id == pack_location) {
+            Some(source) => source,
+            None if packs[0].table.get(i).is_some_and(|e| e.offset != 0 || e.checksum != 0) => 0,
+            None => {
+                if packs[0].info.pack_count > 1 {
+                    println!("\n{} is in pack {}, which was not given - pass the folder with all pack files to extract it", id, pack_location);
+                }
+                continue
+            }
+        };
+        let Some(data_entry) = packs[source].table.get(i) else {
+            continue
+        };
+        if data_entry.offset == 0 && data_entry.checksum == 0 {
             continue;
         }
+        let (size, offset) = (data_entry.size, data_entry.offset);
 
         act_ei += 1;
         println!("\n({}/{}) - {}, Size: {}, Offset: {}, Pack location: {}",
-                act_ei, info.entries_in_file, entry.id(), data_entry.size, data_entry.offset, entry.pack_location);
-
-        let data = common::read_file(&mut file, data_entry.offset as u64, data_entry.size as usize)?;
-        let mut out_data;
-
-        // -- try to decrypt --
-        let mut dec_key: Option<[u8; 8]> = None;
-        let mut is_pack = false;
-
-        //try using standard data key
-        let data_key = app_ctx.keys.get_key_as_arr::<8>("ONKYO_DATA_KEY", 0)?;
-        if ub_encrypte_block(&data[..16], &data_key).starts_with(ONKYO_MAGIC) {
-            dec_key = Some(data_key);
-
-        //try header key, if success it means this is a package inside a package, and it should not be decrypted
-        } else if ub_encrypte_block(&data[..16], &hdr_key).starts_with(ONKYO_MAGIC) { 
-            is_pack = true;
+                act_ei, entries_in_release, id, size, offset, pack_location);
 
-        //if not matched with data key, try to calc key
-        } else {
-            let calced_key = calc_key(&data[..8]); 
+        let reader = &mut packs[source].reader;
+        reader.seek(SeekFrom::Start(offset as u64))?;
+        let data = common::read_exact(reader, size as usize)?;
 
-            if ub_encrypte_block(&data[..16], &calced_key).starts_with(ONKYO_MAGIC) {  
-                dec_key = Some(calced_key);
+        let out_data = match cipher.decrypt_entry(data) {
+            EntryData::Decrypted(data) => {
+                println!("- Detected encrypted data, decrypting...");
+                data
             }
-        }
-
-        if let Some(key) = dec_key {
-            println!("- Detected encrypted data, decrypting...");
-            out_data = ub_encrypte_block(&data, &key);
-            out_data.drain(0..16);      //remove ONKYO Encryption heading
-
-        } else if is_pack {
-            println!("- Inner pack detected!");     //maybe handle this...
-            out_data = data;
-
-        } else {
-            println!("- Failed to decrypt data or entry is not encrypted, saving raw data...");
-            out_data = data;
-        }
+            EntryData::Pack(data) => {
+                println!("- Inner pack detected, extracting...");
+                let inner_dir = output_dir.join(format!("{}_{}", act_ei, id));
+                let inner = read_pack(app_ctx, Box::new(Cursor::new(data)), cipher, &format!("header_{}", id))?;
+                extract_packs(app_ctx, &mut [inner], cipher, &inner_dir)?;
+                println!("-- Extracted inner pack {}!", id);
+                continue
+            }
+            EntryData::Raw(data) => {
+                println!("- Failed to decrypt data or entry is not encrypted, saving raw data...");
+                data
+            }
+        };
 
-        let output_path = Path::new(&app_ctx.output_dir).join(format!("{}_{}.bin", act_ei, entry.id()));
-        fs::create_dir_all(&app_ctx.output_dir)?;
-        let mut out_file = OpenOptions::new().write(true).create(true).open(output_path)?;        
+        let output_path = output_dir.join(format!("{}_{}.bin", act_ei, id));
+        fs::create_dir_all(output_dir)?;
+        let mut out_file = OpenOptions::new().write(true).create(true).open(output_path)?;
         out_file.write_all(&out_data)?;
 
         println!("-- Saved file!");
-
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn extract_onkyo(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let ctx = ctx.downcast::<OnkyoCtx>().expect("Missing context");
+    if let Cipher::Aes {name, ..} = &ctx.cipher {
+        println!("Newer encryption, key: {}", name);
+    }
+
+    let mut packs = Vec::new();
+    if let Some(file) = app_ctx.file() {
+        packs.push(read_pack(app_ctx, Box::new(file), &ctx.cipher, "header")?);
+    } else {
+        for path in &ctx.pack_files {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            println!("\nPack file: {}", file_name);
+            packs.push(read_pack(app_ctx, Box::new(File::open(path)?), &ctx.cipher, &format!("header_{}", file_name))?);
+        }
+    }
+
+    extract_packs(app_ctx, &mut packs, &ctx.cipher, &app_ctx.output_dir)
+}
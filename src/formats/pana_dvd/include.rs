@@ -2,37 +2,37 @@ use crate::utils::common;
 use binrw::{BinRead, BinWrite};
 use super::pana_dvd_crypto::{decrypt_data};
 use crate::utils::aes::{decrypt_aes128_cbc_nopad};
+use crate::ukf::{Collection, part_as_arr};
+use crate::utils::keytrial::{find_first, prepare_keys};
 
 //find key
 
-pub fn find_key(key_array: &Vec<(String, Vec<Vec<u8>>)>, data: &[u8], expected_magic: &[u8], magic_offset: usize) -> Result<Option<[u8; 8]>, Box<dyn std::error::Error>> {
-    for (_, keys) in key_array {
-        let key_bytes = keys.first().unwrap();
-        let key_array: [u8; 8] = key_bytes.as_slice().try_into()?;
-        let decrypted = decrypt_data(data, &key_array);
-     
-        if decrypted[magic_offset..].starts_with(expected_magic) {
-            return Ok(Some(key_array));
-        }
-    }
-    Ok(None)
+//entry name and key
+pub type PanaKey = (String, [u8; 8]);
+//entry name, AES key, AES iv and key
+pub type PanaAesPair = (String, [u8; 16], [u8; 16], [u8; 8]);
+
+pub fn find_key(key_array: &Collection, data: &[u8], expected_magic: &[u8], magic_offset: usize) -> Result<Option<PanaKey>, Box<dyn std::error::Error>> {
+    let keys = prepare_keys(key_array, |name, keys| part_as_arr::<8>(&format!("PANA_DVD_KEYONLY \"{}\"", name), keys, 0))?;
+    let found = find_first(&keys, |(_, key_array)| {
+        decrypt_data(data, key_array)[magic_offset..].starts_with(expected_magic).then_some(())
+    });
+    Ok(found.map(|(i, _)| keys[i].clone()))
 }
 
-pub fn find_aes_key_pair(key_array: &Vec<(String, Vec<Vec<u8>>)>, data: &[u8], expected_magic: &[u8], magic_offset: usize) -> Result<Option<([u8; 16], [u8; 16], [u8; 8])>, Box<dyn std::error::Error>> {
-    for (_, keys) in key_array {
-        let aes_key: [u8; 16] = keys[0].as_slice().try_into()?;
-        let aes_iv: [u8; 16] = keys[1].as_slice().try_into()?;
-        let aes_decrypted = decrypt_aes128_cbc_nopad(data, &aes_key, &aes_iv)?;
-
-        let key_bytes = &keys[2];
-        let key_array: [u8; 8] = key_bytes.as_slice().try_into()?;
-        let decrypted = decrypt_data(&aes_decrypted, &key_array);
-     
-        if decrypted[magic_offset..].starts_with(expected_magic) {
-            return Ok(Some((aes_key, aes_iv, key_array)));
-        }
-    }
-    Ok(None)
+pub fn find_aes_key_pair(key_array: &Collection, data: &[u8], expected_magic: &[u8], magic_offset: usize) -> Result<Option<PanaAesPair>, Box<dyn std::error::Error>> {
+    let keys = prepare_keys(key_array, |name, keys| {
+        let owner = format!("PANA_DVD_AESPAIR \"{}\"", name);
+        Ok((part_as_arr::<16>(&owner, keys, 0)?, part_as_arr::<16>(&owner, keys, 1)?, part_as_arr::<8>(&owner, keys, 2)?))
+    })?;
+    let found = find_first(&keys, |(_, (aes_key, aes_iv, key_array))| {
+        let aes_decrypted = decrypt_aes128_cbc_nopad(data, aes_key, aes_iv).ok()?;
+        decrypt_data(&aes_decrypted, key_array)[magic_offset..].starts_with(expected_magic).then_some(())
+    });
+    Ok(found.map(|(i, _)| {
+        let (name, (aes_key, aes_iv, key_array)) = &keys[i];
+        (name.clone(), *aes_key, *aes_iv, *key_array)
+    }))
 }
 
 // --
@@ -94,10 +94,20 @@ pub struct MainListHeader {
     pub decompressed_part_size: u32,    
     pub _compression_type: u32,         //see CompressionType
 }
-impl MainListHeader {
-    pub fn entry_count(&self) -> u32 {
-        (&self.list_size - 20) / 8
-    }
+
+//very old files do not have the always 1 field
+#[derive(BinRead)]
+pub struct LegacyMainListHeader {
+    pub _checksum: u32,
+    pub list_size: u32,
+    pub decompressed_part_size: u32,
+    pub _compression_type: u32,
+}
+
+//entry count if the list fits in the module
+pub fn main_entry_count(list_size: u32, header_size: u32, module_size: u32) -> Option<u32> {
+    let entries_size = list_size.checked_sub(header_size)?;
+    (entries_size > 0 && entries_size % 8 == 0 && list_size <= module_size).then_some(entries_size / 8)
 }
 
 #[derive(BinRead)]
@@ -113,7 +123,7 @@ pub struct CompressedFileHeader {
     _magic_bytes: [u8; 8],      // EXTRHEAD
     _unk_string: [u8; 4],       // "DRV " ?
     pub _compressed_flag: u16,  // checks for 1 here, else -> "Error! Not compress"
-    pub compression_type: u16,  // see CompressionType. GzipAndLzss is not used in this context.
+    pub compression_type: u16,  // see CompressionType
     pub dest_size: u32,         // decompressed size
     pub _dest_address: u32,
     pub src_size: u32,          // compressed size
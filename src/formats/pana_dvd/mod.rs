@@ -4,6 +4,7 @@ mod lzss;
 mod util;
 use std::any::Any;
 use crate::AppContext;
+use crate::keyhunt::HuntTarget;
 
 use std::path::{Path, PathBuf};
 use std::fs::{self, OpenOptions};
@@ -20,6 +21,7 @@ use include::*;
 use util::split_main_file;
 
 pub struct PanaDvdContext {
+    key_name: String,
     matching_key: [u8; 8],
     base_hdr_size: u32,
     is_aes: bool,
@@ -30,24 +32,32 @@ pub struct PanaDvdContext {
 pub fn is_pana_dvd_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
     let header = common::read_file(&file, 0, 64)?;
-    if let Some(matching_key) = find_key(app_ctx.keys.get_collection("PANA_DVD_KEYONLY")?, &header, b"PROG", 0)? {
+    //nothing is readable before decryption, so key hints are kept for the whole format
+    let key_only = app_ctx.get_hinted_collection("pana_dvd", "", "PANA_DVD_KEYONLY")?;
+    if let Some((key_name, matching_key)) = find_key(&key_only, &header, b"PROG", 0)? {
+        app_ctx.remember_key("pana_dvd", "", "PANA_DVD_KEYONLY", &key_name);
         Ok(Some(Box::new(PanaDvdContext {
+            key_name: format!("PANA_DVD_KEYONLY \"{}\"", key_name),
             matching_key: matching_key,
             base_hdr_size: 0,
             is_aes: false,
             aes_key: None, 
             aes_iv: None,
         })))
-    } else if header.starts_with(b"PANASONIC\x00\x00\x00") && let Some(matching_key) = find_key(app_ctx.keys.get_collection("PANA_DVD_KEYONLY")?, &header, b"PROG", 48)? {
+    } else if header.starts_with(b"PANASONIC\x00\x00\x00") && let Some((key_name, matching_key)) = find_key(&key_only, &header, b"PROG", 48)? {
+        app_ctx.remember_key("pana_dvd", "", "PANA_DVD_KEYONLY", &key_name);
         Ok(Some(Box::new(PanaDvdContext {
+            key_name: format!("PANA_DVD_KEYONLY \"{}\"", key_name),
             matching_key: matching_key,
             base_hdr_size: 48,
             is_aes: false,
             aes_key: None, 
             aes_iv: None,
         })))
-    } else if let Some((aes_key, aes_iv, matching_key)) = find_aes_key_pair(app_ctx.keys.get_collection("PANA_DVD_AESPAIR")?, &header, b"PANASONIC", 32)? {
+    } else if let Some((key_name, aes_key, aes_iv, matching_key)) = find_aes_key_pair(&app_ctx.get_hinted_collection("pana_dvd", "", "PANA_DVD_AESPAIR")?, &header, b"PANASONIC", 32)? {
+        app_ctx.remember_key("pana_dvd", "", "PANA_DVD_AESPAIR", &key_name);
         Ok(Some(Box::new(PanaDvdContext {
+            key_name: format!("PANA_DVD_AESPAIR \"{}\"", key_name),
             matching_key: matching_key,
             base_hdr_size: 48,
             is_aes: true,
@@ -59,6 +69,21 @@ pub fn is_pana_dvd_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Bo
     }
 }
 
+pub fn find_pana_dvd_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(is_pana_dvd_file(app_ctx)?.map(|ctx| ctx.downcast::<PanaDvdContext>().expect("Missing context").key_name))
+}
+
+//same checks as the detector for key only files
+pub fn hunt_pana_dvd_key(app_ctx: &AppContext) -> Result<Option<HuntTarget>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let header = common::read_file(file, 0, 64)?;
+    let magic_offset = if header.starts_with(b"PANASONIC\x00\x00\x00") {48} else {0};
+    Ok(Some(HuntTarget::new("PANA_DVD_KEYONLY", vec![vec![8]], move |key| {
+        decrypt_data(&header, key.try_into().unwrap())[magic_offset..].starts_with(b"PROG")
+    })))
+}
+
 pub fn extract_pana_dvd(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let context = ctx.downcast::<PanaDvdContext>().expect("Missing context");
@@ -160,7 +185,7 @@ fn extract_file(app_ctx: &AppContext, file_reader: &mut Cursor<Vec<u8>>, header_
         //special treatment of MAIN
         if module.name() == "MAIN" {
             println!("- Extracting MAIN...");
-            extract_main(file_reader, key, &output_path)?;
+            extract_main(file_reader, key, module.size, &output_path)?;
             if app_ctx.has_option("pana_dvd:split_main") {
                 println!("\n- Splitting MAIN...");
                 split_main_file(&output_path, output_folder)?;
@@ -189,20 +214,79 @@ fn extract_file(app_ctx: &AppContext, file_reader: &mut Cursor<Vec<u8>>, header_
     Ok(())
 }
 
-fn extract_main(file_reader: &mut Cursor<Vec<u8>>, key: [u8; 8], output_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    let main_list_hdr: MainListHeader = file_reader.read_le()?;
-    if main_list_hdr.entry_count() > 200 {
-        println!("Unsupported MAIN data, skipping!");
-        return Ok(())
+//list with the size and checksum of each compressed part of MAIN
+struct MainList {
+    decompressed_part_size: u32,
+    entries: Vec<MainListEntry>,
+}
+
+//newer list header, the one without the always 1 field of very old files, or no list at all (None)
+fn read_main_list(file_reader: &mut Cursor<Vec<u8>>, module_size: u32) -> Result<Option<MainList>, Box<dyn std::error::Error>> {
+    let start = file_reader.position();
+    let header: MainListHeader = file_reader.read_le()?;
+    file_reader.seek(SeekFrom::Start(start))?;
+    let legacy_header: LegacyMainListHeader = file_reader.read_le()?;
+
+    let layouts = [
+        (20, header.list_size, header.decompressed_part_size, "new"),
+        (16, legacy_header.list_size, legacy_header.decompressed_part_size, "old"),
+    ];
+    for (header_size, list_size, decompressed_part_size, layout) in layouts {
+        let Some(entry_count) = main_entry_count(list_size, header_size, module_size) else {
+            continue
+        };
+        file_reader.seek(SeekFrom::Start(start + header_size as u64))?;
+        let mut entries: Vec<MainListEntry> = Vec::new();
+        for _ in 0..entry_count {
+            entries.push(file_reader.read_le()?);
+        }
+        //all parts have to fit in the module after the list
+        let parts_size: u64 = entries.iter().map(|e| e.size as u64).sum();
+        if entries.iter().all(|e| e.size != 0) && list_size as u64 + parts_size <= module_size as u64 {
+            println!("MAIN - List layout: {}", layout);
+            return Ok(Some(MainList {decompressed_part_size, entries}))
+        }
     }
 
-    println!("MAIN - Entry count: {}, Decompressed part size: {}", main_list_hdr.entry_count(), main_list_hdr.decompressed_part_size);
-    let mut main_entries: Vec<MainListEntry> = Vec::new();
-    for i in 0..main_list_hdr.entry_count() {
-        let main_entry: MainListEntry = file_reader.read_le()?;
+    file_reader.seek(SeekFrom::Start(start))?;
+    Ok(None)
+}
+
+//checksums are mostly Adler32, but some very old files use Checksum32 instead
+fn check_main_entry(expected: u32, data: &[u8]) {
+    if common::adler32(data) == expected {
+        println!("- Checksum OK (Adler32)");
+    } else if common::byte_sum(data) == expected {
+        println!("- Checksum OK (Checksum32)");
+    } else {
+        println!("- Warning: checksum {:#010x} does not match the decompressed data!", expected);
+    }
+}
+
+fn extract_main(file_reader: &mut Cursor<Vec<u8>>, key: [u8; 8], module_size: u32, output_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(main_list) = read_main_list(file_reader, module_size)? else {
+        //very old files can have MAIN as a single compressed file
+        let data = common::read_exact(file_reader, (module_size as usize + 7) & !7)?;
+        let mut dec_data = decrypt_data(&data, &key);
+        dec_data.truncate(module_size as usize);
+        let out_data = if dec_data.starts_with(COMPRESSED_FILE_MAGIC) {
+            print!("\nMAIN (1/1) - ");
+            decompress_data(&dec_data)?
+        } else {
+            println!("Unknown MAIN layout, saving decrypted data!");
+            dec_data
+        };
+        let mut main_out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_path)?;
+        main_out_file.write_all(&out_data)?;
+        println!("-- Saved to MAIN!");
+        return Ok(())
+    };
+    let main_entries = main_list.entries;
+
+    println!("MAIN - Entry count: {}, Decompressed part size: {}", main_entries.len(), main_list.decompressed_part_size);
+    for (i, main_entry) in main_entries.iter().enumerate() {
         println!("- Entry {}/{} - Size: {}, Checksum: {:#010x}",
-                i + 1, main_list_hdr.entry_count(), main_entry.size, main_entry.checksum);
-        main_entries.push(main_entry);
+                i + 1, main_entries.len(), main_entry.size, main_entry.checksum);
     }
 
     let mut maine_i = 0;
@@ -210,7 +294,7 @@ fn extract_main(file_reader: &mut Cursor<Vec<u8>>, key: [u8; 8], output_path: &P
     for entry in &main_entries {
         maine_i += 1;
         let mut data = common::read_exact(file_reader, entry.size as usize)?;
-        let decrypt_size: usize = if main_list_hdr.decompressed_part_size == 0x2000000 {10240} /* old type */ else {5120};
+        let decrypt_size: usize = if main_list.decompressed_part_size == 0x2000000 {10240} /* old type */ else {5120};
         if entry.size > decrypt_size as u32 {
             //decrypt first and last 5kb
             let first_decrypted = decrypt_data(&data[..decrypt_size], &key);
@@ -226,6 +310,7 @@ fn extract_main(file_reader: &mut Cursor<Vec<u8>>, key: [u8; 8], output_path: &P
         
         print!("\nMAIN ({}/{}) - ", maine_i, main_entries.len());
         let decompressed_data = decompress_data(&data)?;
+        check_main_entry(entry.checksum, &decompressed_data);
            
         main_out_file.write_all(&decompressed_data)?;
         
@@ -258,10 +343,16 @@ fn decompress_data(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
             return Err("Decompressed size does not match size in header, decompression failed!".into());
         }
 
+    } else if compression_type == CompressionType::GzipAndLzss {
+        println!("- Decompressing GZIP and LZSS...");
+        decompressed_data = decompress_lzss(&decompress_gzip(&compressed_data)?);
+        if decompressed_data.len() != header.dest_size as usize {
+            return Err("Decompressed size does not match size in header, decompression failed!".into());
+        }
+
     } else if compression_type == CompressionType::None {
         decompressed_data = compressed_data;
-            
-    //GzipAndLzss is not used in this context.
+
     } else {
         println!("- Unknown compression method!");
         decompressed_data = compressed_data;
@@ -1,79 +1,30 @@
 use crate::utils::common;
-use std::{fs::{self, File, OpenOptions}, io::{Read, Write}, path::{Path, PathBuf}};
+use crate::utils::pana_parts::{parse_bootargs, split_parts};
+use std::{fs::{File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}};
 
 pub fn split_main_file(path: &PathBuf, out_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = File::open(path)?;
-    let mut file_size = file.metadata()?.len();
+    let file_size = file.metadata()?.len();
     let output_folder = Path::new(&out_path).join("MAIN");
 
-    let mut args_bytes = common::read_file(&mut file, 0, 0x1000)?;
-    let mut has_swup_addon = false;
+    //partitions are stored from the bootargs on, up to the optional swup addon
+    let mut args_bytes = common::read_file(&file, 0, 0x1000)?;
+    let mut addon_offset = None;
     if args_bytes.starts_with(b"SWUP_ADDON") {
-        has_swup_addon = true;
-        let addon_offset = u32::from_le_bytes(args_bytes[12..16].try_into()?);
-        file_size = addon_offset as u64;
-        args_bytes = common::read_file(&mut file, 16, 0x1000)?;
+        addon_offset = Some(u32::from_le_bytes(args_bytes[12..16].try_into()?) as u64);
+        args_bytes = common::read_file(&file, 16, 0x1000)?;
     }
-    let args = common::string_from_bytes(&args_bytes);    
+    let table = parse_bootargs(&common::string_from_bytes(&args_bytes))?;
 
-    let mut root: Option<String> = None;
-    let mut parts = Vec::<(String, u64)>::new();
-
-    //parse parts
-    for line in args.lines() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-
-        //get the root partition, strip /dev/
-        if let Some(rest) = line.strip_prefix("root=") {
-            let dev = rest.trim();
-            if let Some(name) = dev.strip_prefix("/dev/") {
-                root = Some(name.to_string());
-            }
-        }
-
-        //get fmaX=XXXXXk partitions
-        if let Some((name, size_part)) = line.split_once('=') {
-            if name.starts_with("fma") {
-                let size_str = size_part.split(':').next().unwrap().trim();
-
-                if let Some(num) = size_str.strip_suffix('k') {
-                    let kb: u64 = num.parse().map_err(|_| format!("Bad size: {}", size_str))?;
-                    parts.push((name.to_string(), kb * 1024));
-                }
-            }
-        }
-    }
-
-    let root = root.ok_or("Failed to get root partition!")?;
-    println!("Root - {}", root);
-    let root_index = parts.iter().position(|(n, _s)| n == &root).ok_or("Root partition not found in partition list!")?;
-
-    let mut tsize: u64 = 0;
-
-    //read parts from file
-    let start_index = root_index - 1; // root is always the second partition in MAIN, so we start from the previous one.
-    for (part_name, part_size) in parts.iter().skip(start_index) {
-        if tsize >= file_size {
-            break
-        }
-
-        println!("- {} - Size: {}", part_name, part_size);
-        tsize += part_size;
-
-        let data = common::read_exact(&mut file, *part_size as usize)?;
- 
-        let output_path = Path::new(&output_folder).join(format!("{}.bin", part_name));
-        fs::create_dir_all(&output_folder)?;
-        
-        let mut out_file = OpenOptions::new().write(true).create(true).open(output_path)?;
-        out_file.write_all(&data)?;
-    }
+    let (data_offset, data_end) = match addon_offset {
+        Some(addon_offset) => (16, addon_offset),
+        None => (0, file_size),
+    };
+    split_parts(&mut file, data_offset, data_end.saturating_sub(data_offset), &table, &output_folder, true)?;
 
     //read optional swup addon
-    if has_swup_addon {
+    if let Some(addon_offset) = addon_offset {
+        file.seek(SeekFrom::Start(addon_offset))?;
         let mut data = Vec::new();
         file.read_to_end(&mut data)?;
         println!("- SWUP_ADDON - Size: {}", data.len());
@@ -84,4 +35,4 @@ pub fn split_main_file(path: &PathBuf, out_path: &PathBuf) -> Result<(), Box<dyn
     }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -9,6 +9,7 @@ use binrw::BinReaderExt;
 use rsa::{RsaPublicKey, BigUint};
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, prepare_keys};
 use crate::utils::aes::decrypt_aes256_ecb;
 use include::*;
 
@@ -50,30 +51,25 @@ pub fn extract_pfl_upg(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), B
         let ciphertext = common::read_file(&mut file, header.header_size as u64, 64)?;
 
         //try find key
-        let mut key: Option<(String, [u8; 32])> = None;
-        for (name, keys) in app_ctx.keys.get_collection("PFL_UPG")? {     
-            let n = BigUint::from_bytes_be(keys.first().unwrap());
+        let pubkeys = prepare_keys(&app_ctx.get_collection("PFL_UPG")?, |name, keys| {
+            let n = BigUint::from_bytes_be(keys.first().ok_or(format!("PFL_UPG \"{}\" is empty!", name))?);
             let e = BigUint::from_bytes_be(b"\x01\x00\x01");
-            let pubkey = RsaPublicKey::new(n, e)?;
-
+            Ok(RsaPublicKey::new(n, e)?)
+        })?;
+        let found = find_first(&pubkeys, |(_, pubkey)| {
             let sig_int = BigUint::from_bytes_le(&signature);
-            let dec_int = rsa::hazmat::rsa_encrypt(&pubkey, &sig_int)?;
+            let dec_int = rsa::hazmat::rsa_encrypt(pubkey, &sig_int).ok()?;
             let dec_sig = dec_int.to_bytes_le();
 
-            let aes_key = &dec_sig[20..52];
-            let dec_ciphertext = decrypt_aes256_ecb(&ciphertext, aes_key.try_into().unwrap())?;
+            let aes_key: [u8; 32] = dec_sig.get(20..52)?.try_into().unwrap();
+            let dec_ciphertext = decrypt_aes256_ecb(&ciphertext, &aes_key).ok()?;
         
             //needs to start with null-termninated filename string
-            let end = match dec_ciphertext.iter().position(|&b| b == 0) {
-                Some(pos) => pos,
-                None => continue,       //there is no 0, continue
-            };
+            let end = dec_ciphertext.iter().position(|&b| b == 0)?;
             let fname = &dec_ciphertext[..end];
-            if fname.len() > 1 && fname.is_ascii() {       //is ascii filename
-                key = Some((name.to_string(), aes_key.try_into().unwrap()));
-                break
-            }
-        }
+            (fname.len() > 1 && fname.is_ascii()).then_some(aes_key)       //is ascii filename
+        });
+        let key = found.map(|(i, aes_key)| (pubkeys[i].0.clone(), aes_key));
 
         let aes_key;
         if let Some((key_name, key)) = key {
@@ -142,6 +138,7 @@ pub fn extract_pfl_upg(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), B
                 output_dir: output_path, 
                 options: app_ctx.options,
                 keys: app_ctx.keys,
+                hints: app_ctx.hints,
             };
 
             //do check just in case and extract
@@ -19,11 +19,14 @@ pub fn is_philips_bdp_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>,
 
     let header = common::read_file(&file, 0, 16)?;
     if header.starts_with(b"PHILIPS") {
-        if header[15].is_ascii_alphanumeric() {
-            Ok(Some(Box::new(PhilipsBdpCtx {header_type: HeaderType::New})))
-        } else {
-            Ok(Some(Box::new(PhilipsBdpCtx {header_type: HeaderType::Old})))
-        }
+        //guessed from the byte after the name, unless given with the variant option
+        let header_type = match app_ctx.option_choice("philips_bdp:variant", &["old", "new"])? {
+            Some("new") => HeaderType::New,
+            Some(_) => HeaderType::Old,
+            None if header[15].is_ascii_alphanumeric() => HeaderType::New,
+            None => HeaderType::Old,
+        };
+        Ok(Some(Box::new(PhilipsBdpCtx {header_type})))
     } else {
         Ok(None)
     }
@@ -81,6 +84,7 @@ pub fn extract_philips_bdp(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<()
                 output_dir: app_ctx.output_dir.join("0"),
                 options: app_ctx.options,
                 keys: app_ctx.keys,
+                hints: app_ctx.hints,
             };
 
             if let Some(result) = formats::mtk_bdp::is_mtk_bdp_file(&mtk_ctx)? {
@@ -10,7 +10,7 @@ pub struct AImageHeader {
     _magic: u32,                        // 0x41676d69
     _magic2: u32,                       // 0x43634d52
     _release_id: u32,                   // toplevel build / release version
-    _platform_id: u32,                  // target platform information
+    pub platform_id: u32,               // target platform information
     pub image_type: u32,                // image type
     pub lenght: u32,                    // length of entire image (header + data + trailing padding) 
     _data_lenght: u32,                  // length of image data (ie from data_start_offset to start of trailing padding)
@@ -32,7 +32,7 @@ pub struct AImageHeader {
               secret in any way, but it _should_ be unique for every
               image which is encrypted with a given AES key.
     */
-    _iv: [u8; 16],                      // space reserved for random data or timestamp to ensure IV is unique for every image which is signed
+    pub iv: [u8; 16],                   // space reserved for random data or timestamp to ensure IV is unique for every image which is signed
 }
 impl AImageHeader {
     //pub fn release_id_str(&self) -> String {
@@ -77,16 +77,37 @@ impl AImageHeader {
         }
     }
 
+    //3 bit field, not single flags
+    pub fn encmode(&self) -> EncMode {
+        match (self.flags >> 6) & 7 {
+            0 => EncMode::None,
+            1 => EncMode::CbcOnePass,   // image data is encrypted before signing: CBC mode, entire image in one pass
+            2 => EncMode::Cbc4kBlocks,  // image data is encrypted before signing: CBC mode, 4k byte blocks, block group iv = (iv + (first block's block offset))
+            3 => EncMode::Ctr,          // image data is encrypted before signing: CTR mode, block iv = (iv + (block offset))
+            n => EncMode::Unknown(n),
+        }
+    }
+
     pub fn encmode_str(&self) -> &str {
-        let encmode_flags = self.flags & (7 << 6);
-        if (encmode_flags & (1 << 6)) != 0 {    // image data is encrypted before signing: CBC mode, entire image in one pass
-            return "cbc_onepass"
-        } else if (encmode_flags & (2 << 6)) != 0 {    // image data is encrypted before signing: CBC mode, 4k byte blocks, block group iv = (iv + (first block's block offset))
-            return "cbc_4kblocks"
-        } else if (encmode_flags & (3 << 6)) != 0 {    // image data is encrypted before signing: CTR mode, block iv = (iv + (block offset))
-            return "ctr"
-        } else {
-            return "none/unknown"
+        match self.encmode() {
+            EncMode::None => "none",
+            EncMode::CbcOnePass => "cbc_onepass",
+            EncMode::Cbc4kBlocks => "cbc_4kblocks",
+            EncMode::Ctr => "ctr",
+            EncMode::Unknown(_) => "unknown",
         }
     }
+}
+
+pub enum EncMode {
+    None,
+    CbcOnePass,
+    Cbc4kBlocks,
+    Ctr,
+    Unknown(u32),
+}
+
+//the iv plus a number of AES blocks, as a 128 bit big endian number
+pub fn offset_iv(iv: &[u8; 16], blocks: usize) -> [u8; 16] {
+    u128::from_be_bytes(*iv).wrapping_add(blocks as u128).to_be_bytes()
 }
\ No newline at end of file
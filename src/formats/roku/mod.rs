@@ -9,9 +9,14 @@ use tar::Archive;
 use binrw::BinReaderExt;
 
 use crate::utils::common;
-use crate::utils::aes::{decrypt_aes128_cbc_nopad, decrypt_aes128_cbc_pcks7};
+use crate::utils::signature::verify_pkcs1;
+use crate::utils::aes::{decrypt_aes128_cbc_nopad, decrypt_aes128_cbc_pcks7, decrypt_aes128_ctr};
+use crate::ukf::{MissingKey, part_as_arr};
 use include::*;
 
+//key entry name and the AES key
+type ImageKey = (String, [u8; 16]);
+
 pub fn is_roku_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
 
@@ -26,6 +31,59 @@ pub fn is_roku_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dy
     }
 }
 
+//the manifest ends with a 2048 bit RSA signature of the rest, with SHA-1 or SHA-256
+fn check_manifest_signature(app_ctx: &AppContext, content: &[u8], signature: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let modulus = match app_ctx.keys.get_key("ROKU_MANIFEST_PUBKEY") {
+        Ok(parts) => parts.first().ok_or("ROKU_MANIFEST_PUBKEY is empty!")?.clone(),
+        Err(e) if e.is::<MissingKey>() => {
+            println!("Manifest signature not checked, ROKU_MANIFEST_PUBKEY is not loaded");
+            return Ok(())
+        }
+        Err(e) => return Err(e),
+    };
+    if verify_pkcs1(&modulus, signature, content)? {
+        println!("Manifest signature OK");
+    } else {
+        println!("Warning: manifest signature does not match ROKU_MANIFEST_PUBKEY!");
+    }
+    Ok(())
+}
+
+//entries are named after the platform id, the key option can point to another one. None if there is no key for it
+fn find_image_key(app_ctx: &AppContext, image: &AImageHeader) -> Result<Option<ImageKey>, Box<dyn std::error::Error>> {
+    let name = app_ctx.forced_key().map_or(format!("{:08x}", image.platform_id), |forced| forced.to_string());
+    let collection = match app_ctx.keys.get_collection("ROKU_IMAGE") {
+        Ok(collection) => collection,
+        Err(e) if e.is::<MissingKey>() => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let Some((_, parts)) = collection.iter().find(|(entry, _)| *entry == name) else {return Ok(None)};
+    let key = part_as_arr::<16>(&format!("ROKU_IMAGE \"{}\"", name), parts, 0)?;
+    Ok(Some((name, key)))
+}
+
+//only whole AES blocks are encrypted, the rest is plain
+fn decrypt_image_data(encmode: &EncMode, data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let align_size = data.len() & !0xF;
+    let mut out_data = data.to_vec();
+    match encmode {
+        EncMode::CbcOnePass => {
+            out_data[..align_size].copy_from_slice(&decrypt_aes128_cbc_nopad(&data[..align_size], key, iv)?);
+        }
+        EncMode::Cbc4kBlocks => {
+            for (i, chunk) in out_data[..align_size].chunks_mut(4096).enumerate() {
+                let decrypted = decrypt_aes128_cbc_nopad(chunk, key, &offset_iv(iv, i * 4096 / 16))?;
+                chunk.copy_from_slice(&decrypted);
+            }
+        }
+        EncMode::Ctr => {
+            out_data = decrypt_aes128_ctr(data, key, iv);
+        }
+        EncMode::None | EncMode::Unknown(_) => {}
+    }
+    Ok(out_data)
+}
+
 pub fn extract_roku(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
 
@@ -48,8 +106,10 @@ pub fn extract_roku(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
             let mut contents = Vec::new();
             entry.read_to_end(&mut contents)?;
 
-            let text = String::from_utf8_lossy(&contents[..size - 256]); //dont display signature
+            let (manifest, signature) = contents[..size].split_at(size - 256);
+            let text = String::from_utf8_lossy(manifest); //dont display signature
             println!("Manifest file:\n{}", text);
+            check_manifest_signature(app_ctx, manifest, signature)?;
         } else {
             let mut contents = Vec::new();
             entry.read_to_end(&mut contents)?; //entry cant seek
@@ -62,11 +122,11 @@ pub fn extract_roku(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
 
                 while image_reader.stream_position()? < size as u64 {
                     let image: AImageHeader = image_reader.read_le()?;
-                    println!("  #{} - Type: {}(0x{:x}), Lenght: {}, encmode: {}", 
-                           i, image.image_type_str(), image.image_type, image.lenght, image.encmode_str());
+                    println!("  #{} - Type: {}(0x{:x}), Lenght: {}, Platform: {:08x}, encmode: {}", 
+                           i, image.image_type_str(), image.image_type, image.lenght, image.platform_id, image.encmode_str());
                     //println!("{:?}", image);
                     
-                    let data = 
+                    let mut data = 
                     if image.data_start_offset == 0 { // "0 if header is part of data"
                         image_reader.seek(SeekFrom::Current(-256))?;    //rewind header
                         common::read_exact(&mut image_reader, image.lenght as usize)?
@@ -76,6 +136,21 @@ pub fn extract_roku(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<
                         common::read_exact(&mut image_reader, image.lenght as usize - image.data_start_offset as usize)?
                     };
 
+                    match image.encmode() {
+                        EncMode::None => {}
+                        EncMode::Unknown(n) => println!("  - Warning: unknown encmode {}, saving ENCRYPTED data!", n),
+                        encmode => match find_image_key(app_ctx, &image)? {
+                            Some((key_name, key)) => {
+                                println!("  - Decrypting with key: {}...", key_name);
+                                //the header stays plain when it is part of the data
+                                let data_start = if image.data_start_offset == 0 {256} else {0};
+                                let decrypted = decrypt_image_data(&encmode, &data[data_start..], &key, &image.iv)?;
+                                data[data_start..].copy_from_slice(&decrypted);
+                            }
+                            None => println!("  - Warning: no ROKU_IMAGE key for platform {:08x}, saving ENCRYPTED data!", image.platform_id),
+                        },
+                    }
+
                     let folder_path = Path::new(&app_ctx.output_dir).join(&path);
                     let output_path = Path::new(&folder_path).join(format!("{}_{}.bin", i, image.image_type_str()));
 
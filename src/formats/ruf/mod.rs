@@ -1,6 +1,7 @@
 mod include;
 use std::any::Any;
 use crate::AppContext;
+use crate::ukf::NamedKey;
 
 use std::path::Path;
 use std::fs::{self, OpenOptions};
@@ -22,6 +23,23 @@ pub fn is_ruf_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn
     }
 }
 
+//keys are picked by model name prefix, unless one is given with the key option
+fn find_key(app_ctx: &AppContext, model: &str) -> Result<Option<NamedKey>, Box<dyn std::error::Error>> {
+    for (name, keys) in app_ctx.keys.get_collection("RUF")?.iter() {
+        if app_ctx.forced_key().map_or(model.starts_with(name), |forced| forced == name) {
+            return Ok(Some((name.clone(), keys.first().ok_or("Empty RUF key!")?.clone())))
+        }
+    }
+    Ok(None)
+}
+
+pub fn find_ruf_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut file = app_ctx.file().ok_or("Expected file")?;
+    file.seek(SeekFrom::Start(0))?;
+    let header: RufHeader = file.read_be()?;
+    Ok(find_key(app_ctx, &header.model())?.map(|(name, _)| format!("RUF \"{}\"", name)))
+}
+
 pub fn extract_ruf(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let header: RufHeader = file.read_be()?;
@@ -53,18 +71,11 @@ pub fn extract_ruf(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<d
         entries.push(entry);
     }
 
-    let mut key: Option<&Vec<u8>> = None;
     let key_bytes;
     let iv_bytes: [u8; 16] = [0x00; 16];
 
     //find key
-    for (name, keys) in app_ctx.keys.get_collection("RUF")? {
-        if header.model().starts_with(name) {
-            key = Some(keys.first().unwrap());
-            break;
-        }
-    }
-    if let Some(k) = key {
+    if let Some((_, k)) = find_key(app_ctx, &header.model())? {
         key_bytes = k.as_slice().try_into()?;
     } else {
         return Err("no key found for this firmware".into());
@@ -21,6 +21,25 @@ pub enum HeaderType {
     MVP,
 }
 
+//the data after the header is XORed, or AES-128-CBC encrypted with an RVP_AES key
+pub enum Variant {
+    Xor,
+    Aes(String),    //name of the RVP_AES entry
+}
+
+pub struct ModuleHeader {
+    pub name: String,
+    pub size: u32,
+    pub start_address: Option<u32>,
+    pub total_sum: Option<u32>,
+    pub crc: Option<u32>,
+}
+
+//checksums and addresses are hex strings like "34D0757C", sometimes with 0x
+pub fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok()
+}
+
 #[derive(BinRead)]
 pub struct RVPHeader {
     _crc: u32,
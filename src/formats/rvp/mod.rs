@@ -8,8 +8,15 @@ use std::io::{Write, Read, Cursor, Seek};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::aes::decrypt_aes128_cbc_nopad;
+use crate::utils::keytrial::{find_first, optional_aes_pairs};
 use include::*;
 
+//variant and the decrypted data
+type Decrypted = (Variant, Vec<u8>);
+//entry name, key and iv
+type NamedAesKey = (String, [u8; 16], [u8; 16]);
+
 pub struct RvpContext {
     header_type: HeaderType,
 }
@@ -33,6 +40,117 @@ pub fn is_rvp_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn
     Ok(Some(Box::new(RvpContext {header_type: HeaderType::RVP})))
 }
 
+//module count and the size of the first module header have to make sense after decryption
+fn is_plausible(data: &[u8]) -> bool {
+    if data.len() < 260 {
+        return false
+    }
+    let module_count = u32::from_le_bytes(data[0..4].try_into().unwrap());
+    let header_size = u32::from_be_bytes(data[256..260].try_into().unwrap());
+    (1..=63).contains(&module_count) && (1..=1024).contains(&header_size)
+}
+
+//only the block aligned part is encrypted
+fn decrypt_aes(data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let aligned = data.len() - data.len() % 16;
+    let mut out = decrypt_aes128_cbc_nopad(&data[..aligned], key, iv)?;
+    out.extend_from_slice(&data[aligned..]);
+    Ok(out)
+}
+
+//None if there is no RVP_AES collection or none of its keys gives a valid module table
+fn find_aes_key(app_ctx: &AppContext, obf_data: &[u8]) -> Result<Option<NamedAesKey>, Box<dyn std::error::Error>> {
+    let Some(keys) = optional_aes_pairs(app_ctx, "RVP_AES")? else {
+        return Ok(None)
+    };
+    let first_blocks = &obf_data[..obf_data.len().min(272) / 16 * 16];
+    let Some((i, _)) = find_first(&keys, |(_, (key, iv))| {
+        decrypt_aes128_cbc_nopad(first_blocks, key, iv).ok().filter(|d| is_plausible(d))
+    }) else {
+        return Ok(None)
+    };
+    let (name, (key, iv)) = &keys[i];
+    Ok(Some((name.clone(), *key, *iv)))
+}
+
+//AES keys are checked first, anything else is treated as a XOR file
+fn decrypt_data(app_ctx: &AppContext, obf_data: &[u8]) -> Result<Decrypted, Box<dyn std::error::Error>> {
+    if let Some((name, key, iv)) = find_aes_key(app_ctx, obf_data)? {
+        return Ok((Variant::Aes(name), decrypt_aes(obf_data, &key, &iv)?))
+    }
+
+    let xor_key = app_ctx.keys.get_key_as_arr::<32>("RVP_XOR_KEY", 0)?;
+    Ok((Variant::Xor, decrypt_xor(obf_data, &xor_key)))
+}
+
+fn parse_module_header(i: usize, hdr: &[u8]) -> Result<Option<ModuleHeader>, Box<dyn std::error::Error>> {
+    let hdr_string = String::from_utf8_lossy(hdr);
+    let lines: Vec<String> = hdr_string.lines().map(|l| l.trim().to_string()).collect();
+
+    let header = if i == 0 { //first entry is always HOST module (SEINE)
+        if lines.len() < 13 {
+            return Err("Host module header is too short!".into())
+        }
+        //BEAUTIFUL
+        println!("ModelName: {}\nFileName: {}\nModelID: {}\nNewUpdate: {}\nNewMajorVer: {}\nNewMinorVer: {}\nForcedFlag: {}\nStartAddress: {}\nJumpAddress: {}\nMagicAddress: {}\nTotalSize: {}\nTotalSum: {}\nTotalCrc: {}",
+                lines[0], lines[1], lines[2], lines[3], lines[4], lines[5], lines[6], lines[7], lines[8], lines[9], lines[10], lines[11], lines[12]);
+
+        ModuleHeader {
+            name: String::new(),
+            size: lines[10].parse()?,
+            start_address: parse_hex(&lines[7]),
+            total_sum: parse_hex(&lines[11]),
+            crc: parse_hex(&lines[12]),
+        }
+
+    } else if hdr.len() == 32 {
+        //1. CRC32 checksum like "34D0757C"
+        //2. unknown - "FFFFFFFF"
+        //3. size in hex string like "00040000"
+        ModuleHeader {name: String::new(), size: parse_hex(&lines[2]).ok_or("Invalid module size!")?, start_address: None, total_sum: None, crc: parse_hex(&lines[0])}
+
+    } else if hdr.len() == 48 || hdr.len() == 44 || hdr.len() == 40 {     //for disk drive firmware
+        //1. - name, like "L12_110.IMG"
+        //2. - size in hex string like "001E6388"
+        //3. - unknown - single number like "3" (force flag?)
+        //4. - crc32 checksum like "0BC0F6F7"
+        //5. - unknown - like "00011200" -- this line is not present when size is 40 but were not using it anyway so whatever
+        println!("Name: {}", lines[0]);
+        ModuleHeader {name: lines[0].clone(), size: parse_hex(&lines[1]).ok_or("Invalid module size!")?, start_address: None, total_sum: None, crc: parse_hex(&lines[3])}
+
+    } else if hdr.len() == 16 {
+        // 4 bytes CRC32
+        // 4 bytes unknown "FF FF FF FF"
+        // 4 bytes size
+        // 4 bytes unknown "00 00 00 00"
+        ModuleHeader {
+            name: String::new(),
+            size: u32::from_be_bytes(hdr[8..12].try_into().unwrap()),
+            start_address: None,
+            total_sum: None,
+            crc: Some(u32::from_be_bytes(hdr[0..4].try_into().unwrap())),
+        }
+
+    } else {
+        return Ok(None)
+    };
+    Ok(Some(header))
+}
+
+fn check_module(header: &ModuleHeader, data: &[u8]) {
+    if let Some(start_address) = header.start_address {
+        println!("Start address: 0x{:08x}", start_address);
+    }
+    if let Some(crc) = header.crc {
+        let actual = common::crc32(data);
+        if actual == crc {println!("CRC32 OK")} else {println!("Warning: CRC32 mismatch! Expected {:08X}, got {:08X}", crc, actual)}
+    }
+    if let Some(total_sum) = header.total_sum {
+        let actual = common::byte_sum(data);
+        if actual == total_sum {println!("Sum OK")} else {println!("Warning: sum mismatch! Expected {:08X}, got {:08X}", total_sum, actual)}
+    }
+}
+
 pub fn extract_rvp(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<RvpContext>().expect("Missing context");
@@ -47,10 +165,13 @@ pub fn extract_rvp(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dy
 
     let mut obf_data = Vec::new();
     file.read_to_end(&mut obf_data)?;
-    println!("DeXORing data..");
+    println!("Decrypting data..");
 
-    let xor_key= app_ctx.keys.get_key_as_arr::<32>("RVP_XOR_KEY", 0)?;
-    let data = decrypt_xor(&obf_data, &xor_key); 
+    let (variant, data) = decrypt_data(app_ctx, &obf_data)?;
+    match &variant {
+        Variant::Xor => println!("Encryption: XOR"),
+        Variant::Aes(name) => println!("Encryption: AES, key: {}", name),
+    }
     let data_size = data.len();
     let mut data_reader = Cursor::new(data);
 
@@ -87,52 +208,15 @@ pub fn extract_rvp(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dy
         println!("\n({}/{}) - {}, Offset: {}, Header size: {}", i+1, module_count, module_name, data_reader.position() - 4, header_size);
         let hdr = common::read_exact(&mut data_reader, header_size as usize)?;
 
-        let size;
-        let mut name = String::new();
-        if i == 0 { //first entry is always HOST module (SEINE)
-            let hdr_string = String::from_utf8_lossy(&hdr);
-            let lines: Vec<String> = hdr_string.lines().map(|l| l.trim().to_string()).collect();
-
-            //BEAUTIFUL
-            println!("ModelName: {}\nFileName: {}\nModelID: {}\nNewUpdate: {}\nNewMajorVer: {}\nNewMinorVer: {}\nForcedFlag: {}\nStartAddress: {}\nJumpAddress: {}\nMagicAddress: {}\nTotalSize: {}\nTotalSum: {}\nTotalCrc: {}",
-                    lines[0], lines[1], lines[2], lines[3], lines[4], lines[5], lines[6], lines[7], lines[8], lines[9], lines[10], lines[11], lines[12]);
-
-            size = lines[10].parse().unwrap();
-
-        } else if header_size == 32 {
-            let hdr_string = String::from_utf8_lossy(&hdr);
-            let lines: Vec<String> = hdr_string.lines().map(|l| l.trim().to_string()).collect();
-            //1. CRC32 checksum like "34D0757C"
-            //2. unknown - "FFFFFFFF"
-            //3. size in hex string like "00040000"
-            size = u32::from_str_radix(&lines[2], 16).unwrap();
-
-        } else if header_size == 48 || header_size == 44 || header_size == 40 {     //for disk drive firmware 
-            let hdr_string = String::from_utf8_lossy(&hdr);
-            let lines: Vec<String> = hdr_string.lines().map(|l| l.trim().to_string()).collect();
-            //1. - name, like "L12_110.IMG"
-            //2. - size in hex string like "001E6388"
-            //3. - unknown - single number like "3" (force flag?)
-            //4. - crc32 checksum like "0BC0F6F7"
-            //5. - unknown - like "00011200" -- this line is not present when size is 40 but were not using it anyway so whatever
-            name = lines[0].clone();
-            size = u32::from_str_radix(&lines[1], 16).unwrap();
-            println!("Name: {}", name);
-
-        } else if header_size == 16 {
-            // 4 bytes CRC32
-            // 4 bytes unknown "FF FF FF FF"
-            // 4 bytes size
-            // 4 bytes unknown "00 00 00 00"
-            size = u32::from_be_bytes(hdr[8..12].try_into().unwrap());
-
-        } else {
+        let Some(header) = parse_module_header(i, &hdr)? else {
             println!("Unsupported header size!");
             break
-        }
+        };
 
-        println!("Size: {}", size);
-        let data = common::read_exact(&mut data_reader, size as usize)?;
+        println!("Size: {}", header.size);
+        let data = common::read_exact(&mut data_reader, header.size as usize)?;
+        check_module(&header, &data);
+        let name = header.name;
         let output_path = Path::new(&app_ctx.output_dir).join(if name=="" {format!("{}_{}.bin", i+1, module_name)} else {format!("{}_{}_{}", i+1, module_name, name)});
 
         fs::create_dir_all(&app_ctx.output_dir)?;
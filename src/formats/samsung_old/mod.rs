@@ -33,9 +33,10 @@ pub fn extract_samsung_old(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(
 
     let mut secret: Option<&Vec<u8>> = None;
 
-    //find secret
-    for (name, keys) in app_ctx.keys.get_collection("SAMSUNG_OLD")? {
-        if fw_info.starts_with(name) {
+    //find secret, by firmware info prefix or the one given with the key option
+    let collection = app_ctx.keys.get_collection("SAMSUNG_OLD")?;
+    for (name, keys) in collection.iter() {
+        if app_ctx.forced_key().map_or(fw_info.starts_with(name), |forced| forced == name) {
             secret = Some(keys.first().unwrap());
             break;
         }
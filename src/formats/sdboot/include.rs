@@ -1,21 +1,5 @@
 use crate::utils::common::{string_from_bytes};
 use binrw::BinRead;
-use crate::formats::sddl_sec::include::AesKeyEntry;
-
-//These keys seem to be the same for most models
-pub const KEYS: [AesKeyEntry; 2] = [
-    //from tbl_sdboot;0, Decrypted with AES
-    AesKeyEntry {
-        key: [0x2e, 0x2a, 0x33, 0x62, 0x33, 0xe5, 0x5a, 0xba, 0xf5, 0xff, 0xec, 0x54, 0xf8, 0xab, 0x71, 0x25 ],
-        iv:  [0x2c, 0xa4, 0xb4, 0x7a, 0xff, 0xcb, 0x1a, 0xe8, 0xe1, 0xea, 0x2d, 0x9e, 0xf5, 0x12, 0x62, 0x9a]
-    },
-
-    //from tbl_sdboot;1, Decrypted with AES
-    AesKeyEntry {
-        key: [0x24, 0x5e, 0x8d, 0xe8, 0xf4, 0x99, 0xb0, 0xf9, 0x6e, 0xc1, 0x55, 0xb6, 0x08, 0xe2, 0x42, 0xf3],
-        iv:  [0x3e, 0x8f, 0x29, 0xd4, 0xba, 0xe7, 0x76, 0xa5, 0x18, 0xa7, 0xb6, 0x3c, 0x42, 0xca, 0x1b, 0x43]
-    }
-];
 
 #[derive(Debug, BinRead)]
 pub struct SdbootSecHeader {
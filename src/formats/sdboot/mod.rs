@@ -9,6 +9,7 @@ use std::collections::HashSet;
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::ukf::part_as_arr;
 use crate::formats::sddl_sec::include::*;
 use crate::utils::compression::decompress_zlib;
 use include::*;
@@ -53,11 +54,14 @@ pub fn extract_sdboot(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Bo
     let mut secfile_hdr_reader = Cursor::new(decipher(&common::read_exact(&mut file, 32)?));
     let secfile_header: SdbootSecHeader = secfile_hdr_reader.read_be()?;
 
+    //entries are named after the key id, the key option can point to another one
     let key_id = secfile_header.key_id();
-    if key_id != 0 && key_id != 1 {
-        return Err(format!("Invalid sdboot key_id! got {} but must be 0 or 1", key_id).into());
-    }
-    let key: KeyEntry = KeyEntry::AES(KEYS[key_id as usize]);
+    let key_name = app_ctx.forced_key().map_or(key_id.to_string(), |forced| forced.to_string());
+    let collection = app_ctx.keys.get_collection("SDBOOT")?;
+    let (_, parts) = collection.iter().find(|(name, _)| *name == key_name)
+        .ok_or_else(|| format!("No SDBOOT key {}!", key_name))?;
+    let owner = format!("SDBOOT \"{}\"", key_name);
+    let key: KeyEntry = KeyEntry::AES(AesKeyEntry {key: part_as_arr(&owner, parts, 0)?, iv: part_as_arr(&owner, parts, 1)?});
 
     println!("File info -\nKey ID: {}\nFile count: {}", secfile_header.key_id(), secfile_header.num_files());
 
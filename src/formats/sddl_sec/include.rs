@@ -70,6 +70,13 @@ impl KeyEntry {
             KeyEntry::AESPcks7(k) => decrypt_aes128_cbc_pcks7(data, &k.key, &k.iv)
         }
     }
+
+    pub fn block_size(&self) -> usize {
+        match self {
+            KeyEntry::DES(_) => 8,
+            KeyEntry::AES(_) | KeyEntry::AESPcks7(_) => 16,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -78,6 +85,7 @@ pub struct AesKeyEntry {
     pub iv: [u8; 16],
 }
 
+#[derive(Copy, Clone)]
 pub struct DesKeyEntry {
     pub key: [u8; 24],
     pub iv: [u8; 8],
@@ -97,20 +105,28 @@ pub struct SecHeader {
     _unused_or_reserved: [u8; 16], //not used, is zeros
 }
 impl SecHeader {
-    pub fn key_id(&self) -> u32 {
-        let string = string_from_bytes(&self.key_id_str_bytes);
-        string.parse().unwrap()
+    pub fn key_id(&self) -> Result<u32, String> {
+        parse_ascii_u32(&self.key_id_str_bytes, "key_id")
     }
-    pub fn grp_num(&self) -> u32 {
-        let string = string_from_bytes(&self.grp_num_str_bytes);
-        string.parse().unwrap()
+    pub fn grp_num(&self) -> Result<u32, String> {
+        parse_ascii_u32(&self.grp_num_str_bytes, "grp_num")
     }
-    pub fn prg_num(&self) -> u32 {
-        let string = string_from_bytes(&self.prg_num_str_bytes);
-        string.parse().unwrap()
+    pub fn prg_num(&self) -> Result<u32, String> {
+        parse_ascii_u32(&self.prg_num_str_bytes, "prg_num")
     }
 }
 
+//sizes and counts are stored as ASCII decimal numbers
+pub fn parse_ascii_number(bytes: &[u8], field: &str) -> Result<u64, String> {
+    let string = string_from_bytes(bytes);
+    string.trim().parse().map_err(|_| format!("Invalid {} field {:?}, not a number!", field, string))
+}
+
+fn parse_ascii_u32(bytes: &[u8], field: &str) -> Result<u32, String> {
+    let number = parse_ascii_number(bytes, field)?;
+    u32::try_from(number).map_err(|_| format!("Invalid {} field {}, too large!", field, number))
+}
+
 pub static INFO_FILE_EXTENSION: &str = ".TXT";
 
 #[derive(Debug, BinRead)]
@@ -122,16 +138,15 @@ impl FileHeader {
     pub fn name(&self) -> String {
         string_from_bytes(&self.name_str_bytes)
     }
-    pub fn size(&self) -> u64 {
-        let string = string_from_bytes(&self.size_str_bytes);
-        string.parse().unwrap()
+    pub fn size(&self) -> Result<u64, String> {
+        parse_ascii_number(&self.size_str_bytes, "file size")
     }
 }
 
 // -- MODULE --
 #[derive(Debug, BinRead)]
 pub struct ModuleComHeader { //"com_header"
-    pub _download_id: [u8; 4],      //always 0x11, 0x22, 0x33, 0x44 - magic?
+    pub download_id: [u8; 4],       //mostly 0x11, 0x22, 0x33, 0x44, but it can differ in some files
     _outer_maker_id: u8,
     _outer_model_id: u8,
     _inner_maker_id: u8,
@@ -3,6 +3,7 @@ pub mod include;
 mod util;
 use std::any::Any;
 use crate::AppContext;
+use crate::ukf::{KeyAlgorithm, part_as_arr};
 
 use std::path::{Path, PathBuf};
 use std::fs::{self, File, OpenOptions};
@@ -10,9 +11,10 @@ use std::io::{Cursor, Seek, SeekFrom, Write};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
-use crate::utils::common::{string_from_bytes, read_exact};
+use crate::utils::common::read_exact;
 use crate::utils::aes::{decrypt_aes128_cbc_nopad, decrypt_aes128_cbc_pcks7};
 use crate::utils::compression::{decompress_zlib};
+use crate::utils::keytrial::{find_first, prepare_keys};
 use include::*;
 use util::split_peaks_file;
 
@@ -39,14 +41,14 @@ fn get_sec_file(mut file: &File, key_entry: &KeyEntry) -> Result<(FileHeader, Ve
     let file_header: FileHeader = hdr_reader.read_be()?;
 
     let enc_size= if new_type {
-        file_header.size() as usize
+        file_header.size()? as usize
     } else {
         //extra ciphered data before encrypted data, prefixed by size, like "0021XXXXXX PEAKS.T00/12900002"
         //this counts into file size but not decrypt size
-        let extra_size: usize = string_from_bytes(&read_exact(&mut file, 4)?).parse().unwrap();
+        let extra_size = parse_ascii_number(&read_exact(&mut file, 4)?, "extra data size")? as usize;
         let _extra_data = read_exact(&mut file, extra_size)?;
 
-        file_header.size() as usize - (extra_size + 4)
+        (file_header.size()? as usize).checked_sub(extra_size + 4).ok_or("Extra data is larger than the file!")?
     };
 
     let dec_data = KeyEntry::decrypt(key_entry, &read_exact(&mut file, enc_size)?)?;
@@ -57,10 +59,10 @@ fn get_sec_file(mut file: &File, key_entry: &KeyEntry) -> Result<(FileHeader, Ve
 
         //extra info in enc data, like "0021XXXXXX PEAKS.T00/12900002000000571800"
         //part before size looks to be a duplicate of previous extra data, probably for signing purpose, size used for unpad
-        let extra_size: usize = string_from_bytes(&read_exact(&mut data_rdr, 4)?).parse().unwrap();
+        let extra_size = parse_ascii_number(&read_exact(&mut data_rdr, 4)?, "extra data size")? as usize;
         let _extra_data = read_exact(&mut data_rdr, extra_size + 4)?;
 
-        let data_size: usize = string_from_bytes(&read_exact(&mut data_rdr, 12)?).parse().unwrap();
+        let data_size = parse_ascii_number(&read_exact(&mut data_rdr, 12)?, "data size")? as usize;
         read_exact(&mut data_rdr, data_size)?
     };
 
@@ -99,6 +101,47 @@ fn parse_tdi_to_modules(tdi_data: Vec<u8>) -> Result<Vec<TdiTgtInf>, Box<dyn std
     Ok(modules)
 }
 
+//by knowing that the first file is always SDIT.FDI, find key(and mode), returns the key's name with it
+fn find_key(app_ctx: &AppContext, try_hdr: &[u8]) -> Result<Option<(String, KeyEntry)>, Box<dyn std::error::Error>> {
+    //for new, key will always be the same
+    let new_keys = app_ctx.keys.get_key_for("SDDL_SEC_NEW_KEY", KeyAlgorithm::Aes128)?;
+    let new_entry = AesKeyEntry {key: part_as_arr("key SDDL_SEC_NEW_KEY", &new_keys, 0)?, iv: part_as_arr("key SDDL_SEC_NEW_KEY", &new_keys, 1)?};
+    if let Ok(dec) = decrypt_aes128_cbc_pcks7(try_hdr, &new_entry.key, &new_entry.iv) && dec.starts_with(TDI_FILENAME.as_bytes()) {
+        return Ok(Some(("SDDL_SEC_NEW_KEY".to_string(), KeyEntry::AESPcks7(new_entry))))
+    }
+    //the file name fits in the first block
+    let first_block = &try_hdr[..16];
+    //new did not match, try all old AES keys
+    let aes_keys = prepare_keys(&app_ctx.get_collection_for("SDDL_SEC_OLD_KEYS_AES", KeyAlgorithm::Aes128)?, |name, keys| {
+        let owner = format!("SDDL_SEC_OLD_KEYS_AES \"{}\"", name);
+        Ok(AesKeyEntry {key: part_as_arr(&owner, keys, 0)?, iv: part_as_arr(&owner, keys, 1)?})
+    })?;
+    if let Some((i, _)) = find_first(&aes_keys, |(_, key_entry)| {
+        decrypt_aes128_cbc_nopad(first_block, &key_entry.key, &key_entry.iv).is_ok_and(|dec| dec.starts_with(TDI_FILENAME.as_bytes())).then_some(())
+    }) {
+        let (name, key_entry) = &aes_keys[i];
+        return Ok(Some((format!("SDDL_SEC_OLD_KEYS_AES \"{}\"", name), KeyEntry::AES(*key_entry))))
+    }
+    //...old DES keys
+    let des_keys = prepare_keys(&app_ctx.get_collection_for("SDDL_SEC_OLD_KEYS_DES", KeyAlgorithm::TripleDes)?, |name, keys| {
+        let owner = format!("SDDL_SEC_OLD_KEYS_DES \"{}\"", name);
+        Ok(DesKeyEntry {key: part_as_arr(&owner, keys, 0)?, iv: part_as_arr(&owner, keys, 1)?})
+    })?;
+    if let Some((i, _)) = find_first(&des_keys, |(_, key_entry)| {
+        decrypt_3des(first_block, key_entry).is_ok_and(|dec| dec.starts_with(TDI_FILENAME.as_bytes())).then_some(())
+    }) {
+        let (name, key_entry) = &des_keys[i];
+        return Ok(Some((format!("SDDL_SEC_OLD_KEYS_DES \"{}\"", name), KeyEntry::DES(*key_entry))))
+    }
+    Ok(None)
+}
+
+pub fn find_sddl_sec_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Expected file")?;
+    let try_hdr = common::read_file(file, 32, 0x20)?;
+    Ok(find_key(app_ctx, &try_hdr)?.map(|(name, _)| name))
+}
+
 pub fn extract_sddl_sec(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let save_extra = app_ctx.has_option("sddl_sec:save_extra");
@@ -106,59 +149,25 @@ pub fn extract_sddl_sec(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(),
     let mut secfile_hdr_reader = Cursor::new(decipher(&read_exact(&mut file, 32)?));
     let secfile_header: SecHeader = secfile_hdr_reader.read_be()?;
 
-    println!("File info -\nKey ID: {}\nGroup count: {}\nModule file count: {}", secfile_header.key_id(), secfile_header.grp_num(), secfile_header.prg_num());
+    println!("File info -\nKey ID: {}\nGroup count: {}\nModule file count: {}", secfile_header.key_id()?, secfile_header.grp_num()?, secfile_header.prg_num()?);
 
-    //by knowing that the first file is always SDIT.FDI, find key(and mode)
     let try_hdr = read_exact(&mut file, 0x20)?;
-
-    let mut key: Option<KeyEntry> = None;
-
-    //for new, key will always be the same
-    let (new_key, new_iv) = app_ctx.keys.get_double_key_as_arr::<16, 16>("SDDL_SEC_NEW_KEY")?;
-    let new_entry = AesKeyEntry {key: new_key, iv: new_iv};
-    if let Ok(dec) = decrypt_aes128_cbc_pcks7(&try_hdr, &new_entry.key, &new_entry.iv) {
-        if dec.starts_with(TDI_FILENAME.as_bytes()) {
-            println!("- New type detected\n");
-            key = Some(KeyEntry::AESPcks7(new_entry));
-        }
-    }
-    //new did not match, try all old AES keys
-    if key.is_none() {
-        for (_, keys) in app_ctx.keys.get_collection("SDDL_SEC_OLD_KEYS_AES")? {
-            let key_entry = AesKeyEntry {key: keys[0].as_slice().try_into().unwrap(), iv: keys[1].as_slice().try_into().unwrap()};
-            let dec = decrypt_aes128_cbc_nopad(&try_hdr, &key_entry.key, &key_entry.iv)?;
-            if dec.starts_with(TDI_FILENAME.as_bytes()) {
-                println!("- Old type detected with AES key={}, iv={}\n", hex::encode(key_entry.key) ,hex::encode(key_entry.iv));
-                key = Some(KeyEntry::AES(key_entry));
-                break
-            }
-        }        
-    }
-    //...old DES keys
-    if key.is_none() {
-        for (_, keys) in app_ctx.keys.get_collection("SDDL_SEC_OLD_KEYS_DES")? {
-            let key_entry = DesKeyEntry {key: keys[0].as_slice().try_into().unwrap(), iv: keys[1].as_slice().try_into().unwrap()};
-            let dec = decrypt_3des(&try_hdr, &key_entry)?;
-            if dec.starts_with(TDI_FILENAME.as_bytes()) {
-                println!("- Old type detected with DES key={}, iv={}\n", hex::encode(key_entry.key) ,hex::encode(key_entry.iv));
-                key = Some(KeyEntry::DES(key_entry));
-                break
-            }
-        }        
-    }
-    //nothing matched, quit
-    if key.is_none() {
-        return Err("No matching key found!".into());
+    let key = match find_key(app_ctx, &try_hdr)? {
+        Some((_, key)) => key,
+        //nothing matched, quit
+        None => return Err("No matching key found!".into()),
+    };
+    match &key {
+        KeyEntry::AESPcks7(_) => println!("- New type detected\n"),
+        KeyEntry::AES(key_entry) => println!("- Old type detected with AES key={}, iv={}\n", hex::encode(key_entry.key) ,hex::encode(key_entry.iv)),
+        KeyEntry::DES(key_entry) => println!("- Old type detected with DES key={}, iv={}\n", hex::encode(key_entry.key) ,hex::encode(key_entry.iv)),
     }
 
-    // -- key search end
-
-    let key = key.unwrap();
     fs::create_dir_all(&app_ctx.output_dir)?;
     file.seek(SeekFrom::Start(0x20))?;
 
     let (tdi_file, tdi_data) = get_sec_file(&file, &key)?;
-    println!("[TDI] Name: {}, Size: {}", tdi_file.name(), tdi_file.size());
+    println!("[TDI] Name: {}, Size: {}", tdi_file.name(), tdi_file.size()?);
     if save_extra { //Save SDIT
         let mut out_file = OpenOptions::new().write(true).create(true).open(Path::new(&app_ctx.output_dir).join(tdi_file.name()))?;
         out_file.write_all(&tdi_data)?;
@@ -170,9 +179,9 @@ pub fn extract_sddl_sec(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(),
     let modules = parse_tdi_to_modules(tdi_data)?;
 
     //get info files, each info file belongs to its respecitve group in the TDI
-    for i in 0..secfile_header.grp_num() {
+    for i in 0..secfile_header.grp_num()? {
         let (info_file, info_data) = get_sec_file(&file, &key)?;
-        println!("\n[INFO] ID: {}, Name: {}, Size: {}", i, info_file.name(), info_file.size());
+        println!("\n[INFO] ID: {}, Name: {}, Size: {}", i, info_file.name(), info_file.size()?);
         if !info_file.name().ends_with(INFO_FILE_EXTENSION) {
             return Err(format!("Info file {} does not have the expected extension {}!", info_file.name(), INFO_FILE_EXTENSION).into());
         }
@@ -196,13 +205,14 @@ pub fn extract_sddl_sec(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(),
             if !module_file.name().starts_with(&module.module_name()) {
                 return Err(format!("Module file {} does not start with the module's name: {}!", module_file.name(), module.module_name()).into());
             }    
-            println!("  Segment #{}/{} - Name: {}, Size: {}", i+1, module.num_of_txx, module_file.name(), module_file.size());
+            println!("  Segment #{}/{} - Name: {}, Size: {}", i+1, module.num_of_txx, module_file.name(), module_file.size()?);
 
             let mut module_reader = Cursor::new(module_data);
-            let _com_header: ModuleComHeader = module_reader.read_be()?;
-            //if com_header.download_id != DOWNLOAD_ID {            it seems this can differ in some files
-            //    return Err("Invalid module com_header!".into());
-            //}
+            let com_header: ModuleComHeader = module_reader.read_be()?;
+            //it seems this can differ in some files, so it is not an error
+            if com_header.download_id != DOWNLOAD_ID {
+                println!("      - Warning: unexpected download ID {} in com_header!", hex::encode_upper(com_header.download_id));
+            }
 
             let module_header: ModuleHeader = module_reader.read_be()?;
             let mut module_data = read_exact(&mut module_reader, module_header.cmp_size as usize)?;
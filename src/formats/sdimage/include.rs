@@ -8,11 +8,11 @@ pub struct EntryHeader {
     pub model_id: u32,
     pub version: [u8; 4],
     flags: u32,
-    pub size1: u32,
-    pub _size2: u32,
+    pub size1: u32,     //stored size
+    pub size2: u32,     //size after decompression
     _info_size: u32,
     _unused: u32,
-    _sha1: [u8; 20],
+    pub sha1: [u8; 20], //of the stored data after decryption
     #[br(count = _info_size)] info_bytes: Vec<u8>,
 }
 impl EntryHeader {
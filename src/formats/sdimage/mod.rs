@@ -1,13 +1,18 @@
 mod include;
 use std::any::Any;
 use std::fs::{self, OpenOptions};
-use std::io::{Seek, Write};
+use std::io::{Seek, SeekFrom, Write};
 use std::path::Path;
 use crate::AppContext;
 use binrw::BinReaderExt;
+use sha1::{Digest, Sha1};
 
 use include::*;
 use crate::utils::common;
+use crate::utils::compression::decompress_zlib;
+use crate::utils::keytrial::{find_first, prepare_optional_keys};
+use crate::ukf::part_as_arr;
+use crate::formats::sddl_sec::include::{KeyEntry, AesKeyEntry, DesKeyEntry};
 
 pub fn is_sdimage_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
@@ -20,10 +25,69 @@ pub fn is_sdimage_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box
     }
 }
 
+//entry name and key
+type NamedKeyEntry = (String, KeyEntry);
+
+//AES entries are (key, iv) of 16 bytes, 3DES entries have a 24 byte key and 8 byte iv. None if there is no SDIMAGE collection
+fn prepare_sdimage_keys(app_ctx: &AppContext) -> Result<Option<Vec<NamedKeyEntry>>, Box<dyn std::error::Error>> {
+    prepare_optional_keys(app_ctx.get_collection("SDIMAGE"), |name, keys| {
+        let owner = format!("SDIMAGE \"{}\"", name);
+        if keys.first().is_some_and(|key| key.len() == 24) {
+            Ok(KeyEntry::DES(DesKeyEntry {key: part_as_arr(&owner, keys, 0)?, iv: part_as_arr(&owner, keys, 1)?}))
+        } else {
+            Ok(KeyEntry::AES(AesKeyEntry {key: part_as_arr(&owner, keys, 0)?, iv: part_as_arr(&owner, keys, 1)?}))
+        }
+    })
+}
+
+//decrypt only aligned data, rest is plain
+fn decrypt_entry(data: &[u8], key: &KeyEntry) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let align_size = data.len() / key.block_size() * key.block_size();
+    let mut out_data = data.to_vec();
+    out_data[..align_size].copy_from_slice(&key.decrypt(&data[..align_size])?);
+    Ok(out_data)
+}
+
+//the entry header has the SHA-1 of the decrypted data, so a key is only accepted if it matches
+fn find_entry_key(keys: &[NamedKeyEntry], entry: &EntryHeader, data: &[u8]) -> Option<(usize, Vec<u8>)> {
+    find_first(keys, |(_, key)| {
+        let decrypted = decrypt_entry(data, key).ok()?;
+        (Sha1::digest(&decrypted).as_slice() == entry.sha1).then_some(decrypted)
+    })
+}
+
+fn entry_filename(entry: &EntryHeader) -> Result<String, Box<dyn std::error::Error>> {
+    let info = entry.info();
+    let filename = info.split("FN=\"").nth(1).and_then(|s| s.split('"').next()).ok_or("Missing filename in entry info!")?;
+    Ok(filename.to_string())
+}
+
+pub fn find_sdimage_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut file = app_ctx.file().ok_or("Expected file")?;
+    let Some(keys) = prepare_sdimage_keys(app_ctx)? else {return Ok(None)};
+
+    file.seek(SeekFrom::Start(16))?;
+    while file.stream_position()? < file.metadata()?.len() {
+        let entry: EntryHeader = file.read_le()?;
+        if entry.is_empty() {
+            continue
+        }
+        let data = common::read_exact(&mut file, entry.size1 as usize)?;
+        if entry.is_encrypted() {
+            return Ok(find_entry_key(&keys, &entry, &data).map(|(i, _)| format!("SDIMAGE \"{}\"", keys[i].0)))
+        }
+    }
+    Ok(None)
+}
+
 pub fn extract_sdimage(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
 
-    file.seek(std::io::SeekFrom::Start(16))?;
+    file.seek(SeekFrom::Start(16))?;
+
+    let keys = prepare_sdimage_keys(app_ctx)?;
+    //entries of one file should use the same key, so the last matching one is tried first
+    let mut last_key: Option<usize> = None;
 
     let mut e_i = 0;
     while file.stream_position()? < file.metadata()?.len() as u64 {
@@ -33,20 +97,22 @@ pub fn extract_sdimage(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), B
                 if entry.is_encrypted() {" [ENCRYPTED]"} else {""}, if entry.is_empty() {"[EMPTY]"} else {""});
 
         if !entry.is_empty() {
-            let info = entry.info();
-            let filename = info.split("FN=\"").nth(1).and_then(|s| s.split('"').next()).unwrap();
+            let filename = entry_filename(&entry)?;
             println!("- Filename: {}", filename);
 
             let data = common::read_exact(&mut file, entry.size1 as usize)?;
 
-            fs::create_dir_all(&app_ctx.output_dir)?;
-            let out_folder = Path::new(&app_ctx.output_dir).join(entry.target_name());
-            fs::create_dir_all(&out_folder)?;
-            let output_path = out_folder.join(filename);
-
-            let mut out_file = OpenOptions::new().write(true).create(true).open(output_path)?;
-            out_file.write_all(&data)?;
+            let data = if !entry.is_encrypted() {
+                decompress_if_needed(&entry, data)?
+            } else if let Some((name, decrypted)) = decrypt_with_keys(keys.as_deref(), &mut last_key, &entry, &data) {
+                println!("- Decrypted with key: {}", name);
+                decompress_if_needed(&entry, decrypted)?
+            } else {
+                println!("- Warning: no matching SDIMAGE key - saving ENCRYPTED data!");
+                data
+            };
 
+            save_entry(app_ctx, &entry, &filename, &data)?;
             println!("-- Saved file!");
         }
 
@@ -54,4 +120,35 @@ pub fn extract_sdimage(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(), B
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn decrypt_with_keys(keys: Option<&[NamedKeyEntry]>, last_key: &mut Option<usize>, entry: &EntryHeader, data: &[u8]) -> Option<(String, Vec<u8>)> {
+    let keys = keys?;
+    if let Some(i) = *last_key && let Some((_, decrypted)) = find_entry_key(&keys[i..=i], entry, data) {
+        return Some((keys[i].0.clone(), decrypted))
+    }
+    let (i, decrypted) = find_entry_key(keys, entry, data)?;
+    *last_key = Some(i);
+    Some((keys[i].0.clone(), decrypted))
+}
+
+//compressed entries are zlib streams, with the decompressed size in the header
+fn decompress_if_needed(entry: &EntryHeader, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if entry.size2 == entry.size1 || !data.starts_with(&[0x78]) {
+        return Ok(data)
+    }
+    println!("- Decompressing...");
+    let decompressed = decompress_zlib(&data)?;
+    if decompressed.len() != entry.size2 as usize {
+        println!("- Warning: decompressed size {} does not match the expected {}", decompressed.len(), entry.size2);
+    }
+    Ok(decompressed)
+}
+
+fn save_entry(app_ctx: &AppContext, entry: &EntryHeader, filename: &str, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let out_folder = Path::new(&app_ctx.output_dir).join(entry.target_name());
+    fs::create_dir_all(&out_folder)?;
+    let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(out_folder.join(filename))?;
+    out_file.write_all(data)?;
+    Ok(())
+}
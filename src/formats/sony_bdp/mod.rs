@@ -9,6 +9,8 @@ use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use binrw::BinReaderExt;
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, prepare_keys};
+use crate::ukf::part_as_arr;
 use crate::formats;
 use include::*;
 
@@ -31,23 +33,31 @@ pub fn is_sony_bdp_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Bo
     }
 
     //try new encryption (aes)
-    for (name, keys) in app_ctx.keys.get_collection("SONY_BDP_AES")? {
-        let key_array: [u8; 16] = keys[0].as_slice().try_into()?;
-        let iv_array: [u8; 16] = keys[1].as_slice().try_into()?;
-        let try_decrypt = ver_up_decrypt_aes128ofb(&key_array, &iv_array, &header_magic);
-
-        if is_valid_header_magic(&try_decrypt) {
-            return Ok(Some(Box::new(
-                SonyBdpCtx {encryption_type: 
-                    EncryptionType::AesOfb((key_array, iv_array, name.to_string()))
-                }
-            )));
-        }
+    let aes_keys = prepare_keys(&app_ctx.get_collection("SONY_BDP_AES")?, |name, keys| {
+        let owner = format!("SONY_BDP_AES \"{}\"", name);
+        Ok((part_as_arr::<16>(&owner, keys, 0)?, part_as_arr::<16>(&owner, keys, 1)?))
+    })?;
+    if let Some((i, _)) = find_first(&aes_keys, |(_, (key_array, iv_array))| {
+        is_valid_header_magic(&ver_up_decrypt_aes128ofb(key_array, iv_array, &header_magic)).then_some(())
+    }) {
+        let (name, (key_array, iv_array)) = &aes_keys[i];
+        return Ok(Some(Box::new(
+            SonyBdpCtx {encryption_type: 
+                EncryptionType::AesOfb((*key_array, *iv_array, name.clone()))
+            }
+        )));
     }
 
     Ok(None)
 }
 
+pub fn find_sony_bdp_key(app_ctx: &AppContext) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(is_sony_bdp_file(app_ctx)?.map(|ctx| match ctx.downcast::<SonyBdpCtx>().expect("Missing context").encryption_type {
+        EncryptionType::HexSubst => "SONY_BDP_HEX_SUBST".to_string(),
+        EncryptionType::AesOfb((_, _, key_name)) => format!("SONY_BDP_AES \"{}\"", key_name),
+    }))
+}
+
 pub fn extract_sony_bdp(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
     let mut file = app_ctx.file().ok_or("Extractor expected file")?;
     let ctx = ctx.downcast::<SonyBdpCtx>().expect("Missing context");
@@ -119,6 +129,7 @@ pub fn extract_sony_bdp(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), B
             output_dir: mtk_extraction_path,
             options: app_ctx.options,
             keys: app_ctx.keys,
+            hints: app_ctx.hints,
         };
 
         if let Some(result) = formats::mtk_bdp::is_mtk_bdp_file(&ctx)? {
@@ -0,0 +1,147 @@
+use crate::utils::common;
+use binrw::BinRead;
+
+pub static UIMAGE_MAGIC: &[u8; 4] = b"\x27\x05\x19\x56";
+pub static LEGACY_HEADER_SIZE: usize = 64;
+
+//image types with a size table in front of the data
+pub static IH_TYPE_MULTI: u8 = 4;
+pub static IH_TYPE_SCRIPT: u8 = 6;
+
+#[derive(BinRead)]
+pub struct LegacyHeader {
+    _magic: u32,
+    pub header_crc: u32,        //of the header with this field zeroed
+    pub timestamp: u32,
+    pub data_size: u32,
+    pub load_addr: u32,
+    pub entry_point: u32,
+    pub data_crc: u32,
+    pub os: u8,
+    pub arch: u8,
+    pub image_type: u8,
+    pub compression: u8,
+    name_bytes: [u8; 32],
+}
+impl LegacyHeader {
+    pub fn name(&self) -> String {
+        common::string_from_bytes(&self.name_bytes)
+    }
+}
+
+//legacy headers use numbers, FIT uses the same names as strings
+pub fn os_name(id: u8) -> &'static str {
+    match id {
+        1 => "openbsd",
+        2 => "netbsd",
+        3 => "freebsd",
+        4 => "4_4bsd",
+        5 => "linux",
+        6 => "svr4",
+        7 => "esix",
+        8 => "solaris",
+        9 => "irix",
+        10 => "sco",
+        11 => "dell",
+        12 => "ncr",
+        13 => "lynxos",
+        14 => "vxworks",
+        15 => "psos",
+        16 => "qnx",
+        17 => "u-boot",
+        18 => "rtems",
+        19 => "artos",
+        20 => "unity",
+        21 => "integrity",
+        22 => "ose",
+        23 => "plan9",
+        24 => "openrtos",
+        25 => "arm-trusted-firmware",
+        26 => "tee",
+        27 => "opensbi",
+        28 => "efi",
+        _ => "unknown",
+    }
+}
+
+pub fn arch_name(id: u8) -> &'static str {
+    match id {
+        1 => "alpha",
+        2 => "arm",
+        3 => "x86",
+        4 => "ia64",
+        5 => "mips",
+        6 => "mips64",
+        7 => "powerpc",
+        8 => "s390",
+        9 => "sh",
+        10 => "sparc",
+        11 => "sparc64",
+        12 => "m68k",
+        14 => "microblaze",
+        15 => "nios2",
+        16 => "blackfin",
+        17 => "avr32",
+        18 => "st200",
+        19 => "sandbox",
+        20 => "nds32",
+        21 => "or1k",
+        22 => "arm64",
+        23 => "arc",
+        24 => "x86_64",
+        25 => "xtensa",
+        26 => "riscv",
+        _ => "unknown",
+    }
+}
+
+pub fn type_name(id: u8) -> &'static str {
+    match id {
+        1 => "standalone",
+        2 => "kernel",
+        3 => "ramdisk",
+        4 => "multi",
+        5 => "firmware",
+        6 => "script",
+        7 => "filesystem",
+        8 => "flat_dt",
+        9 => "kwbimage",
+        10 => "imximage",
+        11 => "ublimage",
+        12 => "omapimage",
+        13 => "aisimage",
+        14 => "kernel_noload",
+        15 => "pblimage",
+        16 => "mxsimage",
+        17 => "gpimage",
+        18 => "atmelimage",
+        19 => "socfpgaimage",
+        20 => "x86_setup",
+        21 => "lpc32xximage",
+        22 => "loadables",
+        23 => "rkimage",
+        24 => "rksd",
+        25 => "rkspi",
+        26 => "zynqimage",
+        27 => "zynqmpimage",
+        28 => "fpga",
+        29 => "vybridimage",
+        30 => "tee",
+        31 => "firmware_ivt",
+        32 => "pmmc",
+        _ => "unknown",
+    }
+}
+
+pub fn compression_name(id: u8) -> &'static str {
+    match id {
+        0 => "none",
+        1 => "gzip",
+        2 => "bzip2",
+        3 => "lzma",
+        4 => "lzo",
+        5 => "lz4",
+        6 => "zstd",
+        _ => "unknown",
+    }
+}
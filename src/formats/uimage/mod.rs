@@ -0,0 +1,245 @@
+mod include;
+use std::any::Any;
+use crate::AppContext;
+
+use std::path::Path;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use binrw::BinReaderExt;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha1::Sha1;
+
+use crate::utils::common;
+use crate::utils::compression::{decompress_gzip, decompress_bzip, decompress_lzma, decompress_lz4_frame, decompress_zstd};
+use crate::utils::lzop::unlzop_to_file;
+use crate::utils::fdt::{self, FdtNode, FDT_MAGIC};
+use crate::formats::dtb::decode_embedded_dtbs;
+use include::*;
+
+pub enum UImageContext {
+    Legacy,
+    Fit,
+}
+
+pub fn is_uimage_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+
+    let header = common::read_file(file, 0, 40)?;
+    if &header[0..4] == UIMAGE_MAGIC {
+        return Ok(Some(Box::new(UImageContext::Legacy)))
+    }
+
+    //plain DTBs share the magic, FIT images have an /images node
+    if &header[0..4] == FDT_MAGIC {
+        let total_size = u32::from_be_bytes(header[4..8].try_into()?) as u64;
+        if total_size > file.metadata()?.len() {return Ok(None)}
+        let fdt_data = common::read_file(file, 0, total_size as usize)?;
+        if let Ok((_, root)) = fdt::parse_fdt(&fdt_data) && root.child("images").is_some() {
+            return Ok(Some(Box::new(UImageContext::Fit)))
+        }
+    }
+
+    Ok(None)
+}
+
+//writes the payload decompressed when possible, the raw data otherwise, returns the decompressed data if it was kept in memory
+fn save_payload(data: &[u8], compression: &str, output_dir: &Path, name: &str, extension: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let output_path = output_dir.join(format!("{}.{}", name, extension));
+
+    let decompressed = match compression {
+        "none" => Ok(data.to_vec()),
+        "gzip" => decompress_gzip(data),
+        "bzip2" => decompress_bzip(data).map_err(|e| e.into()),
+        "lzma" => decompress_lzma(data),
+        "lz4" => decompress_lz4_frame(data).map_err(|e| e.into()),
+        "zstd" => decompress_zstd(data).map_err(|e| e.into()),
+        "lzo" => {
+            println!("- Decompressing lzo...");
+            if output_path.exists() {
+                fs::remove_file(&output_path)?;
+            }
+            match unlzop_to_file(data, output_path.clone()) {
+                Ok(()) => {
+                    println!("-- Saved file!");
+                    return Ok(None)
+                }
+                Err(e) => Err(e),
+            }
+        }
+        _ => Err(format!("Unsupported compression {}", compression).into()),
+    };
+
+    match decompressed {
+        Ok(out_data) => {
+            if compression != "none" {
+                println!("- Decompressed {}, size: {}", compression, out_data.len());
+            }
+            let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(&output_path)?;
+            out_file.write_all(&out_data)?;
+            println!("-- Saved file!");
+            Ok(Some(out_data))
+        }
+        Err(e) => {
+            println!("- Warning: failed to decompress ({}), saving raw data...", e);
+            let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_dir.join(format!("{}.{}", name, compression)))?;
+            out_file.write_all(data)?;
+            println!("-- Saved file!");
+            Ok(None)
+        }
+    }
+}
+
+fn extract_legacy(app_ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = app_ctx.file().ok_or("Extractor expected file")?;
+
+    let header_bytes = common::read_file(file, 0, LEGACY_HEADER_SIZE)?;
+    let header: LegacyHeader = file.read_be()?;
+    let name = header.name();
+
+    println!("Image info -\nName: {}\nTimestamp: {}\nType: {}\nOS: {}\nArchitecture: {}\nCompression: {}\nLoad address: 0x{:08x}\nEntry point: 0x{:08x}\nData size: {}",
+            name, header.timestamp, type_name(header.image_type), os_name(header.os), arch_name(header.arch), compression_name(header.compression),
+            header.load_addr, header.entry_point, header.data_size);
+
+    let mut crc_header = header_bytes.clone();
+    crc_header[4..8].copy_from_slice(&[0; 4]);
+    if common::crc32(&crc_header) == header.header_crc {
+        println!("- Header CRC OK");
+    } else {
+        println!("- Warning: header CRC mismatch!");
+    }
+
+    let file_size = file.metadata()?.len();
+    if LEGACY_HEADER_SIZE as u64 + header.data_size as u64 > file_size {
+        return Err(format!("Image is truncated, expected {} bytes of data but only {} are present!", header.data_size, file_size - LEGACY_HEADER_SIZE as u64).into());
+    }
+    let data = common::read_file(file, LEGACY_HEADER_SIZE as u64, header.data_size as usize)?;
+    if common::crc32(&data) == header.data_crc {
+        println!("- Data CRC OK");
+    } else {
+        println!("- Warning: data CRC mismatch, data may be corrupted!");
+    }
+
+    fs::create_dir_all(&app_ctx.output_dir)?;
+    let out_name = common::output_name(&name);
+    let compression = compression_name(header.compression);
+
+    if header.image_type == IH_TYPE_MULTI || header.image_type == IH_TYPE_SCRIPT {
+        //zero terminated table of sizes, every image after the first starts on a 4 byte boundary
+        let mut sizes = Vec::new();
+        let mut pos = 0;
+        loop {
+            let size = u32::from_be_bytes(data.get(pos..pos + 4).ok_or("Invalid image size table!")?.try_into()?);
+            pos += 4;
+            if size == 0 {break}
+            sizes.push(size as usize);
+        }
+
+        for (i, size) in sizes.iter().enumerate() {
+            let image_data = data.get(pos..pos + size).ok_or("Sub-image out of bounds!")?;
+            println!("\n({}/{}) - Size: {}", i + 1, sizes.len(), size);
+            let extension = if header.image_type == IH_TYPE_SCRIPT {"txt"} else {"bin"};
+            save_payload(image_data, compression, &app_ctx.output_dir, &format!("{}_{}", out_name, i + 1), extension)?;
+            pos = (pos + size + 3) & !3;
+        }
+    } else {
+        println!();
+        save_payload(&data, compression, &app_ctx.output_dir, &out_name, "bin")?;
+    }
+
+    Ok(())
+}
+
+fn compute_hash(algo: &str, data: &[u8]) -> Option<Vec<u8>> {
+    match algo {
+        "crc32" => Some(common::crc32(data).to_be_bytes().to_vec()),
+        "md5" => Some(md5::compute(data).0.to_vec()),
+        "sha1" => Some(<Sha1 as sha1::Digest>::digest(data).to_vec()),
+        "sha256" => Some(Sha256::digest(data).to_vec()),
+        "sha384" => Some(Sha384::digest(data).to_vec()),
+        "sha512" => Some(Sha512::digest(data).to_vec()),
+        _ => None,
+    }
+}
+
+//data is embedded in the node, or stored after the tree with an offset relative to its end or an absolute position
+fn fit_image_data<'a>(data: &'a [u8], image: &'a FdtNode, external_base: usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+    if let Some(embedded) = image.prop("data") {
+        return Ok(embedded)
+    }
+    let size = image.prop_u32("data-size").ok_or("Image has no data!")? as usize;
+    let start = if let Some(offset) = image.prop_u32("data-offset") {
+        external_base + offset as usize
+    } else {
+        image.prop_u32("data-position").ok_or("Image has no data!")? as usize
+    };
+    data.get(start..start + size).ok_or_else(|| "Image data out of bounds!".into())
+}
+
+fn extract_fit(app_ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+
+    let data = common::read_file(file, 0, file.metadata()?.len() as usize)?;
+    let (header, root) = fdt::parse_fdt(&data)?;
+    let external_base = (header.total_size as usize + 3) & !3;
+
+    println!("FIT info -\nDescription: {}\nTimestamp: {}",
+            root.prop_str("description").unwrap_or_default(), root.prop_u32("timestamp").unwrap_or(0));
+
+    if let Some(configs) = root.child("configurations") {
+        println!("Default configuration: {}", configs.prop_str("default").unwrap_or_default());
+        for config in &configs.children {
+            let parts: Vec<String> = ["kernel", "fdt", "ramdisk", "loadables", "firmware"].iter()
+                .filter_map(|p| config.prop(p).map(|v| format!("{}: {}", p, v.split(|&b| b == 0).filter(|s| !s.is_empty())
+                    .map(|s| String::from_utf8_lossy(s).to_string()).collect::<Vec<_>>().join(", "))))
+                .collect();
+            println!("- {} ({}) - {}", config.name, config.prop_str("description").unwrap_or_default(), parts.join("; "));
+        }
+    }
+
+    fs::create_dir_all(&app_ctx.output_dir)?;
+    let images = root.child("images").ok_or("FIT has no images!")?;
+
+    for (i, image) in images.children.iter().enumerate() {
+        let image_type = image.prop_str("type").unwrap_or_default();
+        let compression = image.prop_str("compression").unwrap_or("none".to_string());
+        let image_data = fit_image_data(&data, image, external_base)?;
+
+        println!("\n({}/{}) - {}, Description: {}, Size: {}", i + 1, images.children.len(), image.name, image.prop_str("description").unwrap_or_default(), image_data.len());
+        println!("- Type: {}, OS: {}, Architecture: {}, Compression: {}",
+                image_type, image.prop_str("os").unwrap_or_default(), image.prop_str("arch").unwrap_or_default(), compression);
+        if let Some(load) = image.prop_addr("load") {
+            println!("- Load address: 0x{:08x}, Entry point: 0x{:08x}", load, image.prop_addr("entry").unwrap_or(0));
+        }
+
+        for sub in &image.children {
+            if sub.name.starts_with("hash") {
+                let algo = sub.prop_str("algo").unwrap_or_default();
+                let expected = sub.prop("value").unwrap_or_default();
+                match compute_hash(&algo, image_data) {
+                    Some(calc) if calc == expected => println!("- Hash {} OK", algo),
+                    Some(calc) => println!("- Warning: hash {} mismatch! (expected {}, got {})", algo, hex::encode(expected), hex::encode(calc)),
+                    None => println!("- Warning: unsupported hash algorithm {}, skipping check...", algo),
+                }
+            } else if sub.name.starts_with("signature") {
+                println!("- Signature {} ({}), not verified", sub.prop_str("algo").unwrap_or_default(), sub.prop_str("key-name-hint").unwrap_or_default());
+            }
+        }
+
+        let extension = if image_type == "flat_dt" {"dtb"} else {"bin"};
+        let payload = save_payload(image_data, &compression, &app_ctx.output_dir, &common::output_name(&image.name), extension)?;
+        if image_type == "flat_dt" && let Some(payload) = payload {
+            decode_embedded_dtbs(&payload, &app_ctx.output_dir, &common::output_name(&image.name))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn extract_uimage(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let ctx = ctx.downcast::<UImageContext>().expect("Missing context");
+
+    match *ctx {
+        UImageContext::Legacy => extract_legacy(app_ctx),
+        UImageContext::Fit => extract_fit(app_ctx),
+    }
+}
@@ -16,6 +16,7 @@ use sha1::Sha1;
 use aes_gcm::{aead::{Aead, KeyInit, Payload}, Aes128Gcm, Nonce};
 
 use crate::utils::common;
+use crate::utils::keytrial::{find_first, prepare_keys};
 
 pub fn is_utv_qterics_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
     let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
@@ -57,19 +58,15 @@ pub fn extract_utv_qterics(app_ctx: &AppContext, _ctx: Box<dyn Any>) -> Result<(
     let enc_key = general_purpose::STANDARD.decode(&key)?;
 
     //find private key that decrypts the encrypted key
-    let mut dec_key: Option<Vec<u8>> = None;
-    for (name, keys) in app_ctx.keys.get_collection("UTV_QTERICS")? {
+    let private_keys = prepare_keys(&app_ctx.get_collection("UTV_QTERICS")?, |name, keys| {
         let key_str= common::string_from_bytes(&general_purpose::STANDARD.decode(keys.first().unwrap())?);
-        let private_key = RsaPrivateKey::from_pkcs8_pem(&key_str)?;
-        if let Ok(dec) = private_key.decrypt(Oaep::new::<Sha1>(), &enc_key) {
-            if dec.len() == 44 {
-                println!("\nUsing key: {}\n", name);
-                dec_key = Some(dec);
-                break
-            }
-        }
-    }
-    let dec_key = if let Some(_dec_key) = dec_key {
+        RsaPrivateKey::from_pkcs8_pem(&key_str).map_err(|e| format!("UTV_QTERICS \"{}\": {}", name, e).into())
+    })?;
+    let found = find_first(&private_keys, |(_, private_key)| {
+        private_key.decrypt(Oaep::new::<Sha1>(), &enc_key).ok().filter(|dec| dec.len() == 44)
+    });
+    let dec_key = if let Some((i, _dec_key)) = found {
+        println!("\nUsing key: {}\n", private_keys[i].0);
         _dec_key
     } else {
         return Err("No matching key found!".into());
@@ -0,0 +1,83 @@
+use crate::utils::common;
+use binrw::BinRead;
+
+//object types
+pub static OBJECT_TYPE_FILE: u32 = 1;
+pub static OBJECT_TYPE_SYMLINK: u32 = 2;
+pub static OBJECT_TYPE_DIRECTORY: u32 = 3;
+pub static OBJECT_TYPE_HARDLINK: u32 = 4;
+pub static OBJECT_TYPE_SPECIAL: u32 = 5;
+
+//fixed object ids
+pub static ROOT_OBJ_ID: u32 = 1;
+pub static LOSTNFOUND_OBJ_ID: u32 = 2;
+
+//sequence numbers of blocks in use, anything else is erased or broken
+pub static LOWEST_SEQUENCE_NUMBER: u32 = 0x00001000;
+pub static HIGHEST_SEQUENCE_NUMBER: u32 = 0xEFFFFF00;
+
+//set in chunk_id of header chunks that carry extra info in the tags, obj_id then has the object type in its top bits
+pub static EXTRA_HEADER_INFO_FLAG: u32 = 0x80000000;
+pub static EXTRA_OBJ_ID_MASK: u32 = 0x0FFFFFFF;
+//ids are handed out from a small range, used to tell real tags from misaligned ones
+pub static MAX_OBJ_ID: u32 = 0x3FFFF;
+
+//(chunk size, spare size) of common NAND geometries
+pub static LAYOUTS: &[(u64, u64)] = &[(2048, 64), (4096, 128), (4096, 224), (8192, 256), (8192, 448), (16384, 1280)];
+//where the packed tags start in the spare area, 0 for images made by mkyaffs2image, 2 behind the bad block marker for MTD dumps
+pub static TAG_OFFSETS: &[u64] = &[0, 2];
+
+#[derive(BinRead)]
+pub struct PackedTags {
+    pub seq_number: u32,
+    pub obj_id: u32,
+    pub chunk_id: u32,          //0 = object header, otherwise 1 based data chunk index
+    pub n_bytes: u32,
+}
+impl PackedTags {
+    pub fn is_valid(&self) -> bool {
+        (LOWEST_SEQUENCE_NUMBER..=HIGHEST_SEQUENCE_NUMBER).contains(&self.seq_number)
+    }
+    pub fn is_header(&self) -> bool {
+        self.chunk_id == 0 || (self.chunk_id & EXTRA_HEADER_INFO_FLAG) != 0
+    }
+    pub fn object_id(&self) -> u32 {
+        if (self.chunk_id & EXTRA_HEADER_INFO_FLAG) != 0 {self.obj_id & EXTRA_OBJ_ID_MASK} else {self.obj_id}
+    }
+}
+
+#[derive(BinRead)]
+pub struct ObjectHeader {
+    pub obj_type: u32,
+    pub parent_obj_id: u32,
+    _sum_no_longer_used: u16,
+    name_bytes: [u8; 256],
+    _pad: u16,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    _atime: u32,
+    _mtime: u32,
+    _ctime: u32,
+    file_size_low: u32,
+    pub equiv_id: u32,          //target object of hardlinks
+    alias_bytes: [u8; 160],     //target of symlinks
+    pub rdev: u32,
+    _win_times: [u32; 6],
+    _inband_shadowed_obj_id: u32,
+    _inband_is_shrink: u32,
+    file_size_high: u32,
+}
+impl ObjectHeader {
+    pub fn name(&self) -> String {
+        common::string_from_bytes(&self.name_bytes)
+    }
+    pub fn alias(&self) -> String {
+        common::string_from_bytes(&self.alias_bytes)
+    }
+    //high word is left erased by older versions
+    pub fn file_size(&self) -> u64 {
+        let high = if self.file_size_high == 0xFFFFFFFF {0} else {self.file_size_high as u64};
+        (high << 32) | self.file_size_low as u64
+    }
+}
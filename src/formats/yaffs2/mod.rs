@@ -0,0 +1,270 @@
+mod include;
+use std::any::Any;
+use crate::AppContext;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use binrw::{BinReaderExt, Endian};
+
+use crate::utils::common;
+use crate::utils::manifest::{Manifest, FileType};
+use include::*;
+
+pub struct Yaffs2Context {
+    chunk_size: u64,
+    spare_size: u64,
+    tag_offset: u64,
+    endian: Endian,
+}
+impl Yaffs2Context {
+    fn page_size(&self) -> u64 {
+        self.chunk_size + self.spare_size
+    }
+}
+
+fn read_tags(file: &File, ctx: &Yaffs2Context, page: u64) -> Result<PackedTags, Box<dyn std::error::Error>> {
+    let tag_bytes = common::read_file(file, page * ctx.page_size() + ctx.chunk_size + ctx.tag_offset, 16)?;
+    Ok(Cursor::new(tag_bytes).read_type(ctx.endian)?)
+}
+
+fn read_header(file: &File, ctx: &Yaffs2Context, page: u64) -> Result<ObjectHeader, Box<dyn std::error::Error>> {
+    let header_bytes = common::read_file(file, page * ctx.page_size(), 512)?;
+    Ok(Cursor::new(header_bytes).read_type(ctx.endian)?)
+}
+
+//there is no magic, so the first used chunks have to look like valid tags and object headers for a layout
+fn check_layout(file: &File, ctx: &Yaffs2Context, page_count: u64) -> Result<bool, Box<dyn std::error::Error>> {
+    for page in 0..page_count.min(64) {
+        let tags = read_tags(file, ctx, page)?;
+        if tags.seq_number == 0xFFFFFFFF {continue}    //erased
+        if !tags.is_valid() || tags.object_id() == 0 || tags.object_id() > MAX_OBJ_ID {return Ok(false)}
+        if !tags.is_header() {continue}
+
+        let header = read_header(file, ctx, page)?;
+        let name = header.name();
+        return Ok((OBJECT_TYPE_FILE..=OBJECT_TYPE_SPECIAL).contains(&header.obj_type) && header.parent_obj_id != 0
+                    && !name.is_empty() && !name.chars().any(|c| c.is_control()))
+    }
+    Ok(false)
+}
+
+pub fn is_yaffs2_file(app_ctx: &AppContext) -> Result<Option<Box<dyn Any>>, Box<dyn std::error::Error>> {
+    let file = match app_ctx.file() {Some(f) => f, None => return Ok(None)};
+    let file_size = file.metadata()?.len();
+
+    for &(chunk_size, spare_size) in LAYOUTS {
+        //images are always made of whole pages
+        if file_size == 0 || file_size % (chunk_size + spare_size) != 0 {continue}
+        for &tag_offset in TAG_OFFSETS {
+            for endian in [Endian::Little, Endian::Big] {
+                let ctx = Yaffs2Context {chunk_size, spare_size, tag_offset, endian};
+                if check_layout(file, &ctx, file_size / ctx.page_size())? {
+                    return Ok(Some(Box::new(ctx)))
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+struct DataChunk {
+    chunk_id: u32,
+    page: u64,
+    n_bytes: u32,
+}
+
+struct Yaffs2Image {
+    objects: HashMap<u32, ObjectHeader>,
+    data_chunks: HashMap<u32, Vec<DataChunk>>,
+}
+
+//chunks are never rewritten in place, the copy with the highest sequence number (or the later one in the same block) is current
+fn scan_chunks(file: &File, ctx: &Yaffs2Context) -> Result<Yaffs2Image, Box<dyn std::error::Error>> {
+    let page_count = file.metadata()?.len() / ctx.page_size();
+    let mut headers: HashMap<u32, (u32, u64)> = HashMap::new();
+    let mut data: HashMap<(u32, u32), (u32, u64, u32)> = HashMap::new();
+
+    for page in 0..page_count {
+        let tags = read_tags(file, ctx, page)?;
+        if !tags.is_valid() {continue}
+
+        if tags.is_header() {
+            let current = headers.entry(tags.object_id()).or_insert((tags.seq_number, page));
+            if (tags.seq_number, page) > *current {
+                *current = (tags.seq_number, page);
+            }
+        } else {
+            let current = data.entry((tags.obj_id, tags.chunk_id)).or_insert((tags.seq_number, page, tags.n_bytes));
+            if (tags.seq_number, page) > (current.0, current.1) {
+                *current = (tags.seq_number, page, tags.n_bytes);
+            }
+        }
+    }
+
+    let mut objects = HashMap::new();
+    for (obj_id, (_, page)) in headers {
+        objects.insert(obj_id, read_header(file, ctx, page)?);
+    }
+    let mut data_chunks: HashMap<u32, Vec<DataChunk>> = HashMap::new();
+    for ((obj_id, chunk_id), (_, page, n_bytes)) in data {
+        data_chunks.entry(obj_id).or_default().push(DataChunk {chunk_id, page, n_bytes});
+    }
+
+    Ok(Yaffs2Image {objects, data_chunks})
+}
+
+fn write_file_data(file: &File, ctx: &Yaffs2Context, image: &Yaffs2Image, obj_id: u32, size: u64, output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_path)?;
+
+    if let Some(chunks) = image.data_chunks.get(&obj_id) {
+        for chunk in chunks {
+            //chunks past the end are leftovers of a truncate
+            let offset = (chunk.chunk_id as u64 - 1) * ctx.chunk_size;
+            if offset >= size {continue}
+            let len = (chunk.n_bytes as u64).min(ctx.chunk_size).min(size - offset);
+            let chunk_data = common::read_file(file, chunk.page * ctx.page_size(), len as usize)?;
+            out_file.seek(SeekFrom::Start(offset))?;
+            out_file.write_all(&chunk_data)?;
+        }
+    }
+
+    //missing chunks are holes
+    out_file.set_len(size)?;
+    Ok(())
+}
+
+struct WalkState {
+    manifest: Manifest,
+    children: HashMap<u32, Vec<(String, u32)>>,
+    link_counts: HashMap<u32, u32>,
+    visited: HashSet<u32>,
+    file_count: u32,
+}
+
+fn walk_dir(file: &File, ctx: &Yaffs2Context, image: &Yaffs2Image, dir_id: u32, rel_path: &Path, output_dir: &Path, state: &mut WalkState) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = state.children.get(&dir_id).cloned().unwrap_or_default();
+
+    for (name, obj_id) in entries {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            println!("- Warning: invalid entry name {:?}, skipping...", name);
+            continue
+        }
+        let entry_path = rel_path.join(&name);
+        let header = &image.objects[&obj_id];
+
+        //hardlinks are separate objects pointing at the real one
+        let (data_id, object) = if header.obj_type == OBJECT_TYPE_HARDLINK {
+            match image.objects.get(&header.equiv_id) {
+                Some(target) => (header.equiv_id, target),
+                None => {
+                    println!("- Warning: hardlink {} points to missing object {}, skipping...", entry_path.display(), header.equiv_id);
+                    continue
+                }
+            }
+        } else {
+            (obj_id, header)
+        };
+
+        let file_type = match object.obj_type {
+            t if t == OBJECT_TYPE_FILE => FileType::Regular,
+            t if t == OBJECT_TYPE_SYMLINK => FileType::Symlink,
+            t if t == OBJECT_TYPE_DIRECTORY => FileType::Directory,
+            _ => FileType::from_mode(object.mode),
+        };
+
+        let mut extra = String::new();
+        let mut size = 0;
+        let output_path = output_dir.join(&entry_path);
+        match file_type {
+            FileType::Directory => {
+                fs::create_dir_all(&output_path)?;
+            }
+            FileType::Regular => {
+                size = object.file_size();
+                write_file_data(file, ctx, image, data_id, size, &output_path)?;
+                state.file_count += 1;
+            }
+            FileType::Symlink => {
+                extra.push_str(&format!(" -> {}", object.alias()));
+            }
+            FileType::CharDevice | FileType::BlockDevice => {
+                let dev = object.rdev;
+                extra.push_str(&format!(" [{},{}]", (dev & 0xFFF00) >> 8, (dev & 0xFF) | ((dev >> 12) & 0xFFF00)));
+            }
+            _ => {}
+        }
+
+        if state.link_counts.get(&data_id).copied().unwrap_or(0) > 1 && file_type != FileType::Directory {
+            extra.push_str(&format!(" [ino {}]", data_id));
+        }
+        state.manifest.add_entry(file_type, object.mode, (object.uid, object.gid), size, &entry_path, &extra);
+
+        if file_type == FileType::Directory {
+            if !state.visited.insert(data_id) {
+                println!("- Warning: directory loop at {}, skipping...", entry_path.display());
+                continue
+            }
+            walk_dir(file, ctx, image, data_id, &entry_path, output_dir, state)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn extract_yaffs2(app_ctx: &AppContext, ctx: Box<dyn Any>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = app_ctx.file().ok_or("Extractor expected file")?;
+    let ctx = ctx.downcast::<Yaffs2Context>().expect("Missing context");
+
+    println!("Scanning chunks...");
+    let image = scan_chunks(file, &ctx)?;
+
+    //objects whose parent is not a live directory (unlinked, deleted) are dropped
+    let mut children: HashMap<u32, Vec<(String, u32)>> = HashMap::new();
+    let mut link_counts: HashMap<u32, u32> = HashMap::new();
+    for (obj_id, header) in &image.objects {
+        if !(OBJECT_TYPE_FILE..=OBJECT_TYPE_SPECIAL).contains(&header.obj_type) {continue}
+        let parent_is_dir = header.parent_obj_id == ROOT_OBJ_ID || header.parent_obj_id == LOSTNFOUND_OBJ_ID
+                            || image.objects.get(&header.parent_obj_id).is_some_and(|p| p.obj_type == OBJECT_TYPE_DIRECTORY);
+        if *obj_id == ROOT_OBJ_ID || !parent_is_dir {continue}
+
+        children.entry(header.parent_obj_id).or_default().push((header.name(), *obj_id));
+        let data_id = if header.obj_type == OBJECT_TYPE_HARDLINK {header.equiv_id} else {*obj_id};
+        *link_counts.entry(data_id).or_default() += 1;
+    }
+    for entries in children.values_mut() {
+        entries.sort();
+    }
+
+    println!("Filesystem info -\nChunk size: {}\nSpare size: {}\nTags offset: {}\nEndianness: {}\nObjects: {}",
+            ctx.chunk_size, ctx.spare_size, ctx.tag_offset, if ctx.endian == Endian::Little {"Little"} else {"Big"}, image.objects.len());
+
+    let output_dir = PathBuf::from(&app_ctx.output_dir);
+    fs::create_dir_all(&output_dir)?;
+
+    let mut state = WalkState {manifest: Manifest::new(), children, link_counts, visited: HashSet::new(), file_count: 0};
+    state.visited.insert(ROOT_OBJ_ID);
+
+    //root usually has no header of its own
+    let (root_mode, root_uid, root_gid) = image.objects.get(&ROOT_OBJ_ID).map_or((0o40755, 0, 0), |h| (h.mode, h.uid, h.gid));
+    state.manifest.add_entry(FileType::Directory, root_mode, (root_uid, root_gid), 0, Path::new(""), "");
+
+    println!("\nExtracting files...");
+    walk_dir(file, &ctx, &image, ROOT_OBJ_ID, Path::new(""), &output_dir, &mut state)?;
+
+    //lost+found is a virtual directory, only shown if something ended up in it
+    if state.children.contains_key(&LOSTNFOUND_OBJ_ID) && !state.visited.contains(&LOSTNFOUND_OBJ_ID) {
+        let lost_path = Path::new("lost+found");
+        fs::create_dir_all(output_dir.join(lost_path))?;
+        state.manifest.add_entry(FileType::Directory, 0o40700, (0, 0), 0, lost_path, "");
+        state.visited.insert(LOSTNFOUND_OBJ_ID);
+        walk_dir(file, &ctx, &image, LOSTNFOUND_OBJ_ID, lost_path, &output_dir, &mut state)?;
+    }
+
+    println!("- Extracted {} files, {} entries total", state.file_count, state.manifest.entry_count());
+    state.manifest.save(&output_dir)?;
+
+    Ok(())
+}
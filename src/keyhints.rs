@@ -0,0 +1,129 @@
+// key hints - remembers which collection entry matched a file, so it is tried first for similar files next time
+// a hint is stored per format and fingerprint, which is something readable before the key is known (like the MSD firmware name)
+// the file is plain text with one hint per line, tab separated - format, fingerprint, collection, entry - and # comments
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ukf::{self, Collection};
+
+#[derive(Clone, PartialEq)]
+struct Hint {
+    format: String,
+    fingerprint: String,
+    collection: String,
+    entry: String,
+}
+
+pub struct KeyHints {
+    path: Option<PathBuf>,      //None when hints are not used
+    hints: RefCell<Vec<Hint>>,  //oldest first
+}
+
+impl KeyHints {
+    pub fn disabled() -> Self {
+        KeyHints {path: None, hints: RefCell::new(Vec::new())}
+    }
+
+    //a missing file is just an empty cache, it is created with the first hint
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let hints = if path.is_file() {parse_hints(&fs::read_to_string(path)?, path)?} else {Vec::new()};
+        Ok(KeyHints {path: Some(path.to_path_buf()), hints: RefCell::new(hints)})
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        ukf::user_config_dir().map(|dir| dir.join("key_hints"))
+    }
+
+    //entries hinted for this fingerprint come first, then ones that matched other files of the format, then the rest in file order
+    pub fn sort_collection(&self, format: &str, fingerprint: &str, collection_name: &str, collection: &Collection) -> Collection {
+        let hints = self.hints.borrow();
+        let rank = |entry: &str| {
+            let matching: Vec<&Hint> = hints.iter().filter(|h| h.format == format && h.collection == collection_name && h.entry == entry).collect();
+            if matching.iter().any(|h| h.fingerprint == fingerprint) {0} else if !matching.is_empty() {1} else {2}
+        };
+        let mut sorted = collection.clone();
+        sorted.sort_by_key(|(name, _)| rank(name));
+        sorted
+    }
+
+    pub fn remember(&self, format: &str, fingerprint: &str, collection: &str, entry: &str) {
+        let Some(path) = &self.path else {return};
+        //tabs and newlines would break the line format
+        let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+        let hint = Hint {format: clean(format), fingerprint: clean(fingerprint), collection: clean(collection), entry: clean(entry)};
+
+        let mut hints = self.hints.borrow_mut();
+        if hints.last() == Some(&hint) {
+            return
+        }
+        hints.retain(|h| !(h.format == hint.format && h.fingerprint == hint.fingerprint && h.collection == hint.collection));
+        hints.push(hint);
+        if let Err(e) = save_hints(path, &hints) {
+            println!("Warning: failed to save key hints to {}: {}", path.display(), e);
+        }
+    }
+
+    pub fn list(&self) {
+        let hints = self.hints.borrow();
+        if hints.is_empty() {
+            println!("\nNo key hints saved!");
+            return
+        }
+        println!("\nKey hints:");
+        for hint in hints.iter() {
+            println!("- {} \"{}\" - {} \"{}\"", hint.format, hint.fingerprint, hint.collection, hint.entry);
+        }
+    }
+
+    pub fn export(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        save_hints(path, &self.hints.borrow())?;
+        println!("\nExported {} hint(s) to {}", self.hints.borrow().len(), path.display());
+        Ok(())
+    }
+
+    //imported hints replace local ones for the same format, fingerprint and collection
+    pub fn import(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let imported = parse_hints(&fs::read_to_string(path)?, path)?;
+        let count = imported.len();
+        {
+            let mut hints = self.hints.borrow_mut();
+            for hint in imported {
+                hints.retain(|h| !(h.format == hint.format && h.fingerprint == hint.fingerprint && h.collection == hint.collection));
+                hints.push(hint);
+            }
+        }
+        let own_path = self.path.as_ref().ok_or("Key hints are not enabled!")?;
+        save_hints(own_path, &self.hints.borrow())?;
+        println!("\nImported {} hint(s) from {}", count, path.display());
+        Ok(())
+    }
+}
+
+fn parse_hints(text: &str, path: &Path) -> Result<Vec<Hint>, Box<dyn std::error::Error>> {
+    let mut hints = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 4 {
+            return Err(format!("{}: line {} - expected 4 tab separated fields, got {}", path.display(), i + 1, fields.len()).into());
+        }
+        hints.push(Hint {format: fields[0].to_string(), fingerprint: fields[1].to_string(), collection: fields[2].to_string(), entry: fields[3].to_string()});
+    }
+    Ok(hints)
+}
+
+fn save_hints(path: &Path, hints: &[Hint]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut text = String::from("# unixtract key hints\n# format\tfingerprint\tcollection\tentry\n");
+    for hint in hints {
+        text.push_str(&format!("{}\t{}\t{}\t{}\n", hint.format, hint.fingerprint, hint.collection, hint.entry));
+    }
+    if let Some(parent) = path.parent() && !parent.as_os_str().is_empty() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
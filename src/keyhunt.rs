@@ -0,0 +1,137 @@
+// `keyhunt` - tests candidate key material against the known plaintext of a package, to find keys that are not in the key files yet
+
+use std::fs;
+use std::path::Path;
+use sha2::{Digest, Sha256};
+
+use crate::AppContext;
+use crate::formats::get_key_hunt_registry;
+
+//returns true when the candidate decrypts the known plaintext
+type KeyCheck = Box<dyn Fn(&[u8]) -> bool>;
+
+//what a key has to decrypt for one format, built from the package by the format
+pub struct HuntTarget {
+    pub collection: &'static str,       //where a found key belongs in a key file
+    pub layouts: Vec<Vec<usize>>,       //part lengths of a candidate, like [16, 16] for key and iv
+    pub check: KeyCheck,
+}
+impl HuntTarget {
+    pub fn new(collection: &'static str, layouts: Vec<Vec<usize>>, check: impl Fn(&[u8]) -> bool + 'static) -> Self {
+        HuntTarget {collection, layouts, check: Box::new(check)}
+    }
+}
+
+pub enum CandidateSource {
+    Binary(Vec<u8>),        //every window of the file is a candidate
+    Wordlist(Vec<String>),  //keys are derived from each line
+}
+impl CandidateSource {
+    pub fn load(path: &Path, wordlist: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        if wordlist {
+            let text = fs::read_to_string(path)?;
+            Ok(CandidateSource::Wordlist(text.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect()))
+        } else {
+            Ok(CandidateSource::Binary(fs::read(path)?))
+        }
+    }
+
+    //calls found with each candidate of the given length and where it came from
+    fn for_each(&self, len: usize, align: usize, mut found: impl FnMut(&[u8], String)) {
+        match self {
+            CandidateSource::Binary(data) => {
+                if data.len() < len {return}
+                for offset in (0..=data.len() - len).step_by(align) {
+                    let window = &data[offset..offset + len];
+                    //padding and erased flash are never keys
+                    if window.iter().all(|&b| b == window[0]) {continue}
+                    found(window, format!("offset 0x{:x}", offset));
+                }
+            }
+            CandidateSource::Wordlist(words) => {
+                for (i, word) in words.iter().enumerate() {
+                    let mut derived: Vec<(Vec<u8>, &str)> = vec![(word.as_bytes().to_vec(), "as text")];
+                    if let Ok(bytes) = hex::decode(word.replace(' ', "")) {
+                        derived.push((bytes, "as hex"));
+                    }
+                    derived.push((md5::compute(word).0.to_vec(), "md5"));
+                    derived.push((Sha256::digest(word).to_vec(), "sha256"));
+
+                    for (candidate, how) in derived {
+                        if candidate.len() == len {
+                            found(&candidate, format!("line {} \"{}\" ({})", i + 1, word, how));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Hit {
+    format: &'static str,
+    collection: &'static str,
+    parts: Vec<Vec<u8>>,
+    origin: String,
+}
+
+//entry_name is used for the found keys, usually the package file name
+pub fn hunt_keys(app_ctx: &AppContext, source: &CandidateSource, entry_name: &str, only_format: Option<&str>, align: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let registry = get_key_hunt_registry();
+    if let Some(name) = only_format && !registry.iter().any(|h| h.name == name) {
+        let names: Vec<&str> = registry.iter().map(|h| h.name).collect();
+        return Err(format!("Format {} does not support key hunting, supported: {}", name, names.join(", ")).into());
+    }
+
+    //formats without a magic would accept any file, they are only tried when asked for or when nothing else matches
+    let mut targets = Vec::new();
+    for has_magic in [true, false] {
+        for hunt in registry.iter().filter(|h| h.has_magic == has_magic && only_format.is_none_or(|name| name == h.name)) {
+            match (hunt.hunt_func)(app_ctx) {
+                Ok(Some(target)) => targets.push((hunt.name, target)),
+                Ok(None) => {}
+                Err(e) => println!("Warning: key hunt for {} failed: {}", hunt.name, e),
+            }
+        }
+        if !targets.is_empty() {break}
+    }
+    if targets.is_empty() {
+        println!("\nInput format not recognized!");
+        return Ok(())
+    }
+
+    let mut hits: Vec<Hit> = Vec::new();
+    for (format, target) in &targets {
+        println!("\n- Hunting {} key ({})...", format, target.collection);
+        for layout in &target.layouts {
+            let len: usize = layout.iter().sum();
+            source.for_each(len, align, |candidate, origin| {
+                if !(target.check)(candidate) {return}
+                let mut parts = Vec::new();
+                let mut pos = 0;
+                for part_len in layout {
+                    parts.push(candidate[pos..pos + part_len].to_vec());
+                    pos += part_len;
+                }
+                if !hits.iter().any(|h| h.collection == target.collection && h.parts == parts) {
+                    println!("-- Found at {}", origin);
+                    hits.push(Hit {format, collection: target.collection, parts, origin});
+                }
+            });
+        }
+    }
+
+    if hits.is_empty() {
+        println!("\nNo key found!");
+        return Ok(())
+    }
+
+    //ready to paste into a key file
+    println!("\nFound {} key(s), add to a key file:", hits.len());
+    for hit in &hits {
+        let parts: Vec<String> = hit.parts.iter().map(|p| format!("x\"{}\"", hex::encode_upper(p))).collect();
+        println!("\n# {} key, found at {}\ncollection \"{}\": {{\n    \"{}\": {{{}}},\n}}", hit.format, hit.origin, hit.collection, entry_name, parts.join(", "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+// `keys` and `formats` subcommands - tools for checking key files and what the formats need
+
+use std::collections::HashMap;
+
+use crate::AppContext;
+use crate::formats::{GLOBAL_OPTIONS, KeySlot, get_registry, get_key_search_registry};
+use crate::ukf::KeySystem;
+
+//keys and collections declared by the formats, each name once
+fn known_slots() -> Vec<&'static KeySlot> {
+    let mut slots: Vec<&'static KeySlot> = Vec::new();
+    for format in get_registry() {
+        for slot in format.keys {
+            if !slots.iter().any(|s| s.name == slot.name && s.is_collection == slot.is_collection) {
+                slots.push(slot);
+            }
+        }
+    }
+    slots.sort_by_key(|slot| slot.name);
+    slots
+}
+
+fn part_lengths(parts: &[Vec<u8>]) -> String {
+    parts.iter().map(|p| p.len().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+pub fn list_keys(keys: &KeySystem) {
+    println!("\nKeys:");
+    for (name, parts) in keys.keys() {
+        println!("- {} - {} part(s), {} bytes", name, parts.len(), part_lengths(parts));
+        let attributes = keys.key_attributes(name);
+        if !attributes.is_empty() {
+            println!("  [{}]", attributes);
+        }
+    }
+
+    println!("\nCollections:");
+    for (name, entries) in keys.collections() {
+        println!("- {} - {} entries", name, entries.len());
+        let collection_attributes = keys.collection_attributes(name).to_string();
+        if !collection_attributes.is_empty() {
+            println!("  [{}]", collection_attributes);
+        }
+        for (i, (entry_name, parts)) in entries.iter().enumerate() {
+            println!("  - \"{}\" - {} part(s), {} bytes", entry_name, parts.len(), part_lengths(parts));
+            //only shown when the entry has its own
+            let attributes = keys.entry_attributes(name, i).to_string();
+            if attributes != collection_attributes {
+                println!("    [{}]", attributes);
+            }
+        }
+    }
+}
+
+//describes what is wrong with the part lengths, if anything
+fn check_parts(parts: &[Vec<u8>], expected: &[&[usize]]) -> Option<String> {
+    if parts.len() != expected.len() {
+        return Some(format!("has {} part(s), expected {}", parts.len(), expected.len()))
+    }
+    for (i, (part, lengths)) in parts.iter().zip(expected).enumerate() {
+        if !lengths.is_empty() && !lengths.contains(&part.len()) {
+            let lengths: Vec<String> = lengths.iter().map(|l| l.to_string()).collect();
+            return Some(format!("part {} is {} bytes, expected {}", i + 1, part.len(), lengths.join(" or ")))
+        }
+    }
+    None
+}
+
+pub fn lint_keys(keys: &KeySystem) -> Result<(), Box<dyn std::error::Error>> {
+    let mut problems: Vec<String> = keys.problems().to_vec();
+
+    let known_slots = known_slots();
+    for known in &known_slots {
+        if known.is_collection {
+            match keys.get_collection(known.name) {
+                Ok(entries) => {
+                    for (entry_name, parts) in entries.iter() {
+                        if let Some(problem) = check_parts(parts, known.parts) {
+                            problems.push(format!("{} \"{}\" {}", known.name, entry_name, problem));
+                        }
+                    }
+                }
+                Err(_) => problems.push(format!("collection {} is missing", known.name)),
+            }
+        } else {
+            match keys.get_key(known.name) {
+                Ok(parts) => {
+                    if let Some(problem) = check_parts(&parts, known.parts) {
+                        problems.push(format!("{} {}", known.name, problem));
+                    }
+                }
+                Err(_) => problems.push(format!("key {} is missing", known.name)),
+            }
+        }
+    }
+
+    //names that no format uses are most likely typos
+    for (name, _) in keys.keys() {
+        if !known_slots.iter().any(|k| !k.is_collection && k.name == name) {
+            problems.push(format!("key {} is not used by any format", name));
+        }
+    }
+    for (name, _) in keys.collections() {
+        if !known_slots.iter().any(|k| k.is_collection && k.name == name) {
+            problems.push(format!("collection {} is not used by any format", name));
+        }
+    }
+
+    //same material stored under more than one name. inside one collection it is a leftover copy,
+    //across names it can be legitimate as some keys are shared between formats
+    let mut material: HashMap<&Vec<Vec<u8>>, Vec<(&String, String)>> = HashMap::new();
+    for (name, parts) in keys.keys() {
+        material.entry(parts).or_default().push((name, name.to_string()));
+    }
+    for (name, entries) in keys.collections() {
+        for (entry_name, parts) in entries {
+            material.entry(parts).or_default().push((name, format!("{} \"{}\"", name, entry_name)));
+        }
+    }
+    let mut notes = Vec::new();
+    let mut duplicates: Vec<Vec<(&String, String)>> = material.into_values().filter(|names| names.len() > 1).collect();
+    duplicates.sort();
+    for names in duplicates {
+        let same_name = names.iter().all(|(name, _)| *name == names[0].0);
+        let labels: Vec<String> = names.into_iter().map(|(_, label)| label).collect();
+        if same_name {
+            problems.push(format!("same key material in {}", labels.join(", ")));
+        } else {
+            notes.push(format!("same key material in {}", labels.join(", ")));
+        }
+    }
+
+    println!();
+    for problem in &problems {
+        println!("- {}", problem);
+    }
+    for note in &notes {
+        println!("- Note: {}", note);
+    }
+    if problems.is_empty() {
+        println!("No problems found!");
+        Ok(())
+    } else {
+        Err(format!("{} problem(s) found", problems.len()).into())
+    }
+}
+
+//runs detection like a normal extraction, then the key search of the detected format
+pub fn which_key(app_ctx: &AppContext) -> Result<(), Box<dyn std::error::Error>> {
+    for format in get_registry().iter().filter(|f| app_ctx.should_detect(f)) {
+        match (format.detector_func)(app_ctx) {
+            Ok(Some(_)) => {
+                println!("\n{} detected!", format.name);
+                match get_key_search_registry().iter().find(|s| s.name == format.name) {
+                    Some(search) => match (search.search_func)(app_ctx)? {
+                        Some(key_name) => println!("Matched key: {}", key_name),
+                        None => println!("No matching key found!"),
+                    },
+                    None => println!("{} does not search for a key", format.name),
+                }
+                return Ok(())
+            }
+            Ok(None) => {}
+            Err(e) => println!("Warning: detector for {} failed: {}", format.name, format.explain_error(e)),
+        }
+    }
+
+    println!("\nInput format not recognized!");
+    Ok(())
+}
+
+//what each format needs, with whether the loaded keys have it. only is a format name
+pub fn list_formats(keys: &KeySystem, only: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let registry = get_registry();
+    if let Some(name) = only && !registry.iter().any(|f| f.name == name) {
+        return Err(format!("Unknown format {}!", name).into());
+    }
+
+    for format in registry.iter().filter(|f| only.is_none_or(|name| name == f.name)) {
+        println!("\n{}", format.name);
+        if format.keys.is_empty() {
+            println!("- Keys: none");
+        }
+        for slot in format.keys {
+            println!("- {}{}", slot.describe(), if slot.is_loaded(keys) {""} else {" [NOT LOADED]"});
+        }
+        for option in format.options {
+            println!("- Option {} - {}", option.usage(), option.description);
+        }
+    }
+    println!("\nGlobal options:");
+    for option in GLOBAL_OPTIONS {
+        println!("- {} - {}", option.usage(), option.description);
+    }
+    Ok(())
+}
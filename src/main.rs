@@ -1,27 +1,98 @@
 mod formats;
 mod utils;
 mod ukf;
+mod keytool;
+mod keyhunt;
+mod keyhints;
+mod options;
 
-use clap::Parser;
-use std::path::{PathBuf};
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use std::io::{self, Seek, SeekFrom};
 use std::fs::{self, File};
 use crate::formats::{Format, get_registry};
-use crate::ukf::KeySystem;
+use crate::ukf::{Collection, KeyAlgorithm, KeySystem};
+use crate::keyhints::KeyHints;
+use crate::options::Options;
 
 #[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    input_target: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(required = true)]
+    input_target: Option<String>,
     output_directory: Option<String>,
 
-    #[arg(short, long)]
-    key_file: Option<String>,
+    ///additional key files, loaded on top of the built in keys in the given order
+    #[arg(short, long, global = true)]
+    key_file: Vec<String>,
+
+    ///folder with a file per key and a subfolder per collection, loaded on top of the key files
+    #[arg(long, global = true)]
+    key_dir: Option<String>,
 
-    ///format specific options
+    ///helper program asked for keys, run with "key <NAME>" or "collection <NAME>"
+    #[arg(long, global = true)]
+    key_command: Option<String>,
+
+    ///format specific options, "name" or "name=value"
     #[arg(short, long)]
     options: Vec<String>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    ///inspect the loaded keys
+    Keys {
+        #[command(subcommand)]
+        action: KeysCommand,
+    },
+    ///list the formats with the keys and options each one uses
+    Formats {
+        ///only show this format
+        name: Option<String>,
+    },
+    ///search candidate key material for a key that decrypts the file
+    Keyhunt {
+        file: String,
+        ///binary to slide over, or a wordlist with --wordlist
+        source: String,
+        ///derive keys from each line of the source instead
+        #[arg(long)]
+        wordlist: bool,
+        ///only hunt for the key of this format
+        #[arg(long)]
+        format: Option<String>,
+        ///step between windows of a binary source
+        #[arg(long, default_value_t = 1)]
+        align: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeysCommand {
+    ///list every key and collection with the length of its parts
+    List,
+    ///check the keys for bad hex strings, wrong lengths and duplicates
+    Lint,
+    ///detect the format of a file and show which key matches it
+    Which {
+        file: String,
+    },
+    ///list the saved key hints
+    Hints,
+    ///write the saved key hints to a file, to share them
+    ExportHints {
+        file: String,
+    },
+    ///add key hints from an exported file to the saved ones
+    ImportHints {
+        file: String,
+    },
+}
+
 pub enum InputTarget {
     File(File),
     Directory(PathBuf),
@@ -31,8 +102,9 @@ pub struct AppContext<'a> {
     pub input: InputTarget,
     pub output_dir: PathBuf,
 
-    pub options: &'a Vec<String>,
+    pub options: &'a Options,
     pub keys: &'a KeySystem,
+    pub hints: &'a KeyHints,
 }
 impl<'a> AppContext<'a> {
     pub fn file(&self) -> Option<&File> {
@@ -50,20 +122,176 @@ impl<'a> AppContext<'a> {
     }
 
     pub fn has_option(&self, option: &'static str) -> bool {
-        self.options.iter().any(|o| o == option)
+        self.options.has(option)
+    }
+
+    pub fn option_value(&self, option: &'static str) -> Option<&str> {
+        self.options.value(option)
+    }
+
+    //decimal or 0x prefixed hex
+    pub fn option_number(&self, option: &'static str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        let Some(value) = self.option_value(option) else {return Ok(None)};
+        let number = match value.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => value.parse(),
+        };
+        number.map(Some).map_err(|_| format!("Option {} expects a number, got {}!", option, value).into())
+    }
+
+    //the value if it is one of the choices the format knows
+    pub fn option_choice(&self, option: &'static str, choices: &[&str]) -> Result<Option<&str>, Box<dyn std::error::Error>> {
+        match self.option_value(option) {
+            Some(value) if !choices.contains(&value) => Err(format!("Unknown {} {}, expected one of: {}", option, value, choices.join(", ")).into()),
+            value => Ok(value),
+        }
+    }
+
+    //with the format option only the named format is detected
+    pub fn should_detect(&self, format: &Format) -> bool {
+        self.option_value("format").is_none_or(|name| name == format.name)
+    }
+
+    //collection entry given with the key option, used instead of the one a format would pick
+    pub fn forced_key(&self) -> Option<&str> {
+        self.option_value("key")
+    }
+
+    fn force_key(&self, collection: Collection) -> Collection {
+        match self.forced_key() {
+            Some(entry) => collection.into_iter().filter(|(name, _)| name == entry).collect(),
+            None => collection,
+        }
+    }
+
+    //a collection for a key search, only the forced entry if there is one
+    pub fn get_collection(&self, name: &str) -> Result<Collection, Box<dyn std::error::Error>> {
+        Ok(self.force_key(self.keys.get_collection(name)?.into_owned()))
+    }
+
+    pub fn get_collection_for(&self, name: &str, algorithm: KeyAlgorithm) -> Result<Collection, Box<dyn std::error::Error>> {
+        Ok(self.force_key(self.keys.get_collection_for(name, algorithm)?.into_owned()))
+    }
+
+    //the collection with entries that matched similar files before moved to the front
+    pub fn get_hinted_collection(&self, format: &str, fingerprint: &str, name: &str) -> Result<Collection, Box<dyn std::error::Error>> {
+        Ok(self.force_key(self.hints.sort_collection(format, fingerprint, name, &*self.keys.get_collection(name)?)))
+    }
+
+    pub fn remember_key(&self, format: &str, fingerprint: &str, collection: &str, entry: &str) {
+        self.hints.remember(format, fingerprint, collection, entry);
+    }
+}
+
+// built in keys.ukf is included at compile time, then key files from the config directories and the command line are loaded on top,
+// then the key folder and environment variables. the key command is asked on lookup, before all of them
+fn load_keys(mut ks: KeySystem, args: &Args) -> Result<KeySystem, Box<dyn std::error::Error>> {
+    ks.load_str(include_str!("keys.ukf"), "built in").map_err(|e| format!("failed to parse built in key file: {e}"))?;
+    for keyfile_path in ukf::config_key_files() {
+        if keyfile_path.is_file() {
+            ks.load_file(&keyfile_path).map_err(|e| format!("failed to parse key file: {e}"))?;
+            println!("Loaded keys from {}", keyfile_path.display());
+        }
+    }
+    for keyfile_path in &args.key_file {
+        ks.load_file(Path::new(keyfile_path)).map_err(|e| format!("failed to parse key file: {e}"))?;
+        println!("Loaded keys from {}", keyfile_path);
+    }
+    if let Some(key_dir) = &args.key_dir {
+        ks.load_dir(Path::new(key_dir)).map_err(|e| format!("failed to load key folder: {e}"))?;
+        println!("Loaded keys from {}", key_dir);
+    }
+    let env_count = ks.load_env().map_err(|e| format!("failed to load keys from environment: {e}"))?;
+    if env_count > 0 {
+        println!("Loaded {} key(s) from environment", env_count);
+    }
+    if let Some(key_command) = &args.key_command {
+        ks.set_key_command(key_command);
+    }
+    Ok(ks)
+}
+
+// key hints are only used when asked for with the key_hints option
+fn load_hints(options: &Options) -> Result<KeyHints, Box<dyn std::error::Error>> {
+    if !options.has("key_hints") {
+        return Ok(KeyHints::disabled())
     }
+    saved_hints()
+}
+
+//the hint subcommands work on the saved hints whether the option is given or not
+fn saved_hints() -> Result<KeyHints, Box<dyn std::error::Error>> {
+    KeyHints::load(&KeyHints::default_path().ok_or("No config folder for key hints!")?)
+}
+
+fn run_keys_command(action: KeysCommand, args: &Args, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        KeysCommand::List => keytool::list_keys(&load_keys(KeySystem::new(), args)?),
+        //lint collects problems instead of stopping at the first one
+        KeysCommand::Lint => keytool::lint_keys(&load_keys(KeySystem::new_lenient(), args)?)?,
+        KeysCommand::Hints => saved_hints()?.list(),
+        KeysCommand::ExportHints { file } => saved_hints()?.export(Path::new(&file))?,
+        KeysCommand::ImportHints { file } => saved_hints()?.import(Path::new(&file))?,
+        KeysCommand::Which { file } => {
+            println!("Input file: {}", file);
+            let ks = load_keys(KeySystem::new(), args)?;
+            let hints = load_hints(options)?;
+            let app_ctx = AppContext {
+                input: InputTarget::File(File::open(&file)?),
+                output_dir: PathBuf::new(),
+                options,
+                keys: &ks,
+                hints: &hints,
+            };
+            keytool::which_key(&app_ctx)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_keyhunt(file: &str, source: &str, wordlist: bool, format: Option<&str>, align: usize, args: &Args, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if align == 0 {
+        return Err("Align must be at least 1!".into());
+    }
+    println!("Input file: {}\nCandidate source: {}", file, source);
+    let ks = load_keys(KeySystem::new(), args)?;
+    let hints = load_hints(options)?;
+    let source = keyhunt::CandidateSource::load(Path::new(source), wordlist)?;
+    let app_ctx = AppContext {
+        input: InputTarget::File(File::open(file)?),
+        output_dir: PathBuf::new(),
+        options,
+        keys: &ks,
+        hints: &hints,
+    };
+    //found keys are named after the file they decrypt
+    let entry_name = Path::new(file).file_stem().and_then(|s| s.to_str()).unwrap_or("found");
+    keyhunt::hunt_keys(&app_ctx, &source, entry_name, format, align)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("unixtract Firmware extractor");
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let options = Options::parse(&args.options)?;
+
+    match args.command.take() {
+        Some(Command::Keys { action }) => return run_keys_command(action, &args, &options),
+        Some(Command::Formats { name }) => return keytool::list_formats(&load_keys(KeySystem::new(), &args)?, name.as_deref()),
+        Some(Command::Keyhunt { file, source, wordlist, format, align }) => return run_keyhunt(&file, &source, wordlist, format.as_deref(), align, &args, &options),
+        None => {}
+    }
+
+    let formats: Vec<Format> = get_registry();
+    if let Some(name) = options.value("format") && !formats.iter().any(|f| f.name == name) {
+        return Err(format!("Unknown format {}!", name).into());
+    }
 
-    let target_path_str = args.input_target;
+    let target_path_str = args.input_target.take().ok_or("Missing input target!")?;
     println!("Input target: {}", target_path_str);
     let target_path = PathBuf::from(&target_path_str);
     
     let output_path_str = if args.output_directory.is_some() {
-        args.output_directory.unwrap()
+        args.output_directory.take().unwrap()
     } else {
         format!("_{}", target_path.file_name().and_then(|s| s.to_str()).unwrap())
     };
@@ -81,14 +309,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    // init key system
-    let keyfile = if let Some(keyfile_path) = args.key_file {
-        &fs::read_to_string(keyfile_path)?
-    } else {
-        //keys.ukf is included at compile time
-        include_str!("keys.ukf")
-    };
-    let ks = KeySystem::init(keyfile).map_err(|e| format!("failed to parse key file: {e}"))?;
+    let ks = load_keys(KeySystem::new(), &args)?;
+    let hints = load_hints(&options)?;
+    if let Some(entry) = options.value("key") && !ks.collections().iter().any(|(_, c)| c.iter().any(|(name, _)| name == entry)) {
+        println!("Warning: no loaded collection has an entry named {}", entry);
+    }
 
     let app_ctx;
 
@@ -97,35 +322,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         app_ctx = AppContext {
             input: InputTarget::File(file),
             output_dir: output_directory_path,
-            options: &args.options,
+            options: &options,
             keys: &ks,
+            hints: &hints,
         };
     } else if target_path.is_dir() {
         app_ctx = AppContext {
             input: InputTarget::Directory(target_path),
             output_dir: output_directory_path,
-            options: &args.options,
+            options: &options,
             keys: &ks,
+            hints: &hints,
         };
     } else {
         return Err("Invalid input path!".into());
     }
 
-    let formats: Vec<Format> = get_registry();
     println!("Loaded {} formats!", formats.len());
 
-    for format in formats {
+    for format in formats.iter().filter(|f| app_ctx.should_detect(f)) {
         match (format.detector_func)(&app_ctx) {
             Ok(result) => {
                 if let Some(ctx) = result {
                     println!("\n{} detected!", format.name);
 
+                    let unused = options.unused_by(format.options);
+                    if !unused.is_empty() {
+                        println!("Warning: {} does not use option(s) {}", format.name, unused.join(", "));
+                    }
+
+                    let missing = format.missing_keys(&ks);
+                    if !missing.is_empty() {
+                        let slots: Vec<String> = missing.iter().map(|slot| format!("\n- {}", slot.describe())).collect();
+                        return Err(format!("{} needs keys that are not loaded:{}", format.name, slots.concat()).into());
+                    }
+
                     //reset seek of the file if present
                     if let Some(mut file) = app_ctx.file() {
                         file.seek(SeekFrom::Start(0))?;
                     }
 
-                    (format.extractor_func)(&app_ctx, ctx)?;
+                    (format.extractor_func)(&app_ctx, ctx).map_err(|e| format.explain_error(e))?;
 
                     //extractor returned with no error
                     println!("\nExtraction finished! Saved extracted files to {}", output_path_str);
@@ -133,7 +370,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             Err(e) => {
-                println!("Warning: detector for {} failed: {}", format.name, e);
+                println!("Warning: detector for {} failed: {}", format.name, format.explain_error(e));
             }
         }  
     }
@@ -0,0 +1,75 @@
+// -o options - checked against the options the formats declare, so a typo is not silently ignored
+
+use crate::formats::{FormatOption, GLOBAL_OPTIONS, get_registry};
+
+//options declared by the formats and the global ones, each name once
+fn known_options() -> Vec<&'static FormatOption> {
+    let mut options: Vec<&'static FormatOption> = GLOBAL_OPTIONS.iter().collect();
+    for format in get_registry() {
+        for option in format.options {
+            if !options.iter().any(|o| o.name == option.name) {
+                options.push(option);
+            }
+        }
+    }
+    options
+}
+
+//options given on the command line, split into name and value
+pub struct Options {
+    given: Vec<(String, Option<String>)>,
+}
+
+impl Options {
+    pub fn parse(raw: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let known = known_options();
+        let mut given = Vec::new();
+        for arg in raw {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            let Some(option) = known.iter().find(|o| o.name == name) else {
+                return Err(unknown_option(name, &known).into())
+            };
+            match (option.value, value) {
+                (None, Some(_)) => return Err(format!("Option {} does not take a value!", name).into()),
+                (Some(_), None) | (Some(_), Some("")) => return Err(format!("Option {} needs a value, use {}", name, option.usage()).into()),
+                _ => {}
+            }
+            given.push((name.to_string(), value.map(|v| v.to_string())));
+        }
+        Ok(Self { given })
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.given.iter().any(|(n, _)| n == name)
+    }
+
+    //the last value wins if an option is given more than once
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.given.iter().rev().find(|(n, _)| n == name).and_then(|(_, v)| v.as_deref())
+    }
+
+    //format specific options given that the format does not declare
+    pub fn unused_by(&self, declared: &[FormatOption]) -> Vec<&str> {
+        self.given.iter()
+            .map(|(n, _)| n.as_str())
+            .filter(|n| !GLOBAL_OPTIONS.iter().chain(declared).any(|o| o.name == *n))
+            .collect()
+    }
+}
+
+//points to the options of the same format if the prefix is known
+fn unknown_option(name: &str, known: &[&FormatOption]) -> String {
+    let mut text = format!("Unknown option {}!", name);
+    if let Some((prefix, _)) = name.split_once(':') {
+        let similar: Vec<String> = known.iter().filter(|o| o.name.starts_with(&format!("{}:", prefix))).map(|o| o.usage()).collect();
+        if !similar.is_empty() {
+            text.push_str(&format!(" Options starting with {}: {}", prefix, similar.join(", ")));
+            return text
+        }
+    }
+    text.push_str(" See `unixtract formats` for the options of each format.");
+    text
+}
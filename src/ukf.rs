@@ -15,60 +15,414 @@
 
 // line comments are supported with '#' character like in python
 
+// keys, collections and collection entries can be annotated with optional attributes in brackets after the name, like -
+// key "key_name" [algorithm: "aes-128", length: "16, 16", vendor: "abc", models: "2010-2012", notes: "key, iv"]: {...}
+// length lists the byte length of each part, alternatives are separated with | (like "16|32"). collection attributes apply to all its entries
+// declared lengths are checked when loading, so a wrong key fails with its name instead of somewhere in the extractor
+
+// another file can be loaded in place with - include "path" - relative paths are resolved from the including file's folder
+
+// several files can be layered on top of each other (built in, config folder, -k files), a later key replaces an earlier one with the same name,
+// and collections are merged by entry name, so a file only needs to contain the keys it adds or changes
+// keys can also come from environment variables, a key folder or a helper command on top of the files, see providers.rs
+
 // i have also made UDL for notepad++ that makes it easier to edit and read the files. (only in dark mode version, and you scare me if you use light mode.)
 // https://gist.github.com/theubusu/0e7a6d2d73375e9453c34e8972aaeb3e
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::sync::Mutex;
+
+mod providers;
+
+pub type Collection = Vec<(String, Vec<Vec<u8>>)>;
+//a collection entry name and its first key part
+pub type NamedKey = (String, Vec<u8>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAlgorithm {
+    Aes128,
+    Aes256,
+    Des,
+    TripleDes,
+    Xor,
+    Rsa,
+    Passphrase,
+    Custom,     //vendor specific ciphers, substitution tables...
+}
+impl KeyAlgorithm {
+    const ALL: [KeyAlgorithm; 8] = [Self::Aes128, Self::Aes256, Self::Des, Self::TripleDes, Self::Xor, Self::Rsa, Self::Passphrase, Self::Custom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Aes128 => "aes-128",
+            Self::Aes256 => "aes-256",
+            Self::Des => "des",
+            Self::TripleDes => "3des",
+            Self::Xor => "xor",
+            Self::Rsa => "rsa",
+            Self::Passphrase => "passphrase",
+            Self::Custom => "custom",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name().eq_ignore_ascii_case(name))
+    }
+}
+
+//optional attributes of a key, collection or collection entry
+#[derive(Debug, Clone, Default)]
+pub struct KeyAttributes {
+    pub algorithm: Option<KeyAlgorithm>,
+    pub length: Option<Vec<Vec<usize>>>,    //allowed byte lengths of each part
+    pub vendor: Option<String>,
+    pub models: Option<String>,
+    pub notes: Option<String>,
+}
+impl KeyAttributes {
+    pub fn is_empty(&self) -> bool {
+        self.algorithm.is_none() && self.length.is_none() && self.vendor.is_none() && self.models.is_none() && self.notes.is_none()
+    }
+
+    //attributes that are not set are taken from defaults (the collection, or an earlier file)
+    fn or(&self, defaults: &KeyAttributes) -> KeyAttributes {
+        KeyAttributes {
+            algorithm: self.algorithm.or(defaults.algorithm),
+            length: self.length.clone().or_else(|| defaults.length.clone()),
+            vendor: self.vendor.clone().or_else(|| defaults.vendor.clone()),
+            models: self.models.clone().or_else(|| defaults.models.clone()),
+            notes: self.notes.clone().or_else(|| defaults.notes.clone()),
+        }
+    }
+
+    //checks the parts against the declared length
+    fn check_length(&self, parts: &[Vec<u8>]) -> Result<(), String> {
+        let Some(length) = &self.length else { return Ok(()) };
+        if parts.len() != length.len() {
+            return Err(format!("length - has {} part(s), declared {}", parts.len(), length.len()));
+        }
+        for (i, (part, allowed)) in parts.iter().zip(length).enumerate() {
+            if !allowed.contains(&part.len()) {
+                return Err(format!("length - part {} is {} bytes, declared {}", i + 1, part.len(), format_lengths(allowed)));
+            }
+        }
+        Ok(())
+    }
+}
+impl std::fmt::Display for KeyAttributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut fields = Vec::new();
+        if let Some(algorithm) = self.algorithm {fields.push(format!("algorithm: \"{}\"", algorithm.name()))}
+        if let Some(length) = &self.length {fields.push(format!("length: \"{}\"", length.iter().map(|l| format_lengths(l)).collect::<Vec<_>>().join(", ")))}
+        if let Some(vendor) = &self.vendor {fields.push(format!("vendor: \"{}\"", vendor))}
+        if let Some(models) = &self.models {fields.push(format!("models: \"{}\"", models))}
+        if let Some(notes) = &self.notes {fields.push(format!("notes: \"{}\"", notes))}
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+fn format_lengths(lengths: &[usize]) -> String {
+    lengths.iter().map(|l| l.to_string()).collect::<Vec<_>>().join("|")
+}
+
+//useful helper: gets a key part as an array, owner is the key or collection entry name used in the error
+pub fn part_as_arr<const N: usize>(owner: &str, parts: &[Vec<u8>], idx: usize) -> Result<[u8; N], Box<dyn std::error::Error>> {
+    let part = parts.get(idx).ok_or_else(|| format!("{}: part {} is missing, it has {} part(s)", owner, idx + 1, parts.len()))?;
+    part.as_slice().try_into().map_err(|_| format!("{}: part {} is {} bytes, expected {}", owner, idx + 1, part.len(), N).into())
+}
+
+//returned when a key or collection is not loaded, so callers can tell it apart from a bad key
+#[derive(Debug)]
+pub struct MissingKey {
+    pub name: String,
+    pub is_collection: bool,
+}
+impl std::fmt::Display for MissingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} not found", if self.is_collection {"collection"} else {"key"}, self.name)
+    }
+}
+impl std::error::Error for MissingKey {}
 
 // -- public definition --
 #[derive(Debug)]
 pub struct KeySystem {
     keys: HashMap<String, Vec<Vec<u8>>>,
-    collections: HashMap<String, Vec<(String, Vec<Vec<u8>>)>>,
+    collections: HashMap<String, Collection>,
+    key_attributes: HashMap<String, KeyAttributes>,
+    collection_attributes: HashMap<String, KeyAttributes>,
+    entry_attributes: HashMap<String, Vec<KeyAttributes>>,     //same order as the collection entries
+    //in lenient mode bad hex strings and duplicates are collected here instead of failing the load
+    lenient: bool,
+    problems: Vec<String>,
+    //helper program asked for keys on lookup, and what it answered for each name
+    key_command: Option<String>,
+    provided_keys: ProvidedCache<Vec<Vec<u8>>>,
+    provided_collections: ProvidedCache<Collection>,
 }
+type ProvidedCache<T> = Mutex<HashMap<String, Option<T>>>;
 impl KeySystem {
+    pub fn new() -> Self {
+        KeySystem {
+            keys: HashMap::new(),
+            collections: HashMap::new(),
+            key_attributes: HashMap::new(),
+            collection_attributes: HashMap::new(),
+            entry_attributes: HashMap::new(),
+            lenient: false,
+            problems: Vec::new(),
+            key_command: None,
+            provided_keys: Mutex::new(HashMap::new()),
+            provided_collections: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn new_lenient() -> Self {
+        KeySystem {lenient: true, ..Self::new()}
+    }
+
     pub fn init(input: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut system = Self::new();
+        system.load(input, "built in", None, &mut Vec::new())?;
+        Ok(system)
+    }
+
+    //loads keys from a string on top of the already loaded keys, source is only used in error messages
+    pub fn load_str(&mut self, input: &str, source: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.load(input, source, None, &mut Vec::new())
+    }
+
+    //layers a key file on top of the already loaded keys
+    pub fn load_file(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.load_file_inner(path, &mut Vec::new())
+    }
+
+    //include_stack holds the files currently being loaded, to catch include loops
+    fn load_file_inner(&mut self, path: &Path, include_stack: &mut Vec<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+        let canonical = fs::canonicalize(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if include_stack.contains(&canonical) {
+            return Err(format!("{}: include loop", path.display()).into());
+        }
+        let input = fs::read_to_string(&canonical).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        include_stack.push(canonical.clone());
+        let result = self.load(&input, &path.display().to_string(), canonical.parent(), include_stack);
+        include_stack.pop();
+        result.map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    fn load(&mut self, input: &str, source: &str, base_dir: Option<&Path>, include_stack: &mut Vec<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
         let mut lexer = Lexer {input, pos: 0, line: 1};
         let tokens = lexer.tokenize()?;
         //println!("{:?}", tokens);
-        let mut parser = Parser {tokens, pos: 0};
-        parser.parse()
+        let mut parser = Parser {tokens, pos: 0, lenient: self.lenient, problems: Vec::new()};
+
+        let entries = parser.parse()?;
+        self.problems.extend(parser.problems.iter().map(|p| format!("{}: {}", source, p)));
+        for entry in entries {
+            match entry {
+                Entry::Key(name, key, attributes, line) => self.insert_key(name, key, attributes, &format!("line {}", line))?,
+                Entry::Collection(name, collection_attributes, entries) => {
+                    let entries = entries.into_iter().map(|(entry_name, key, attributes, line)| (entry_name, key, attributes, format!("line {}", line))).collect();
+                    self.insert_collection(name, collection_attributes, entries)?;
+                }
+                Entry::Include(path, line) => {
+                    let path = match base_dir {
+                        Some(dir) => dir.join(path),
+                        None => PathBuf::from(path),
+                    };
+                    self.load_file_inner(&path, include_stack).map_err(|e| format!("line {} - in include {}", line, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //at is where the key came from, for errors (like a line number)
+    fn insert_key(&mut self, name: String, key: Vec<Vec<u8>>, attributes: KeyAttributes, at: &str) -> Result<(), Box<dyn std::error::Error>> {
+        //a replaced key keeps the attributes it was declared with
+        let attributes = attributes.or(&self.key_attributes.get(&name).cloned().unwrap_or_default());
+        if let Err(e) = attributes.check_length(&key) {
+            self.attribute_problem(format!("{} - key \"{}\": {}", at, name, e))?;
+        }
+        self.keys.insert(name.clone(), key);
+        self.key_attributes.insert(name, attributes);
+        Ok(())
+    }
+
+    fn insert_collection(&mut self, name: String, collection_attributes: KeyAttributes, entries: Vec<(String, Vec<Vec<u8>>, KeyAttributes, String)>) -> Result<(), Box<dyn std::error::Error>> {
+        let collection_attributes = collection_attributes.or(&self.collection_attributes.get(&name).cloned().unwrap_or_default());
+        let collection = self.collections.entry(name.clone()).or_default();
+        let all_attributes = self.entry_attributes.entry(name.clone()).or_default();
+        //only entries from earlier files are replaced, a collection may repeat names (or leave them empty) itself
+        let loaded_count = collection.len();
+        let mut problems = Vec::new();
+        for (entry_name, key, attributes, at) in entries {
+            if let Err(e) = attributes.or(&collection_attributes).check_length(&key) {
+                problems.push(format!("{} - collection \"{}\" entry \"{}\": {}", at, name, entry_name, e));
+            }
+            match collection[..loaded_count].iter().position(|(n, _)| !n.is_empty() && *n == entry_name) {
+                Some(i) => {
+                    collection[i].1 = key;
+                    all_attributes[i] = attributes.or(&all_attributes[i]);
+                }
+                None => {
+                    collection.push((entry_name, key));
+                    all_attributes.push(attributes);
+                }
+            }
+        }
+        self.collection_attributes.insert(name, collection_attributes);
+        for problem in problems {
+            self.attribute_problem(problem)?;
+        }
+        Ok(())
+    }
+
+    fn attribute_problem(&mut self, problem: String) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.lenient {
+            return Err(problem.into());
+        }
+        self.problems.push(problem);
+        Ok(())
     }
 
     //gets a raw specified key
-    pub fn get_key(&self, name: &str) -> Result<&Vec<Vec<u8>>, Box<dyn std::error::Error>> {
-        self.keys.get(name).ok_or_else(|| format!("key {name} not found").into())
+    pub fn get_key(&self, name: &str) -> Result<Cow<'_, [Vec<u8>]>, Box<dyn std::error::Error>> {
+        if let Some(key) = self.provided_key(name)? {
+            return Ok(Cow::Owned(key))
+        }
+        self.keys.get(name).map(|key| Cow::Borrowed(key.as_slice())).ok_or_else(|| MissingKey {name: name.to_string(), is_collection: false}.into())
+    }
+
+    //gets a key that is going to be used with the given algorithm, fails if it was declared for another one
+    pub fn get_key_for(&self, name: &str, algorithm: KeyAlgorithm) -> Result<Cow<'_, [Vec<u8>]>, Box<dyn std::error::Error>> {
+        let key = self.get_key(name)?;
+        check_algorithm(&format!("key {}", name), self.key_attributes.get(name), algorithm)?;
+        Ok(key)
     }
 
     //useful helper: gets a specified index in a key as an array
     pub fn get_key_as_arr<const N: usize>(&self, name: &str, idx: usize) -> Result<[u8; N], Box<dyn std::error::Error>> {
-        Ok(self.get_key(name)?.get(idx).ok_or("key index out of range")?.as_slice().try_into()?)
+        part_as_arr(&format!("key {}", name), &self.get_key(name)?, idx)
     }
 
     //useful helper: gets a double key as tuple of arrays
     pub fn get_double_key_as_arr<const N1: usize, const N2: usize>(&self, name: &str) -> Result<([u8; N1], [u8; N2]), Box<dyn std::error::Error>> {
         let key = self.get_key(name)?;
-        if key.len() != 2 {return Err(format!("key {} expected 2 parts, got {}", name, key.len()).into())};
-        Ok((key[0].as_slice().try_into()?, key[1].as_slice().try_into()?))
+        let owner = format!("key {}", name);
+        if key.len() != 2 {return Err(format!("{}: expected 2 parts, got {}", owner, key.len()).into())};
+        Ok((part_as_arr(&owner, &key, 0)?, part_as_arr(&owner, &key, 1)?))
     }
 
     //gets a raw specified collection
-    pub fn get_collection(&self, name: &str) -> Result<&Vec<(String, Vec<Vec<u8>>)>, Box<dyn std::error::Error>> {
-        self.collections.get(name).ok_or_else(|| format!("collection {name} not found").into())
+    pub fn get_collection(&self, name: &str) -> Result<Cow<'_, Collection>, Box<dyn std::error::Error>> {
+        if let Some(collection) = self.provided_collection(name)? {
+            return Ok(Cow::Owned(collection))
+        }
+        self.collections.get(name).map(Cow::Borrowed).ok_or_else(|| MissingKey {name: name.to_string(), is_collection: true}.into())
+    }
+
+    //gets a collection that is going to be used with the given algorithm, fails if it was declared for another one
+    pub fn get_collection_for(&self, name: &str, algorithm: KeyAlgorithm) -> Result<Cow<'_, Collection>, Box<dyn std::error::Error>> {
+        let collection = self.get_collection(name)?;
+        check_algorithm(&format!("collection {}", name), self.collection_attributes.get(name), algorithm)?;
+        Ok(collection)
+    }
+
+    pub fn key_attributes(&self, name: &str) -> KeyAttributes {
+        self.key_attributes.get(name).cloned().unwrap_or_default()
+    }
+
+    pub fn collection_attributes(&self, name: &str) -> KeyAttributes {
+        self.collection_attributes.get(name).cloned().unwrap_or_default()
+    }
+
+    //attributes of an entry, including the ones it gets from its collection
+    pub fn entry_attributes(&self, collection: &str, index: usize) -> KeyAttributes {
+        let entry = self.entry_attributes.get(collection).and_then(|a| a.get(index)).cloned().unwrap_or_default();
+        entry.or(&self.collection_attributes(collection))
+    }
+
+    //all loaded keys, sorted by name
+    pub fn keys(&self) -> Vec<(&String, &Vec<Vec<u8>>)> {
+        let mut keys: Vec<_> = self.keys.iter().collect();
+        keys.sort_by_key(|(name, _)| *name);
+        keys
+    }
+
+    //all loaded collections, sorted by name
+    pub fn collections(&self) -> Vec<(&String, &Collection)> {
+        let mut collections: Vec<_> = self.collections.iter().collect();
+        collections.sort_by_key(|(name, _)| *name);
+        collections
+    }
+
+    //problems found while loading in lenient mode
+    pub fn problems(&self) -> &[String] {
+        &self.problems
     }
 }
 
+fn check_algorithm(owner: &str, attributes: Option<&KeyAttributes>, algorithm: KeyAlgorithm) -> Result<(), Box<dyn std::error::Error>> {
+    match attributes.and_then(|a| a.algorithm) {
+        Some(declared) if declared != algorithm => Err(format!("{}: algorithm - declared {}, used as {}", owner, declared.name(), algorithm.name()).into()),
+        _ => Ok(()),
+    }
+}
+
+//the user's own config folder, also used for other files like the key hints
+pub fn user_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var("APPDATA").ok().map(|app_data| Path::new(&app_data).join("unixtract"))
+    } else if let Ok(config_home) = std::env::var("XDG_CONFIG_HOME") && !config_home.is_empty() {
+        Some(Path::new(&config_home).join("unixtract"))
+    } else {
+        std::env::var("HOME").ok().map(|home| Path::new(&home).join(".config").join("unixtract"))
+    }
+}
+
+//key files layered on top of the built in keys when present, system wide first, then the user's
+pub fn config_key_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if cfg!(windows) {
+        if let Ok(program_data) = std::env::var("PROGRAMDATA") {
+            files.push(Path::new(&program_data).join("unixtract").join("keys.ukf"));
+        }
+    } else {
+        files.push(PathBuf::from("/etc/unixtract/keys.ukf"));
+    }
+    if let Some(dir) = user_config_dir() {
+        files.push(dir.join("keys.ukf"));
+    }
+    files
+}
+
+// parsed entries, applied to the key system in order
+enum Entry {
+    Key(String, Vec<Vec<u8>>, KeyAttributes, usize),
+    Collection(String, KeyAttributes, Vec<(String, Vec<Vec<u8>>, KeyAttributes, usize)>),
+    Include(String, usize),
+}
+
 // tokens
 #[derive(Debug,PartialEq, Clone)]
 enum Token {
     Key,            // key entry marker
     Collection,     // collection entry marker
+    Include,        // include directive
+    Ident(String),  // attribute name
     HexStr(String), // x"" hexadecimal string
     Str(String),    // ""  quoted string
     Colon,          // :
     Comma,          // ,
     OpenBrace,      // {
     CloseBrace,     // }
+    OpenBracket,    // [
+    CloseBracket,   // ]
 }
 
 // lexer
@@ -121,6 +475,8 @@ impl<'a> Lexer<'a> {
                 Some(',') => { self.advance(); tokens.push((Token::Comma, self.line)); }
                 Some('{') => { self.advance(); tokens.push((Token::OpenBrace, self.line)); }
                 Some('}') => { self.advance(); tokens.push((Token::CloseBrace, self.line)); }
+                Some('[') => { self.advance(); tokens.push((Token::OpenBracket, self.line)); }
+                Some(']') => { self.advance(); tokens.push((Token::CloseBracket, self.line)); }
                 Some('x') => {  //hex string start
                     self.advance(); //skip x
                     if self.next() != Some('"') {
@@ -135,7 +491,7 @@ impl<'a> Lexer<'a> {
                     let string = self.read_string()?;
                     tokens.push((Token::Str(string), self.line));
                 }
-                Some(c) if c.is_alphabetic() || c == '_' => {   //entry marker
+                Some(c) if c.is_alphabetic() || c == '_' => {   //entry marker or attribute name
                     let mut entry = String::new();
                     while let Some(c) = self.next() {
                         if c.is_alphanumeric() || c == '_' {
@@ -148,7 +504,8 @@ impl<'a> Lexer<'a> {
                     let entry_token = match entry.as_str() {
                         "key"        => Token::Key,
                         "collection" => Token::Collection,
-                        unk => return Err(format!("line {} - unknown entry type {}", self.line, unk).into())
+                        "include"    => Token::Include,
+                        other        => Token::Ident(other.to_string()),
                     };
                     tokens.push((entry_token, self.line));
                 }
@@ -163,6 +520,8 @@ impl<'a> Lexer<'a> {
 struct Parser {
     tokens: Vec<(Token, usize)>,
     pos: usize,
+    lenient: bool,
+    problems: Vec<String>,
 }
 impl Parser {
     //helper
@@ -198,6 +557,54 @@ impl Parser {
         }
     }
 
+    //parse optional attributes after a name (like [algorithm: "aes-128", length: "16"]), owner is used in errors
+    fn parse_attributes(&mut self, owner: &str) -> Result<KeyAttributes, Box<dyn std::error::Error>> {
+        let mut attributes = KeyAttributes::default();
+        if !matches!(self.next(), Some((Token::OpenBracket, _))) {
+            return Ok(attributes)
+        }
+        self.advance();
+        loop {
+            let (field, line) = match self.advance() {
+                Some((Token::CloseBracket, _)) => break,
+                Some((Token::Ident(field), line)) => (field.clone(), *line),
+                Some((got, line)) => return Err(format!("line {} - expected attribute name or closing bracket for {}, got {:?}", line, owner, got).into()),
+                None => return Err(format!("unexpected end of file in attributes of {}", owner).into()),
+            };
+            self.expect_next(&Token::Colon, "after attribute name")?;
+            let value = self.expect_next_str_get("attribute value")?;
+            let error = |e: &str| format!("line {} - {}: {} - {}", line, owner, field, e);
+
+            let duplicate = match field.as_str() {
+                "algorithm" => {
+                    let algorithm = KeyAlgorithm::from_name(&value).ok_or_else(|| error(&format!("unknown algorithm \"{}\", expected one of {}",
+                        value, KeyAlgorithm::ALL.map(|a| a.name()).join(", "))))?;
+                    attributes.algorithm.replace(algorithm).is_some()
+                }
+                "length" => {
+                    let length = value.split(',')
+                        .map(|part| part.split('|').map(|l| l.trim().parse::<usize>()).collect::<Result<Vec<_>, _>>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| error(&format!("invalid length \"{}\", expected byte lengths of each part like \"16, 16|32\"", value)))?;
+                    attributes.length.replace(length).is_some()
+                }
+                "vendor" => attributes.vendor.replace(value).is_some(),
+                "models" => attributes.models.replace(value).is_some(),
+                "notes" => attributes.notes.replace(value).is_some(),
+                _ => return Err(error("unknown attribute, expected algorithm, length, vendor, models or notes").into()),
+            };
+            if duplicate {
+                return Err(error("attribute given more than once").into());
+            }
+
+            //optional comma at end
+            if matches!(self.next(), Some((Token::Comma, _))) {
+                self.advance();
+            }
+        }
+        Ok(attributes)
+    }
+
     //parse key block (like{"ABCD","0123",..})
     fn parse_key_block(&mut self) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
         self.expect_next(&Token::OpenBrace, "opening key block")?;
@@ -223,6 +630,7 @@ impl Parser {
 
                     match hex::decode(&clean) {
                         Ok(bytes) => keys.push(bytes),
+                        Err(e) if self.lenient => self.problems.push(format!("line {} - hex string decode error: {}", line, e)),
                         Err(e) => {return Err(format!("line {} - hex string decode error: {}", line, e).into())}
                     }
 
@@ -240,41 +648,48 @@ impl Parser {
         Ok(keys)
     }
 
-    fn parse(&mut self) -> Result<KeySystem, Box<dyn std::error::Error>> {
-        let mut system = KeySystem {
-            keys: HashMap::new(),
-            collections: HashMap::new(),
-        };
+    fn parse(&mut self) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+        //duplicate keys inside one file are most likely a mistake, replacing is only done across files
+        let mut key_names = HashSet::new();
         while let Some((token, line)) = self.next().cloned() {
             match token {
                 Token::Key => {
                     //parse key entry
                     self.advance();
                     let name = self.expect_next_str_get("key name")?;
+                    let attributes = self.parse_attributes(&format!("key \"{}\"", name))?;
                     self.expect_next(&Token::Colon, "after key name")?;
 
                     let keys = self.parse_key_block()?;
-                    if system.keys.insert(name.clone(), keys).is_some() {
-                        return Err(format!("line {} - duplicate key {}", line, name).into());
+                    if !key_names.insert(name.clone()) {
+                        if !self.lenient {
+                            return Err(format!("line {} - duplicate key {}", line, name).into());
+                        }
+                        self.problems.push(format!("line {} - duplicate key {}", line, name));
                     }
+                    entries.push(Entry::Key(name, keys, attributes, line));
                 }
                 Token::Collection => {
                     //parse collection entry
                     self.advance();
                     let name = self.expect_next_str_get("collection name")?;
+                    let attributes = self.parse_attributes(&format!("collection \"{}\"", name))?;
                     self.expect_next(&Token::Colon, "after collection name")?;
                     self.expect_next(&Token::OpenBrace, "opening collection")?;
 
-                    let mut entries = Vec::new();
+                    let mut collection_entries = Vec::new();
                     loop {
                         match self.next() {
                             Some((Token::CloseBrace, _)) => {self.advance(); break; }
-                            Some((Token::Str(_), _)) => {
+                            Some((Token::Str(_), entry_line)) => {
+                                let entry_line = *entry_line;
                                 let sub_name = self.expect_next_str_get("collection entry name")?;
+                                let entry_attributes = self.parse_attributes(&format!("collection \"{}\" entry \"{}\"", name, sub_name))?;
                                 self.expect_next(&Token::Colon, "after collection entry name")?;
                                 //parse entry key block
                                 let keys = self.parse_key_block()?;
-                                entries.push((sub_name, keys));
+                                collection_entries.push((sub_name, keys, entry_attributes, entry_line));
                                 //optional comma at end
                                 if matches!(self.next(), Some((Token::Comma, _))) {
                                     self.advance();
@@ -286,13 +701,17 @@ impl Parser {
                             None => return Err("unexpected end of file in collection".into()),
                         }
                     }
-                    if system.collections.insert(name.clone(), entries).is_some() {
-                        return Err(format!("line {} - duplicate collection {}", line, name).into());
-                    }
+                    entries.push(Entry::Collection(name, attributes, collection_entries));
                 }
+                Token::Include => {
+                    self.advance();
+                    let path = self.expect_next_str_get("include path")?;
+                    entries.push(Entry::Include(path, line));
+                }
+                Token::Ident(unk) => return Err(format!("line {} - unknown entry type {}", line, unk).into()),
                 _ => return Err(format!("line {} - expected entry type marker, got {:?}", line, token).into()),
             }
         }
-        Ok(system)
+        Ok(entries)
     }
 }
@@ -0,0 +1,173 @@
+// key providers - keys that come from somewhere else than a key file, for keys that cannot be kept in a shared one
+// environment - UNIXTRACT_KEY_<name> holds a key and UNIXTRACT_COLLECTION_<name> a collection
+// directory - a file per key named after it, and a folder per collection with a file per entry
+// command - a helper program run with "key <name>" or "collection <name>" that prints the value, or nothing when it does not have it
+// a key is written as hex parts separated by commas or new lines, a collection as name=parts entries separated by ; or new lines
+// environment and directory keys are loaded on top of the key files, the command is asked on lookup and its answer is kept for the run
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::{Collection, KeyAttributes, KeySystem};
+
+pub const ENV_KEY_PREFIX: &str = "UNIXTRACT_KEY_";
+pub const ENV_COLLECTION_PREFIX: &str = "UNIXTRACT_COLLECTION_";
+
+fn parse_key_text(text: &str) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut parts = Vec::new();
+    for line in text.lines() {
+        //# comments like in key files
+        let line = line.split('#').next().unwrap_or_default();
+        for part in line.split(',') {
+            let clean: String = part.chars().filter(|c| !c.is_whitespace()).collect();
+            if !clean.is_empty() {
+                parts.push(hex::decode(&clean).map_err(|e| format!("hex string decode error: {}", e))?);
+            }
+        }
+    }
+    if parts.is_empty() {
+        return Err("no key parts".into());
+    }
+    Ok(parts)
+}
+
+fn parse_collection_text(text: &str) -> Result<Collection, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    for entry in text.lines().flat_map(|line| line.split('#').next().unwrap_or_default().split(';')) {
+        if entry.trim().is_empty() {
+            continue
+        }
+        let (name, key) = entry.split_once('=').ok_or_else(|| format!("expected name=value entry, got \"{}\"", entry.trim()))?;
+        let key = parse_key_text(key).map_err(|e| format!("entry \"{}\": {}", name.trim(), e))?;
+        entries.push((name.trim().to_string(), key));
+    }
+    Ok(entries)
+}
+
+fn with_attributes(entries: Collection, at: &str) -> Vec<(String, Vec<Vec<u8>>, KeyAttributes, String)> {
+    entries.into_iter().map(|(name, key)| (name, key, KeyAttributes::default(), at.to_string())).collect()
+}
+
+impl KeySystem {
+    //returns the number of variables loaded
+    pub fn load_env(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut vars: Vec<(String, String)> = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(name, _)| name.starts_with(ENV_KEY_PREFIX) || name.starts_with(ENV_COLLECTION_PREFIX))
+            .collect();
+        vars.sort();
+
+        for (var, value) in &vars {
+            if let Some(name) = var.strip_prefix(ENV_KEY_PREFIX) {
+                let key = parse_key_text(value).map_err(|e| format!("{}: {}", var, e))?;
+                self.insert_key(name.to_string(), key, KeyAttributes::default(), var)?;
+            } else if let Some(name) = var.strip_prefix(ENV_COLLECTION_PREFIX) {
+                let entries = parse_collection_text(value).map_err(|e| format!("{}: {}", var, e))?;
+                self.insert_collection(name.to_string(), KeyAttributes::default(), with_attributes(entries, var))?;
+            }
+        }
+        Ok(vars.len())
+    }
+
+    //hidden files are skipped, entries of a collection are loaded in file name order
+    pub fn load_dir(&mut self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        for path in sorted_dir_entries(dir)? {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let at = path.display().to_string();
+            if path.is_dir() {
+                let mut entries = Vec::new();
+                for entry_path in sorted_dir_entries(&path)? {
+                    let entry_name = entry_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    let key = parse_key_text(&fs::read_to_string(&entry_path)?).map_err(|e| format!("{}: {}", entry_path.display(), e))?;
+                    entries.push((entry_name, key));
+                }
+                self.insert_collection(name, KeyAttributes::default(), with_attributes(entries, &at))?;
+            } else {
+                let key = parse_key_text(&fs::read_to_string(&path)?).map_err(|e| format!("{}: {}", at, e))?;
+                self.insert_key(name, key, KeyAttributes::default(), &at)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_key_command(&mut self, program: &str) {
+        self.key_command = Some(program.to_string());
+    }
+
+    //None when there is no command or it does not have the key
+    fn run_key_command(&self, kind: &str, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(program) = &self.key_command else {return Ok(None)};
+        let output = Command::new(program).arg(kind).arg(name).stderr(Stdio::inherit()).output()
+            .map_err(|e| format!("key command {}: {}", program, e))?;
+        if !output.status.success() {
+            return Ok(None)
+        }
+        let text = String::from_utf8(output.stdout).map_err(|_| format!("key command {}: output for {} {} is not text", program, kind, name))?;
+        Ok(if text.trim().is_empty() {None} else {Some(text)})
+    }
+
+    //answers are cached per name, so the command runs once for each
+    pub(super) fn provided_key(&self, name: &str) -> Result<Option<Vec<Vec<u8>>>, Box<dyn std::error::Error>> {
+        if self.key_command.is_none() {
+            return Ok(None)
+        }
+        let mut provided = self.provided_keys.lock().unwrap();
+        if let Some(key) = provided.get(name) {
+            return Ok(key.clone())
+        }
+
+        let key = match self.run_key_command("key", name)? {
+            Some(text) => {
+                let key = parse_key_text(&text).map_err(|e| format!("key command - key \"{}\": {}", name, e))?;
+                self.key_attributes(name).check_length(&key).map_err(|e| format!("key command - key \"{}\": {}", name, e))?;
+                Some(key)
+            }
+            None => None,
+        };
+        provided.insert(name.to_string(), key.clone());
+        Ok(key)
+    }
+
+    //entries from the command are merged into the loaded collection by name
+    pub(super) fn provided_collection(&self, name: &str) -> Result<Option<Collection>, Box<dyn std::error::Error>> {
+        if self.key_command.is_none() {
+            return Ok(None)
+        }
+        let mut provided = self.provided_collections.lock().unwrap();
+        if let Some(collection) = provided.get(name) {
+            return Ok(collection.clone())
+        }
+
+        let collection = match self.run_key_command("collection", name)? {
+            Some(text) => {
+                let entries = parse_collection_text(&text).map_err(|e| format!("key command - collection \"{}\": {}", name, e))?;
+                let mut merged = self.collections.get(name).cloned().unwrap_or_default();
+                for (entry_name, key) in entries {
+                    self.collection_attributes(name).check_length(&key)
+                        .map_err(|e| format!("key command - collection \"{}\" entry \"{}\": {}", name, entry_name, e))?;
+                    match merged.iter().position(|(n, _)| !n.is_empty() && *n == entry_name) {
+                        Some(i) => merged[i].1 = key,
+                        None => merged.push((entry_name, key)),
+                    }
+                }
+                Some(merged)
+            }
+            None => None,
+        };
+        provided.insert(name.to_string(), collection.clone());
+        Ok(collection)
+    }
+}
+
+fn sorted_dir_entries(dir: &Path) -> Result<Vec<std::path::PathBuf>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+        let path = entry?.path();
+        if !path.file_name().unwrap_or_default().to_string_lossy().starts_with('.') {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
@@ -3,4 +3,10 @@ pub mod aes;
 pub mod lzop;
 pub mod sparse;
 pub mod compression;
-pub mod global;
\ No newline at end of file
+pub mod global;
+pub mod manifest;
+pub mod fdt;
+pub mod cpio;
+pub mod keytrial;
+pub mod signature;
+pub mod pana_parts;
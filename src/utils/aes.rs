@@ -1,4 +1,4 @@
-use aes::{Aes128, Aes256};
+use aes::{Aes128, Aes256, cipher::BlockEncrypt};
 
 use cbc::{Decryptor, cipher::{block_padding::Pkcs7, block_padding::NoPadding, BlockDecryptMut, KeyIvInit}};
 
@@ -65,4 +65,18 @@ pub fn decrypt_aes256_ecb(encrypted_data: &[u8], key: &[u8; 32]) -> Result<Vec<u
     }
 
     Ok(buffer)
-}
\ No newline at end of file
+}
+
+//the counter is the iv as a big endian number, one step per block
+pub fn decrypt_aes128_ctr(encrypted_data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> Vec<u8> {
+    let cipher = Aes128::new(key.into());
+    let counter = u128::from_be_bytes(*iv);
+    let mut buffer = encrypted_data.to_vec();
+    for (i, chunk) in buffer.chunks_mut(16).enumerate() {
+        let mut keystream = GenericArray::from(counter.wrapping_add(i as u128).to_be_bytes());
+        cipher.encrypt_block(&mut keystream);
+        chunk.iter_mut().zip(keystream.iter()).for_each(|(b, k)| *b ^= k);
+    }
+
+    buffer
+}
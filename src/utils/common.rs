@@ -20,4 +20,27 @@ pub fn read_exact<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
 pub fn string_from_bytes(buf: &[u8]) -> String {
     let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
     String::from_utf8_lossy(&buf[..end]).to_string()
-}
\ No newline at end of file
+}
+
+//names read from an image, reduced to characters that are safe in a file name
+pub fn output_name(name: &str) -> String {
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {c} else {'_'}).collect();
+    if name.trim_matches('_').is_empty() {"image".to_string()} else {name}
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut adler = simd_adler32::Adler32::new();
+    adler.write(data);
+    adler.finish()
+}
+
+//plain sum of the bytes, some formats use it as a checksum
+pub fn byte_sum(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |sum, &b| sum.wrapping_add(b as u32))
+}
@@ -57,4 +57,36 @@ pub fn decompress_zstd(compressed: &[u8]) -> io::Result<Vec<u8>> {
     let mut output = Vec::new();
     decoder.read_to_end(&mut output)?;
     Ok(output)
-}
\ No newline at end of file
+}
+
+//lz4 frame format (magic 04 22 4D 18), decompress_lz4 is for raw blocks
+pub fn decompress_lz4_frame(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoder = lz4::Decoder::new(compressed)?;
+    let mut output = Vec::new();
+    decoder.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+//legacy lz4 frame (magic 02 21 4C 18) as written by `lz4 -l`, used for Android ramdisks
+pub fn decompress_lz4_legacy(compressed: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    const LEGACY_MAGIC: u32 = 0x184C2102;
+    const MAX_BLOCK_SIZE: i32 = 8 * 1024 * 1024;
+
+    let mut output = Vec::new();
+    let mut pos = 0;
+    //blocks prefixed by their compressed size, concatenated streams repeat the magic
+    while pos + 4 <= compressed.len() {
+        let block_size = u32::from_le_bytes(compressed[pos..pos + 4].try_into()?);
+        pos += 4;
+        if block_size == LEGACY_MAGIC {continue}
+        if block_size == 0 || pos + block_size as usize > compressed.len() {break}
+
+        output.extend(decompress(&compressed[pos..pos + block_size as usize], Some(MAX_BLOCK_SIZE))?);
+        pos += block_size as usize;
+    }
+
+    if output.is_empty() {
+        return Err("No lz4 blocks found!".into());
+    }
+    Ok(output)
+}
@@ -0,0 +1,107 @@
+use std::path::{Component, Path};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use crate::utils::manifest::{Manifest, FileType};
+
+//"newc" cpio archives, as used by Linux initramfs and Android ramdisks
+
+static NEWC_MAGIC: &[u8; 6] = b"070701";
+static CRC_MAGIC: &[u8; 6] = b"070702";
+static HEADER_SIZE: usize = 110;
+static TRAILER_NAME: &str = "TRAILER!!!";
+
+pub fn is_cpio(data: &[u8]) -> bool {
+    data.len() >= HEADER_SIZE && (&data[0..6] == NEWC_MAGIC || &data[0..6] == CRC_MAGIC)
+}
+
+fn hex_field(header: &[u8], index: usize) -> Result<u32, Box<dyn std::error::Error>> {
+    let field = std::str::from_utf8(&header[6 + index * 8..6 + index * 8 + 8])?;
+    Ok(u32::from_str_radix(field, 16)?)
+}
+
+//only plain relative paths may be written, anything escaping the output folder is refused
+fn safe_path(name: &str) -> Option<&Path> {
+    let path = Path::new(name.trim_start_matches("./"));
+    if path.as_os_str().is_empty() || path.components().any(|c| !matches!(c, Component::Normal(_))) {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+//extracts all files and records symlinks, device nodes and ownership in _manifest.txt, returns the number of entries
+pub fn extract_cpio(data: &[u8], output_dir: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    fs::create_dir_all(output_dir)?;
+    let mut manifest = Manifest::new();
+    let mut pos = 0;
+
+    loop {
+        //concatenated archives are padded, skip to the next header
+        while pos < data.len() && data[pos] == 0 {
+            pos += 1;
+        }
+        if pos + HEADER_SIZE > data.len() {break}
+        let header = &data[pos..pos + HEADER_SIZE];
+        if &header[0..6] != NEWC_MAGIC && &header[0..6] != CRC_MAGIC {
+            println!("- Warning: unexpected data at 0x{:x} in cpio archive, stopping...", pos);
+            break
+        }
+
+        let mode = hex_field(header, 1)?;
+        let uid = hex_field(header, 2)?;
+        let gid = hex_field(header, 3)?;
+        let nlink = hex_field(header, 4)?;
+        let file_size = hex_field(header, 6)? as usize;
+        let rdev_major = hex_field(header, 9)?;
+        let rdev_minor = hex_field(header, 10)?;
+        let name_size = hex_field(header, 11)? as usize;
+
+        let name_start = pos + HEADER_SIZE;
+        let name_bytes = data.get(name_start..name_start + name_size).ok_or("cpio entry name out of bounds!")?;
+        let name = crate::utils::common::string_from_bytes(name_bytes);
+        let data_start = (name_start + name_size + 3) & !3;
+        let file_data = data.get(data_start..data_start + file_size).ok_or("cpio entry data out of bounds!")?;
+        pos = (data_start + file_size + 3) & !3;
+
+        if name == TRAILER_NAME {continue}
+        if name == "." {continue}
+        let rel_path = match safe_path(&name) {
+            Some(p) => p,
+            None => {
+                println!("- Warning: unsafe path {:?} in cpio archive, skipping...", name);
+                continue
+            }
+        };
+
+        let file_type = FileType::from_mode(mode);
+        let output_path = output_dir.join(rel_path);
+        let mut extra = String::new();
+        match file_type {
+            FileType::Directory => {
+                fs::create_dir_all(&output_path)?;
+            }
+            FileType::Regular => {
+                if let Some(parent) = output_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(&output_path)?;
+                out_file.write_all(file_data)?;
+            }
+            FileType::Symlink => {
+                extra.push_str(&format!(" -> {}", String::from_utf8_lossy(file_data)));
+            }
+            FileType::CharDevice | FileType::BlockDevice => {
+                extra.push_str(&format!(" [{},{}]", rdev_major, rdev_minor));
+            }
+            _ => {}
+        }
+        if nlink > 1 && file_type != FileType::Directory {
+            extra.push_str(&format!(" [ino {}]", hex_field(header, 0)?));
+        }
+        manifest.add_entry(file_type, mode, (uid, gid), file_size as u64, rel_path, &extra);
+    }
+
+    manifest.save(output_dir)?;
+    Ok(manifest.entry_count())
+}
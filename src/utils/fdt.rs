@@ -0,0 +1,195 @@
+use binrw::{BinRead, BinReaderExt};
+use std::io::Cursor;
+
+//flattened device tree, used by DTBs and U-Boot FIT images
+
+pub static FDT_MAGIC: &[u8; 4] = b"\xD0\x0D\xFE\xED";
+
+static FDT_BEGIN_NODE: u32 = 0x1;
+static FDT_END_NODE: u32 = 0x2;
+static FDT_PROP: u32 = 0x3;
+static FDT_NOP: u32 = 0x4;
+static FDT_END: u32 = 0x9;
+
+#[derive(BinRead)]
+pub struct FdtHeader {
+    _magic: u32,
+    pub total_size: u32,
+    pub off_dt_struct: u32,
+    pub off_dt_strings: u32,
+    pub off_mem_rsvmap: u32,
+    _version: u32,
+    pub last_comp_version: u32,
+    pub boot_cpuid_phys: u32,
+    _size_dt_strings: u32,
+    _size_dt_struct: u32,
+}
+
+pub struct FdtProp {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+pub struct FdtNode {
+    pub name: String,
+    pub props: Vec<FdtProp>,
+    pub children: Vec<FdtNode>,
+}
+impl FdtNode {
+    pub fn prop(&self, name: &str) -> Option<&[u8]> {
+        self.props.iter().find(|p| p.name == name).map(|p| p.value.as_slice())
+    }
+    pub fn prop_str(&self, name: &str) -> Option<String> {
+        self.prop(name).map(crate::utils::common::string_from_bytes)
+    }
+    pub fn prop_u32(&self, name: &str) -> Option<u32> {
+        let value = self.prop(name)?;
+        Some(u32::from_be_bytes(value.get(0..4)?.try_into().ok()?))
+    }
+    //addresses are one or two cells
+    pub fn prop_addr(&self, name: &str) -> Option<u64> {
+        let value = self.prop(name)?;
+        match value.len() {
+            4 => Some(u32::from_be_bytes(value.try_into().ok()?) as u64),
+            8 => Some(u64::from_be_bytes(value.try_into().ok()?)),
+            _ => None,
+        }
+    }
+    pub fn child(&self, name: &str) -> Option<&FdtNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+pub fn is_fdt(data: &[u8]) -> bool {
+    data.len() >= 40 && &data[0..4] == FDT_MAGIC
+}
+
+fn read_be_u32(data: &[u8], offset: usize) -> Result<u32, Box<dyn std::error::Error>> {
+    let bytes = data.get(offset..offset + 4).ok_or("Unexpected end of device tree structure!")?;
+    Ok(u32::from_be_bytes(bytes.try_into()?))
+}
+
+//returns the string and its length in bytes without the terminator
+fn read_cstr(data: &[u8], offset: usize) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    let bytes = data.get(offset..).ok_or("Device tree string out of bounds!")?;
+    let len = bytes.iter().position(|&b| b == 0).ok_or("Unterminated device tree string!")?;
+    Ok((String::from_utf8_lossy(&bytes[..len]).to_string(), len))
+}
+
+pub fn parse_fdt(data: &[u8]) -> Result<(FdtHeader, FdtNode), Box<dyn std::error::Error>> {
+    if !is_fdt(data) {
+        return Err("Invalid device tree magic!".into());
+    }
+    let header: FdtHeader = Cursor::new(data).read_be()?;
+    if header.last_comp_version > 17 {
+        return Err(format!("Unsupported device tree version {}!", header.last_comp_version).into());
+    }
+
+    let strings = data.get(header.off_dt_strings as usize..).ok_or("Device tree strings out of bounds!")?;
+    let mut pos = header.off_dt_struct as usize;
+
+    //nodes still open, a closed node is added to its parent
+    let mut stack: Vec<FdtNode> = Vec::new();
+    let mut root = None;
+    loop {
+        let token = read_be_u32(data, pos)?;
+        pos += 4;
+
+        if token == FDT_BEGIN_NODE {
+            let (name, name_len) = read_cstr(data, pos)?;
+            pos = (pos + name_len + 1 + 3) & !3;
+            stack.push(FdtNode {name, props: Vec::new(), children: Vec::new()});
+        } else if token == FDT_END_NODE {
+            let node = stack.pop().ok_or("Unbalanced device tree nodes!")?;
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => root = Some(node),
+            }
+        } else if token == FDT_PROP {
+            let len = read_be_u32(data, pos)? as usize;
+            let name_offset = read_be_u32(data, pos + 4)? as usize;
+            pos += 8;
+            let value = data.get(pos..pos + len).ok_or("Device tree property out of bounds!")?.to_vec();
+            pos = (pos + len + 3) & !3;
+            let (name, _) = read_cstr(strings, name_offset)?;
+            stack.last_mut().ok_or("Property outside of a node!")?.props.push(FdtProp {name, value});
+        } else if token == FDT_NOP {
+            continue
+        } else if token == FDT_END {
+            break
+        } else {
+            return Err(format!("Invalid device tree token 0x{:x} at 0x{:x}!", token, pos - 4).into());
+        }
+    }
+
+    let root = root.ok_or("Device tree has no root node!")?;
+    Ok((header, root))
+}
+
+//(address, size) pairs of the memory reservation block
+pub fn parse_mem_reserve(data: &[u8], header: &FdtHeader) -> Result<Vec<(u64, u64)>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    let mut pos = header.off_mem_rsvmap as usize;
+    loop {
+        let entry = data.get(pos..pos + 16).ok_or("Memory reservation block out of bounds!")?;
+        let address = u64::from_be_bytes(entry[0..8].try_into()?);
+        let size = u64::from_be_bytes(entry[8..16].try_into()?);
+        if address == 0 && size == 0 {break}
+        entries.push((address, size));
+        pos += 16;
+    }
+    Ok(entries)
+}
+
+//same guesses dtc makes, a list of printable strings, 32 bit cells or plain bytes
+fn format_value(value: &[u8]) -> String {
+    let is_string_list = value.last() == Some(&0)
+        && value.iter().all(|&b| b == 0 || (0x20..0x7F).contains(&b))
+        && !value.windows(2).any(|w| w == [0, 0]) && value[0] != 0;
+    if is_string_list {
+        let strings: Vec<String> = value[..value.len() - 1].split(|&b| b == 0)
+            .map(|s| format!("\"{}\"", String::from_utf8_lossy(s).replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        strings.join(", ")
+    } else if value.len().is_multiple_of(4) {
+        let cells: Vec<String> = value.chunks(4).map(|c| format!("0x{:x}", u32::from_be_bytes([c[0], c[1], c[2], c[3]]))).collect();
+        format!("<{}>", cells.join(" "))
+    } else {
+        let bytes: Vec<String> = value.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("[{}]", bytes.join(" "))
+    }
+}
+
+fn write_dts_node(node: &FdtNode, depth: usize, out: &mut String) {
+    let indent = "\t".repeat(depth);
+    let name = if depth == 0 {"/"} else {&node.name};
+    out.push_str(&format!("{}{} {{\n", indent, name));
+    for prop in &node.props {
+        if prop.value.is_empty() {
+            out.push_str(&format!("{}\t{};\n", indent, prop.name));
+        } else {
+            out.push_str(&format!("{}\t{} = {};\n", indent, prop.name, format_value(&prop.value)));
+        }
+    }
+    for child in &node.children {
+        out.push('\n');
+        write_dts_node(child, depth + 1, out);
+    }
+    out.push_str(&format!("{}}};\n", indent));
+}
+
+pub fn to_dts(data: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    let (header, root) = parse_fdt(data)?;
+    let mut out = String::from("/dts-v1/;\n\n");
+    for (address, size) in parse_mem_reserve(data, &header)? {
+        out.push_str(&format!("/memreserve/ 0x{:016x} 0x{:016x};\n", address, size));
+    }
+    if header.boot_cpuid_phys != 0 {
+        out.push_str(&format!("// boot_cpuid_phys: 0x{:x}\n", header.boot_cpuid_phys));
+    }
+    if out.len() > "/dts-v1/;\n\n".len() {
+        out.push('\n');
+    }
+    write_dts_node(&root, 0, &mut out);
+    Ok(out)
+}
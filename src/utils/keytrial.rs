@@ -0,0 +1,88 @@
+// key trial - tries many candidate keys on several threads, stopping once one matches
+// candidates are handed out in order and the earliest match wins, so the result is the same as with a plain loop
+// checks should only decrypt as much as is needed to recognize the plaintext, like the first block
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::AppContext;
+use crate::ukf::{Collection, MissingKey, part_as_arr};
+
+//below this, starting threads takes longer than trying the keys
+const MIN_PARALLEL_CANDIDATES: usize = 8;
+
+//returns the index of the first candidate that check accepts, with what check got out of it
+pub fn find_first<'c, C: Sync, T: Send>(candidates: &'c [C], check: impl Fn(&'c C) -> Option<T> + Sync) -> Option<(usize, T)> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()).min(candidates.len());
+    if threads <= 1 || candidates.len() < MIN_PARALLEL_CANDIDATES {
+        return candidates.iter().enumerate().find_map(|(i, c)| check(c).map(|result| (i, result)))
+    }
+
+    let next = AtomicUsize::new(0);
+    let earliest_match = AtomicUsize::new(usize::MAX);
+    let found: Mutex<Option<(usize, T)>> = Mutex::new(None);
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                //candidates after a match do not need to be tried
+                if i >= candidates.len() || i > earliest_match.load(Ordering::Relaxed) {
+                    break
+                }
+                if let Some(result) = check(&candidates[i]) {
+                    earliest_match.fetch_min(i, Ordering::Relaxed);
+                    let mut found = found.lock().unwrap();
+                    if found.as_ref().is_none_or(|(j, _)| i < *j) {
+                        *found = Some((i, result));
+                    }
+                }
+            });
+        }
+    });
+    found.into_inner().unwrap()
+}
+
+//find_first over a collection, check gets the key parts of each entry. returns the entry name
+pub fn find_matching_key<'k, T: Send>(collection: &'k Collection, check: impl Fn(&'k [Vec<u8>]) -> Option<T> + Sync) -> Option<(String, T)> {
+    find_first(collection, |(_, keys)| check(keys)).map(|(i, result)| (collection[i].0.clone(), result))
+}
+
+//converts every entry up front, so a malformed key is reported with its name before any trial starts
+pub fn prepare_keys<C>(collection: &Collection, convert: impl Fn(&str, &[Vec<u8>]) -> Result<C, Box<dyn std::error::Error>>) -> Result<Vec<(String, C)>, Box<dyn std::error::Error>> {
+    collection.iter().map(|(name, keys)| Ok((name.clone(), convert(name, keys)?))).collect()
+}
+
+//entry names with their converted keys
+pub type PreparedKeys<C> = Vec<(String, C)>;
+
+//like prepare_keys, but a collection that is not loaded gives None instead of an error
+pub fn prepare_optional_keys<C>(collection: Result<Collection, Box<dyn std::error::Error>>, convert: impl Fn(&str, &[Vec<u8>]) -> Result<C, Box<dyn std::error::Error>>) -> Result<Option<PreparedKeys<C>>, Box<dyn std::error::Error>> {
+    match collection {
+        Ok(collection) => Ok(Some(prepare_keys(&collection, convert)?)),
+        Err(e) if e.is::<MissingKey>() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+//AES-128 key and iv
+pub type AesPair = ([u8; 16], [u8; 16]);
+//entry name with its key and iv
+pub type NamedAesPair = (String, AesPair);
+
+fn aes_pairs(name: &str, collection: Result<Collection, Box<dyn std::error::Error>>) -> Result<Option<Vec<NamedAesPair>>, Box<dyn std::error::Error>> {
+    prepare_optional_keys(collection, |entry, keys| {
+        let owner = format!("{} \"{}\"", name, entry);
+        Ok((part_as_arr::<16>(&owner, keys, 0)?, part_as_arr::<16>(&owner, keys, 1)?))
+    })
+}
+
+//entries of (key, iv) of a collection that does not have to be loaded
+pub fn optional_aes_pairs(app_ctx: &AppContext, name: &str) -> Result<Option<Vec<NamedAesPair>>, Box<dyn std::error::Error>> {
+    aes_pairs(name, app_ctx.get_collection(name))
+}
+
+//entries of (key, iv) of a collection that does not have to be loaded, the ones that matched similar files first
+pub fn optional_hinted_aes_pairs(app_ctx: &AppContext, format: &str, fingerprint: &str, name: &str) -> Result<Option<Vec<NamedAesPair>>, Box<dyn std::error::Error>> {
+    aes_pairs(name, app_ctx.get_hinted_collection(format, fingerprint, name))
+}
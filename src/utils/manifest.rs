@@ -0,0 +1,84 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+//filesystem extractors can't reproduce symlinks, device nodes, ownership and xattrs portably, so they are recorded in a manifest file
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FileType {
+    Fifo,
+    CharDevice,
+    Directory,
+    BlockDevice,
+    Regular,
+    Symlink,
+    Socket,
+    Unknown,
+}
+impl FileType {
+    //from the S_IFMT bits of a POSIX mode
+    pub fn from_mode(mode: u32) -> Self {
+        match mode & 0xF000 {
+            0x1000 => FileType::Fifo,
+            0x2000 => FileType::CharDevice,
+            0x4000 => FileType::Directory,
+            0x6000 => FileType::BlockDevice,
+            0x8000 => FileType::Regular,
+            0xA000 => FileType::Symlink,
+            0xC000 => FileType::Socket,
+            _ => FileType::Unknown,
+        }
+    }
+    pub fn short_name(&self) -> &str {
+        match self {
+            FileType::Fifo => "fifo",
+            FileType::CharDevice => "chr",
+            FileType::Directory => "dir",
+            FileType::BlockDevice => "blk",
+            FileType::Regular => "file",
+            FileType::Symlink => "link",
+            FileType::Socket => "sock",
+            FileType::Unknown => "unk",
+        }
+    }
+}
+
+pub struct Manifest {
+    lines: Vec<String>,
+}
+impl Manifest {
+    pub fn new() -> Self {
+        Manifest {lines: Vec::new()}
+    }
+
+    //extra is appended as is, like " -> target" for symlinks
+    pub fn add_entry(&mut self, file_type: FileType, mode: u32, (uid, gid): (u32, u32), size: u64, path: &Path, extra: &str) {
+        self.lines.push(format!("{} {:04o} {}:{} {} /{}{}",
+                file_type.short_name(), mode & 0o7777, uid, gid, size, path.to_string_lossy().replace('\\', "/"), extra));
+    }
+
+    //belongs to the previously added entry
+    pub fn add_xattr(&mut self, name: &str, value: &[u8]) {
+        let trimmed = value.strip_suffix(b"\x00").unwrap_or(value);
+        let value_str = if !trimmed.is_empty() && trimmed.iter().all(|&b| (0x20..0x7F).contains(&b)) {
+            format!("\"{}\"", String::from_utf8_lossy(trimmed))
+        } else {
+            format!("0x{}", hex::encode(value))
+        };
+        self.lines.push(format!("    xattr {}={}", name, value_str));
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.lines.iter().filter(|l| !l.starts_with(' ')).count()
+    }
+
+    pub fn save(&self, output_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_dir.join("_manifest.txt"))?;
+        out_file.write_all(b"# type mode uid:gid size path\n")?;
+        for line in &self.lines {
+            writeln!(out_file, "{}", line)?;
+        }
+        println!("- Saved manifest to _manifest.txt");
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+// partitions of Panasonic MAIN (pana_dvd) and PEAKS (sddl_sec) images, described by the bootargs at their start
+// the image holds some of the listed partitions one after another, starting around the root partition
+
+use binrw::{BinReaderExt, Endian};
+use crate::utils::common;
+use std::{fs::{self, File, OpenOptions}, io::{Cursor, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}};
+
+use crate::utils::compression::decompress_zlib;
+
+pub struct Partition {
+    pub name: String,
+    pub size: Option<u64>,      //None = rest of the image
+    pub flags: Vec<String>,     //like "c" for compressed, "ro"
+}
+impl Partition {
+    pub fn is_compressed(&self) -> bool {
+        self.flags.iter().any(|f| f == "c")
+    }
+}
+
+pub struct PartitionTable {
+    pub root: Option<String>,
+    pub parts: Vec<Partition>,
+}
+
+//"512k", "16M", "1G", "0x20000" or plain bytes
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (num, mult) = match text.chars().last()? {
+        'k' | 'K' => (&text[..text.len() - 1], 1024),
+        'm' | 'M' => (&text[..text.len() - 1], 1024 * 1024),
+        'g' | 'G' => (&text[..text.len() - 1], 1024 * 1024 * 1024),
+        _ => (text, 1),
+    };
+    let value = match num.strip_prefix("0x").or(num.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => num.parse().ok()?,
+    };
+    value.checked_mul(mult)
+}
+
+//mtdparts=<mtd-id>:<size>[@<offset>][(<name>)][flags],...[;<mtd-id>:...]
+fn parse_mtdparts(value: &str, parts: &mut Vec<Partition>) -> Result<(), Box<dyn std::error::Error>> {
+    for device in value.split(';') {
+        let Some((_mtd_id, list)) = device.split_once(':') else {
+            continue
+        };
+        for def in list.split(',') {
+            let (size_str, rest) = def.split_at(def.find(['@', '(']).unwrap_or(def.len()));
+            let size = if size_str == "-" {None} else {Some(parse_size(size_str).ok_or_else(|| format!("Bad size: {}", size_str))?)};
+            let rest = match rest.strip_prefix('@') {
+                Some(rest) => &rest[rest.find('(').unwrap_or(rest.len())..],
+                None => rest,
+            };
+            let (name, flags) = match rest.strip_prefix('(').and_then(|r| r.split_once(')')) {
+                Some((name, flags)) => (name.to_string(), flags),
+                None => (format!("part{}", parts.len()), rest),
+            };
+            let flags = flags.split(['-', ' ']).filter(|f| !f.is_empty()).map(|f| f.to_string()).collect();
+            parts.push(Partition {name, size, flags});
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_bootargs(args: &str) -> Result<PartitionTable, Box<dyn std::error::Error>> {
+    let mut root: Option<String> = None;
+    let mut parts = Vec::new();
+
+    for arg in args.split_whitespace() {
+        let Some((name, value)) = arg.split_once('=') else {
+            continue
+        };
+
+        //get the root partition, strip /dev/
+        if name == "root" {
+            root = Some(value.strip_prefix("/dev/").unwrap_or(value).to_string());
+
+        } else if name == "mtdparts" {
+            parse_mtdparts(value, &mut parts)?;
+
+        //fmaX=XXXXXk[:flag] partitions
+        } else if name.starts_with("fma") {
+            let (size_str, flags) = match value.split_once(':') {
+                Some((size, flags)) => (size, flags.split(':').map(|f| f.trim().to_string()).collect()),
+                None => (value, Vec::new()),
+            };
+            let size = parse_size(size_str).ok_or_else(|| format!("Bad size: {}", size_str))?;
+            parts.push(Partition {name: name.to_string(), size: Some(size), flags});
+        }
+    }
+
+    Ok(PartitionTable {root, parts})
+}
+
+//the first partition stored in the image - the one where the stored sizes add up to the image size, closest to the one before root
+pub fn first_stored_index(table: &PartitionTable, image_size: u64) -> Result<usize, Box<dyn std::error::Error>> {
+    let root = table.root.as_ref().ok_or("Failed to get root partition!")?;
+    //root is given by name, or as mtdblockN for the N-th partition
+    let root_index = table.parts.iter().position(|p| &p.name == root)
+        .or_else(|| root.strip_prefix("mtdblock")?.parse().ok().filter(|&n: &usize| n < table.parts.len()))
+        .ok_or("Root partition not found in partition list!")?;
+
+    let fits_exactly = |start: usize| {
+        let mut total = 0;
+        for part in &table.parts[start..] {
+            let Some(size) = part.size else {
+                return true
+            };
+            total += size;
+            if total == image_size {
+                return true
+            }
+            if total > image_size {
+                return false
+            }
+        }
+        false
+    };
+    let preferred = root_index.saturating_sub(1);
+    let start = (0..=root_index).filter(|&s| fits_exactly(s)).min_by_key(|&s| s.abs_diff(preferred)).unwrap_or(preferred);
+    Ok(start)
+}
+
+//writes the partitions stored from data_offset on, until data_size is used up
+pub fn split_parts(file: &mut File, data_offset: u64, data_size: u64, table: &PartitionTable, output_folder: &Path, do_decomp: bool) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Root - {}", table.root.as_deref().unwrap_or("?"));
+    let start_index = first_stored_index(table, data_size)?;
+    file.seek(SeekFrom::Start(data_offset))?;
+
+    let mut tsize: u64 = 0;
+    for part in table.parts.iter().skip(start_index) {
+        if tsize >= data_size {
+            break
+        }
+        let part_size = part.size.unwrap_or(data_size - tsize).min(data_size - tsize);
+
+        println!("- {} - Size: {}{}", part.name, part_size, if part.flags.is_empty() {String::new()} else {format!(", Flags: {}", part.flags.join(","))});
+        tsize += part_size;
+
+        let output_path = output_folder.join(format!("{}.bin", part.name));
+        fs::create_dir_all(output_folder)?;
+
+        let data = common::read_exact(file, part_size as usize)?;
+
+        if do_decomp && part.is_compressed() {
+            println!("-- Decompressing ...");
+            decompress_part_to_file(&data, &output_path)?;
+            continue
+        }
+
+        let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(output_path)?;
+        out_file.write_all(&data)?;
+    }
+
+    Ok(())
+}
+
+//compressed parts are a table of zlib blocks
+fn decompress_part_to_file(data: &[u8], out_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = Cursor::new(data);
+    let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(out_path)?;
+
+    //detect endianness, we can safely assume uncomp size wontbe larger than 4294901760
+    let uncomp_chen = common::read_exact(&mut reader, 4)?;
+    let endianness = if uncomp_chen[0..2] == *b"\x00\x00" {
+        Endian::Little
+    } else {
+        Endian::Big
+    };
+
+    reader.seek(SeekFrom::Start(0))?;
+    let uncompressed_size: u32 = reader.read_type(endianness)?;
+    let first_blk: u32 = reader.read_type(endianness)?;
+    let count_blks = (first_blk - 4) / 4;
+
+    println!("[INFO] Endianness: {}, Uncompressed size: {}, First block loc: {}, Block count: {}",
+            endianness, uncompressed_size, first_blk, count_blks);
+
+    reader.seek(SeekFrom::Start(4))?;
+
+    let mut block_locs: Vec<u32> = Vec::new();
+
+    for _ in 0..count_blks {
+        let loc: u32 = reader.read_type(endianness)?;
+        block_locs.push(loc);
+    }
+
+    for (n, blk) in block_locs.iter().enumerate() {
+        reader.seek(SeekFrom::Start(*blk as u64))?;
+
+        print!("\rDecompressing... {}/{}",
+              n + 1, count_blks);
+        std::io::stdout().flush()?;
+
+        //the last block goes up to the end of the part
+        let compressed = if n == count_blks as usize - 1 {
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest)?;
+            rest
+        } else {
+            common::read_exact(&mut reader, (block_locs[n + 1] - blk) as usize)?
+        };
+        let decompressed = decompress_zlib(&compressed)?;
+
+        out_file.write_all(&decompressed)?;
+    }
+
+    println!();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(table: &PartitionTable) -> Vec<&str> {
+        table.parts.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sizes_with_units() {
+        assert_eq!(parse_size("512k"), Some(512 * 1024));
+        assert_eq!(parse_size("16M"), Some(16 * 1024 * 1024));
+        assert_eq!(parse_size("1G"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_size("0x20000"), Some(0x20000));
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("12x"), None);
+    }
+
+    #[test]
+    fn fma_bootargs() {
+        let table = parse_bootargs("console=ttyS0,115200 root=/dev/fma2 fma1=512k fma2=16M:c fma3=1024").unwrap();
+        assert_eq!(table.root.as_deref(), Some("fma2"));
+        assert_eq!(names(&table), ["fma1", "fma2", "fma3"]);
+        assert_eq!(table.parts[0].size, Some(512 * 1024));
+        assert_eq!(table.parts[2].size, Some(1024));
+        assert!(table.parts[1].is_compressed());
+        assert!(!table.parts[0].is_compressed());
+    }
+
+    #[test]
+    fn root_first() {
+        let table = parse_bootargs("root=/dev/fma1 fma1=1M fma2=2M").unwrap();
+        assert_eq!(first_stored_index(&table, 3 * 1024 * 1024).unwrap(), 0);
+    }
+
+    #[test]
+    fn root_anywhere() {
+        let table = parse_bootargs("fma1=512k fma2=1M root=/dev/fma3 fma3=2M").unwrap();
+        //the parts before root that add up to the image size
+        assert_eq!(first_stored_index(&table, 3 * 1024 * 1024).unwrap(), 1);
+        assert_eq!(first_stored_index(&table, 3 * 1024 * 1024 + 512 * 1024).unwrap(), 0);
+    }
+
+    #[test]
+    fn mtdparts_table() {
+        let table = parse_bootargs("root=/dev/mtdblock2 mtdparts=nand:512k(boot)ro,1M@0x80000(kernel),4M(rootfs)c,-(data)").unwrap();
+        assert_eq!(names(&table), ["boot", "kernel", "rootfs", "data"]);
+        assert_eq!(table.parts[0].flags, ["ro"]);
+        assert_eq!(table.parts[1].size, Some(1024 * 1024));
+        assert!(table.parts[2].is_compressed());
+        assert_eq!(table.parts[3].size, None);
+    }
+
+    #[test]
+    fn mtdblock_root() {
+        let table = parse_bootargs("root=/dev/mtdblock2 mtdparts=nand:512k(boot),1M(kernel),4M(rootfs)c,-(data)").unwrap();
+        assert_eq!(first_stored_index(&table, 8 * 1024 * 1024).unwrap(), 1);
+
+        let table = parse_bootargs("root=/dev/mtdblock9 mtdparts=nand:512k(boot),1M(kernel)").unwrap();
+        assert!(first_stored_index(&table, 1024 * 1024).is_err());
+    }
+
+    #[test]
+    fn unnamed_mtdparts() {
+        let table = parse_bootargs("mtdparts=nand:512k,1M root=/dev/mtdblock1").unwrap();
+        assert_eq!(names(&table), ["part0", "part1"]);
+        assert_eq!(table.root.as_deref(), Some("mtdblock1"));
+    }
+}
@@ -0,0 +1,24 @@
+use rsa::{RsaPublicKey, BigUint};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+static SHA1_DIGEST_INFO: &[u8] = &[0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14];
+static SHA256_DIGEST_INFO: &[u8] = &[0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20];
+
+//strips the 00 01 FF.. 00 padding of a PKCS#1 v1.5 signature, the leading zero is already gone from the number
+fn pkcs1_digest_info(em: &[u8]) -> Option<&[u8]> {
+    let rest = em.strip_prefix(&[0x01])?;
+    let end = rest.iter().position(|&b| b != 0xFF)?;
+    (end >= 8 && rest[end] == 0x00).then(|| &rest[end + 1..])
+}
+
+//PKCS#1 v1.5 RSA signature of content with SHA-1 or SHA-256, the public exponent is always 65537
+pub fn verify_pkcs1(modulus: &[u8], signature: &[u8], content: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
+    let pubkey = RsaPublicKey::new(BigUint::from_bytes_be(modulus), BigUint::from_bytes_be(b"\x01\x00\x01"))?;
+    let em = rsa::hazmat::rsa_encrypt(&pubkey, &BigUint::from_bytes_be(signature))?.to_bytes_be();
+
+    Ok(pkcs1_digest_info(&em).is_some_and(|digest_info| {
+        digest_info == [SHA1_DIGEST_INFO, <Sha1 as sha1::Digest>::digest(content).as_slice()].concat() ||
+        digest_info == [SHA256_DIGEST_INFO, Sha256::digest(content).as_slice()].concat()
+    }))
+}